@@ -0,0 +1,511 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+use tracing::debug;
+
+use crate::{
+    constant::{BUNDLE_DIR, REGEX_CACHE_FILE},
+    error::{Error, Fallible},
+    internal,
+    package::{
+        download::PackageSet, manifest::Manifest, query, resolve, HashMismatchContext, HashString,
+        Package,
+    },
+    Event, Session,
+};
+
+/// Name of the lock file in a bundle.
+const LOCK_FILE: &str = "bundle.json";
+
+/// Lock of packages contained in an offline bundle.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BundleLock {
+    packages: Vec<BundlePackage>,
+}
+
+/// A package locked in an offline bundle.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BundlePackage {
+    name: String,
+    bucket: String,
+    version: String,
+    files: Vec<BundleFile>,
+}
+
+/// A cache file locked in an offline bundle.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BundleFile {
+    filename: String,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+}
+
+impl BundleLock {
+    /// Get packages of this bundle.
+    #[inline]
+    pub fn packages(&self) -> &[BundlePackage] {
+        &self.packages
+    }
+}
+
+impl BundlePackage {
+    /// Get the name of this package.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the bucket the package was bundled from.
+    #[inline]
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    /// Get the version of this package.
+    #[inline]
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Get the path of the manifest of this package in the bundle archive.
+    ///
+    /// Manifests are keyed by bucket and name, as packages of the same name
+    /// may come from different buckets.
+    fn archive_path(&self) -> String {
+        format!("manifests/{}/{}.json", self.bucket, self.name)
+    }
+}
+
+/// Create an offline bundle of the given packages and their dependencies.
+pub(crate) fn create(session: &Session, queries: &[&str], path: &Path) -> Fallible<BundleLock> {
+    let synced = query::query_synced(session, &["*"], &[])?;
+    let mut packages = vec![];
+
+    for &query in queries {
+        let (query_bucket, query_name) = query.split_once('/').unwrap_or(("", query));
+        let mut matched = synced
+            .iter()
+            .filter(|&p| {
+                let bucket_matched = query_bucket.is_empty() || p.bucket() == query_bucket;
+                bucket_matched && p.name() == query_name
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if matched.is_empty() {
+            return Err(Error::PackageNotFound(query.to_owned()));
+        }
+
+        if matched.len() > 1 {
+            resolve::select_candidate(session, &mut matched)?;
+        }
+
+        let p = matched.pop().unwrap();
+        if !packages.contains(&p) {
+            packages.push(p);
+        }
+    }
+
+    resolve::resolve_dependencies(session, &mut packages)?;
+
+    // make sure all files are cached and valid
+    let refs = packages.iter().collect::<Vec<_>>();
+    let mut set = PackageSet::new(session, &refs, true, true)?;
+    let size = set.calculate_download_size()?;
+    if size.total > 0 || size.estimated {
+        if let Some(tx) = session.emitter() {
+            let _ = tx.send(Event::PackageDownloadStart);
+        }
+
+        set.download()?;
+
+        if let Some(tx) = session.emitter() {
+            let _ = tx.send(Event::PackageDownloadDone);
+        }
+    }
+
+    let config = session.config();
+    let cache_root = config.cache_path();
+    let mut lock = BundleLock { packages: vec![] };
+
+    for pkg in packages.iter() {
+        let nightly = pkg.version() == "nightly";
+        let urls = pkg.download_urls();
+        let hashes = pkg.download_hashes();
+        let mut files = vec![];
+
+        for (idx, filename) in pkg.download_filenames().into_iter().enumerate() {
+            let hash = hashes.get(idx).filter(|_| !nightly);
+            if let Some(&hash) = hash {
+                if !set.is_verified(&filename) {
+                    verify_file(pkg.name(), urls[idx], &cache_root.join(&filename), hash)?;
+                }
+            }
+
+            files.push(BundleFile {
+                filename,
+                url: urls[idx].to_owned(),
+                hash: hash.map(|h| h.to_string()),
+            });
+        }
+
+        lock.packages.push(BundlePackage {
+            name: pkg.name().to_owned(),
+            bucket: pkg.bucket().to_owned(),
+            version: pkg.version().to_owned(),
+            files,
+        });
+    }
+
+    let mut builder = tar::Builder::new(File::create(path)?);
+
+    let data = serde_json::to_vec_pretty(&lock)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp() as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, LOCK_FILE, data.as_slice())?;
+
+    for (pkg, locked) in packages.iter().zip(lock.packages.iter()) {
+        let manifest_path = pkg.manifest().path();
+        builder.append_path_with_name(manifest_path, locked.archive_path())?;
+
+        for file in locked.files.iter() {
+            debug!("bundling {}", file.filename);
+            let name = format!("cache/{}", file.filename);
+            builder.append_path_with_name(cache_root.join(&file.filename), name)?;
+        }
+    }
+
+    builder.into_inner()?.sync_all()?;
+
+    Ok(lock)
+}
+
+/// Import an offline bundle into the cache.
+///
+/// Cache files are verified against the lock before being imported, and
+/// manifests are imported into the [`BUNDLE_DIR`] so that the bundled
+/// versions can be installed regardless of the synced buckets.
+///
+/// # Returns
+///
+/// The lock of the bundle, along with the bundled packages. Packages keep the
+/// buckets they were bundled from.
+pub(crate) fn import(session: &Session, path: &Path) -> Fallible<(BundleLock, Vec<Package>)> {
+    let invalid = || Error::InvalidBundle {
+        path: path.to_owned(),
+    };
+
+    let config = session.config();
+    let cache_root = config.cache_path();
+    let manifest_root = config.root_path().join(BUNDLE_DIR);
+
+    internal::fs::ensure_dir(cache_root)?;
+
+    let mut archive = tar::Archive::new(File::open(path)?);
+    let mut entries = archive.entries()?;
+
+    // the lock file always comes first
+    let lock: BundleLock = match entries.next() {
+        Some(entry) => {
+            let mut entry = entry?;
+            if entry.path()?.as_ref() != Path::new(LOCK_FILE) {
+                return Err(invalid());
+            }
+
+            let mut data = vec![];
+            entry.read_to_end(&mut data)?;
+            serde_json::from_slice(&data).map_err(|_| invalid())?
+        }
+        None => return Err(invalid()),
+    };
+
+    // bucket and package names must not escape the manifest root, nor
+    // filenames the cache root
+    let is_plain = |name: &str| {
+        let mut components = Path::new(name).components();
+        matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none()
+    };
+    let is_valid_file = |file: &BundleFile, nightly: bool| {
+        let hashed = file.hash.is_some() || nightly;
+        hashed && is_plain(&file.filename) && REGEX_CACHE_FILE.is_match(&file.filename)
+    };
+    if !lock.packages.iter().all(|p| {
+        let nightly = p.version == "nightly";
+        is_plain(&p.bucket)
+            && is_plain(&p.name)
+            && p.files.iter().all(|f| is_valid_file(f, nightly))
+    }) {
+        return Err(invalid());
+    }
+
+    let manifest_path = |pkg: &BundlePackage| -> PathBuf {
+        manifest_root
+            .join(&pkg.bucket)
+            .join(format!("{}.json", pkg.name))
+    };
+
+    for entry in entries {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.to_string_lossy().into_owned();
+
+        if entry_path.starts_with("manifests/") {
+            let pkg = lock
+                .packages
+                .iter()
+                .find(|p| p.archive_path() == entry_path)
+                .ok_or_else(invalid)?;
+
+            let path = manifest_path(pkg);
+            internal::fs::ensure_dir(path.parent().unwrap())?;
+            entry.unpack(path)?;
+        } else if let Some(filename) = entry_path.strip_prefix("cache/") {
+            let (pkg, file) = lock
+                .packages
+                .iter()
+                .find_map(|p| {
+                    p.files
+                        .iter()
+                        .find(|f| f.filename == filename)
+                        .map(|f| (p, f))
+                })
+                .ok_or_else(invalid)?;
+
+            // unpacked to `cache/` of the cache root, out of the way of
+            // the cache files until verified
+            if !entry.unpack_in(cache_root)? {
+                return Err(invalid());
+            }
+            let tmp = cache_root.join(&entry_path);
+
+            if let Some(hash) = file.hash.as_deref() {
+                let hash = HashString::new(hash)?;
+                if let Err(err) = verify_file(&pkg.name, &file.url, &tmp, &hash) {
+                    let _ = std::fs::remove_file(&tmp);
+                    let _ = std::fs::remove_dir(tmp.parent().unwrap());
+                    return Err(err);
+                }
+            }
+
+            std::fs::rename(&tmp, cache_root.join(filename))?;
+            let _ = std::fs::remove_dir(tmp.parent().unwrap());
+        } else {
+            return Err(invalid());
+        }
+    }
+
+    let apps_dir = config.root_path().join("apps");
+    let mut packages = vec![];
+    for pkg in lock.packages.iter() {
+        let manifest = Manifest::parse(manifest_path(pkg)).map_err(|_| invalid())?;
+        let package = Package::from(&pkg.name, &pkg.bucket, manifest);
+        query::fill_install_state(&apps_dir, &package);
+        packages.push(package);
+    }
+
+    Ok((lock, packages))
+}
+
+/// Verify the file at `path` against the given `hash`.
+fn verify_file(name: &str, url: &str, path: &Path, hash: &HashString) -> Fallible<()> {
+    let actual = crate::package::file_checksum(path, hash)?;
+    let expected = hash.value();
+
+    if actual != expected {
+        let ctx =
+            HashMismatchContext::new(name.to_owned(), url.to_owned(), expected.to_owned(), actual);
+        return Err(Error::HashMismatch(ctx));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{http::test::Server, operation, session::test, SyncOption};
+
+    /// Add package `name` to `bucket`, downloading `content` from `url`.
+    fn add_package(session: &Session, bucket: &str, name: &str, url: &str, content: &str) {
+        let mut checksum = scoop_hash::ChecksumBuilder::new().sha256().build();
+        checksum.consume(content.as_bytes());
+        let mut json = serde_json::json!({
+            "version": "1.0",
+            "homepage": "https://example.com",
+            "license": "MIT",
+            "url": url,
+            "hash": checksum.finalize(),
+        });
+        if name == "foo" {
+            json["depends"] = serde_json::json!("extras/bar");
+        }
+        test::add_manifest(session, bucket, name, &json);
+    }
+
+    #[test]
+    fn test_bundle() {
+        let (foo, bar) = ("https://example.com/foo.zip", "https://example.com/bar.zip");
+        let server = Server::default().serve(foo, b"foo").serve(bar, b"bar");
+        let session = test::temp_session("bundle-create", serde_json::json!({}));
+        session.set_http_client(server).unwrap();
+        add_package(&session, "main", "foo", foo, "foo");
+        add_package(&session, "extras", "bar", bar, "bar");
+        // a package of the same name in another bucket
+        add_package(&session, "main", "bar", "https://example.com/other.zip", "");
+
+        let path = session.config().root_path().join("bundle.tar");
+        let lock = create(&session, &["foo"], &path).unwrap();
+        let mut locked = lock
+            .packages()
+            .iter()
+            .map(|p| format!("{}/{}@{}", p.bucket(), p.name(), p.version()))
+            .collect::<Vec<_>>();
+        locked.sort();
+        assert_eq!(locked, ["extras/bar@1.0", "main/foo@1.0"]);
+
+        let mut archive = tar::Archive::new(File::open(&path).unwrap());
+        let entries = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(entries[0], LOCK_FILE);
+        assert!(entries.contains(&"manifests/main/foo.json".to_owned()));
+        assert!(entries.contains(&"manifests/extras/bar.json".to_owned()));
+        assert_eq!(entries.len(), 5);
+
+        // import into another root without any bucket or network access
+        let other = test::temp_session("bundle-import", serde_json::json!({}));
+        other.set_http_client(Server::default()).unwrap();
+        let (imported, packages) = import(&other, &path).unwrap();
+        assert_eq!(imported.packages().len(), 2);
+        let mut idents = packages.iter().map(|p| p.ident()).collect::<Vec<_>>();
+        idents.sort();
+        assert_eq!(idents, ["extras/bar", "main/foo"]);
+
+        let config = other.config();
+        for pkg in packages.iter() {
+            let filename = &pkg.download_filenames()[0];
+            let data = std::fs::read(config.cache_path().join(filename)).unwrap();
+            assert_eq!(data, pkg.name().as_bytes());
+        }
+        drop(config);
+
+        let options = vec![SyncOption::AssumeYes];
+        operation::bundle_install(&other, &path, options).unwrap();
+    }
+
+    #[test]
+    fn test_bundle_invalid() {
+        let session = test::temp_session("bundle-invalid", serde_json::json!({}));
+        let root = session.config().root_path().to_owned();
+        let write = |name: &str, entries: &[(&str, &[u8])]| {
+            let path = root.join(name);
+            let mut builder = tar::Builder::new(File::create(&path).unwrap());
+            for &(name, data) in entries {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                builder.append_data(&mut header, name, data).unwrap();
+            }
+            builder.into_inner().unwrap();
+            path
+        };
+        let is_invalid =
+            |path: PathBuf| matches!(import(&session, &path), Err(Error::InvalidBundle { .. }));
+
+        // the lock must come first
+        let path = write("no-lock.tar", &[("cache/foo#1.0#abcdef0.zip", b"foo")]);
+        assert!(is_invalid(path));
+
+        // names of the lock must not escape the manifest root
+        let lock = br#"{"packages":[{"name":"foo","bucket":"..","version":"1.0","files":[]}]}"#;
+        let path = write("escape.tar", &[(LOCK_FILE, lock)]);
+        assert!(is_invalid(path));
+
+        // filenames of the lock must not escape the cache root
+        let hash = "sha256:".to_owned() + &"0".repeat(64);
+        for filename in [
+            "../foo#1.0#abcdef0.zip",
+            "/tmp/foo#1.0#abcdef0.zip",
+            "foo.zip",
+        ] {
+            let lock = serde_json::json!({"packages": [{
+                "name": "foo", "bucket": "main", "version": "1.0",
+                "files": [{"filename": filename, "url": "https://example.com/foo.zip", "hash": hash}],
+            }]});
+            let path = write(
+                "escape-cache.tar",
+                &[(LOCK_FILE, lock.to_string().as_bytes())],
+            );
+            assert!(is_invalid(path), "{}", filename);
+        }
+
+        // locked files must have a hash, unless the package is nightly
+        let file = serde_json::json!({
+            "filename": "foo#nightly#abcdef0.zip",
+            "url": "https://example.com/foo.zip",
+        });
+        let lock = serde_json::json!({"packages": [{
+            "name": "foo", "bucket": "main", "version": "1.0", "files": [file],
+        }]});
+        let path = write("no-hash.tar", &[(LOCK_FILE, lock.to_string().as_bytes())]);
+        assert!(is_invalid(path));
+        let lock = serde_json::json!({"packages": [{
+            "name": "foo", "bucket": "main", "version": "nightly", "files": [file],
+        }]});
+        let manifest = serde_json::json!({
+            "version": "nightly",
+            "homepage": "https://example.com",
+            "license": "MIT",
+            "url": "https://example.com/foo.zip",
+        });
+        let path = write(
+            "nightly.tar",
+            &[
+                (LOCK_FILE, lock.to_string().as_bytes()),
+                ("manifests/main/foo.json", manifest.to_string().as_bytes()),
+                ("cache/foo#nightly#abcdef0.zip", b"foo"),
+            ],
+        );
+        import(&session, &path).unwrap();
+        let cache_root = session.config().cache_path().to_owned();
+        assert!(cache_root.join("foo#nightly#abcdef0.zip").exists());
+        assert!(!cache_root.join("cache").exists());
+
+        // entries must be locked
+        let lock = br#"{"packages":[{"name":"foo","bucket":"main","version":"1.0","files":[]}]}"#;
+        let manifest = b"{}" as &[u8];
+        let path = write(
+            "unlocked.tar",
+            &[(LOCK_FILE, lock), ("manifests/extras/foo.json", manifest)],
+        );
+        assert!(is_invalid(path));
+
+        // bundled files are verified
+        let lock = serde_json::json!({"packages": [{
+            "name": "foo", "bucket": "main", "version": "1.0",
+            "files": [{"filename": "foo#1.0#abcdef0.zip", "url": "https://example.com/foo.zip", "hash": hash}],
+        }]});
+        let lock = lock.to_string();
+        let path = write(
+            "corrupt.tar",
+            &[
+                (LOCK_FILE, lock.as_bytes()),
+                ("cache/foo#1.0#abcdef0.zip", b"foo"),
+            ],
+        );
+        assert!(matches!(
+            import(&session, &path),
+            Err(Error::HashMismatch(_))
+        ));
+        assert!(!cache_root.join("foo#1.0#abcdef0.zip").exists());
+        assert!(!cache_root.join("cache").exists());
+    }
+}
@@ -3,6 +3,24 @@ use std::path::{Path, PathBuf};
 use crate::constant::REGEX_CACHE_FILE;
 use crate::error::{Error, Fallible};
 
+/// Verification state of a cache file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CacheState {
+    /// The cache file matches its manifest.
+    Valid,
+
+    /// The cache file is empty, or its hash does not match its manifest.
+    Corrupt,
+
+    /// No manifest of the package could be found.
+    Orphaned,
+
+    /// The cache file does not match the current version or urls of the
+    /// manifest.
+    Outdated,
+}
+
 /// Scoop cache file representation
 #[derive(Clone, Debug)]
 pub struct CacheFile {
@@ -41,4 +59,14 @@ impl CacheFile {
     pub fn version(&self) -> &str {
         self.file_name().splitn(3, '#').collect::<Vec<_>>()[1]
     }
+
+    /// Get the url hash part of this cache file
+    ///
+    /// The url hash is the first 7 characters of the SHA-256 hash of the
+    /// download url, used to distinguish files of the same package version.
+    #[inline]
+    pub fn url_hash(&self) -> &str {
+        let part = self.file_name().splitn(3, '#').collect::<Vec<_>>()[2];
+        part.split_once('.').map(|s| s.0).unwrap_or(part)
+    }
 }
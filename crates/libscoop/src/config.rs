@@ -1,8 +1,11 @@
+use once_cell::sync::OnceCell;
+use regex::Regex;
 use std::collections::HashMap;
 use std::io::Read;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 
 use crate::error::{Error, Fallible};
 use crate::internal;
@@ -96,6 +99,22 @@ pub struct ConfigInner {
     #[serde(skip_serializing_if = "Option::is_none")]
     aria2_warning_enabled: Option<bool>,
 
+    /// Whether to prune the cache after a successful sync.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_auto_prune: Option<bool>,
+
+    /// Number of newest versions of each package to keep in the cache.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_keep_versions: Option<u32>,
+
+    /// Maximum age in days of cache files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_max_age: Option<u64>,
+
+    /// Size budget of the cache, e.g. `10G`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_max_size: Option<String>,
+
     #[serde(alias = "cachePath")]
     #[serde(default = "default::cache_path")]
     #[serde(skip_serializing_if = "default::is_default_cache_path")]
@@ -110,6 +129,37 @@ pub struct ConfigInner {
     #[serde(skip_serializing_if = "Option::is_none")]
     debug: Option<bool>,
 
+    /// Timeout in seconds for establishing a download connection.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    download_connect_timeout: Option<u64>,
+
+    /// Maximum number of simultaneously open download connections.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    download_max_connections: Option<u32>,
+
+    /// Overall download rate limit per second, e.g. `512K` or `2M`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    download_max_rate: Option<String>,
+
+    /// Maximum number of simultaneously open download connections to a single
+    /// host.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    download_max_host_connections: Option<u32>,
+
+    /// Number of times a failed file download is retried.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    download_retries: Option<u32>,
+
+    /// Initial wait in seconds before retrying a failed file download, the
+    /// wait is doubled on every further retry, up to 60 seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    download_retry_wait: Option<u64>,
+
+    /// Seconds without receiving any data after which a download is considered
+    /// stalled and aborted. `0` disables the check.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    download_stall_timeout: Option<u64>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     force_update: Option<bool>,
 
@@ -128,6 +178,15 @@ pub struct ConfigInner {
     #[serde(skip_serializing_if = "Option::is_none")]
     last_update: Option<String>,
 
+    /// URL prefix rewrites for package downloads.
+    ///
+    /// Each key is a URL prefix to be replaced with its value, e.g.
+    /// `{"https://github.com/": "https://mirror.example.com/github/"}`.
+    /// Downloads are tried with the rewritten URL first, and fall back to the
+    /// original URL on failure.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mirrors: Option<HashMap<String, String>>,
+
     #[serde(alias = "manifest_review")]
     #[serde(skip_serializing_if = "Option::is_none")]
     show_manifest: Option<bool>,
@@ -172,6 +231,12 @@ pub struct ConfigInner {
     #[serde(skip_serializing_if = "Option::is_none")]
     private_hosts: Option<Vec<PrivateHosts>>,
 
+    /// Proxy of network connections, `default` or `currentuser@default` for
+    /// the proxy of the environment, or `[user:pass@]host:port`.
+    ///
+    /// Unlike Scoop, `default` does not read the Windows system proxy settings
+    /// but the `HTTP(S)_PROXY` environment variables. `none` is the same as
+    /// leaving the config unset.
     #[serde(skip_serializing_if = "Option::is_none")]
     proxy: Option<String>,
 
@@ -205,6 +270,44 @@ pub struct PrivateHosts {
 
     /// A string defining HTTP headers.
     headers: String,
+
+    /// Compiled regex of the `match` string, `None` if it is not a valid
+    /// regex.
+    #[serde(skip)]
+    regex: OnceCell<Option<Regex>>,
+}
+
+impl PrivateHosts {
+    /// Check if the given `url` matches this private host.
+    ///
+    /// The `match` string is treated as a regular expression as Scoop does,
+    /// it falls back to a plain substring match if it is not a valid regex.
+    pub fn is_match(&self, url: &str) -> bool {
+        let regex = self.regex.get_or_init(|| Regex::new(&self.match_).ok());
+        match regex {
+            Some(re) => re.is_match(url),
+            None => url.contains(&self.match_),
+        }
+    }
+
+    /// Get the HTTP headers of this private host.
+    ///
+    /// Headers are separated by `;`, name and value of a header can be
+    /// separated by either `=` (Scoop style) or `:` (HTTP style), e.g.
+    /// `Authorization=token abc; X-Foo: bar`.
+    pub fn headers(&self) -> Vec<(&str, &str)> {
+        self.headers
+            .split(';')
+            .filter_map(|h| {
+                let idx = h.find([':', '='])?;
+                let (name, value) = (h[..idx].trim(), h[idx + 1..].trim());
+                match name.is_empty() {
+                    true => None,
+                    false => Some((name, value)),
+                }
+            })
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -255,6 +358,43 @@ impl Config {
         self.cache_path.as_path()
     }
 
+    /// Get the `cache_auto_prune` config. Defaults to `false`.
+    #[inline]
+    pub fn cache_auto_prune(&self) -> bool {
+        self.cache_auto_prune.unwrap_or_default()
+    }
+
+    /// Get the `cache_keep_versions` config.
+    ///
+    /// # Returns
+    ///
+    /// The number of versions to keep per package, or `None` if unlimited.
+    #[inline]
+    pub fn cache_keep_versions(&self) -> Option<u32> {
+        self.cache_keep_versions.filter(|&n| n > 0)
+    }
+
+    /// Get the `cache_max_age` config.
+    ///
+    /// # Returns
+    ///
+    /// The maximum age of cache files, or `None` if unlimited.
+    #[inline]
+    pub fn cache_max_age(&self) -> Option<Duration> {
+        self.cache_max_age
+            .filter(|&days| days > 0)
+            .map(|days| Duration::from_secs(days.saturating_mul(86_400)))
+    }
+
+    /// Get the `cache_max_size` config in bytes.
+    ///
+    /// # Returns
+    ///
+    /// The size budget of the cache, or `None` if unlimited.
+    pub fn cache_max_size(&self) -> Option<u64> {
+        self.cache_max_size.as_deref().and_then(parse_size)
+    }
+
     /// Get the root directory of Scoop.
     ///
     /// This is the root directory of a Scoop installation, by default the value
@@ -271,10 +411,45 @@ impl Config {
         self.no_junction.unwrap_or_default()
     }
 
-    /// Get the `proxy` config.
+    /// Get the `proxy` config, `none` is normalized to `None`.
     #[inline]
     pub fn proxy(&self) -> Option<&str> {
-        self.proxy.as_deref()
+        self.proxy
+            .as_deref()
+            .filter(|s| !s.trim().is_empty() && !s.trim().eq_ignore_ascii_case("none"))
+    }
+
+    /// Get the `gh_token` config.
+    ///
+    /// The `SCOOP_GH_TOKEN` environment variable takes precedence over the
+    /// config as in Scoop, empty values of either are ignored.
+    pub fn gh_token(&self) -> Option<String> {
+        std::env::var("SCOOP_GH_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty())
+            .or_else(|| self.gh_token.clone().filter(|t| !t.is_empty()))
+    }
+
+    /// Get the `private_hosts` config.
+    #[inline]
+    pub fn private_hosts(&self) -> &[PrivateHosts] {
+        self.private_hosts.as_deref().unwrap_or_default()
+    }
+
+    /// Get the mirror URL of the given `url` according to the `mirrors` config.
+    ///
+    /// The longest matching prefix wins.
+    ///
+    /// # Returns
+    ///
+    /// The rewritten URL, or `None` if no mirror is configured for the url.
+    pub fn mirror_url(&self, url: &str) -> Option<String> {
+        self.mirrors
+            .as_ref()?
+            .iter()
+            .filter(|(prefix, _)| !prefix.is_empty() && url.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, mirror)| format!("{}{}", mirror, &url[prefix.len()..]))
     }
 
     /// Get the `cat_style` config.
@@ -283,6 +458,90 @@ impl Config {
         self.cat_style.as_deref().unwrap_or_default()
     }
 
+    /// Get the `aria2-enabled` config.
+    ///
+    /// aria2 itself is not used, the config is respected to enable segmented
+    /// (multi-connection) downloads of large files.
+    #[inline]
+    pub fn aria2_enabled(&self) -> bool {
+        self.aria2_enabled.unwrap_or_default()
+    }
+
+    /// Get the `aria2-split` config, the number of connections used to
+    /// download a single file. Defaults to `5`.
+    #[inline]
+    pub fn aria2_split(&self) -> u32 {
+        self.aria2_split.unwrap_or(5).max(1)
+    }
+
+    /// Get the `aria2-max-connection-per-server` config. Defaults to `5`.
+    #[inline]
+    pub fn aria2_max_connection_per_server(&self) -> u32 {
+        self.aria2_max_connection_per_server.unwrap_or(5).max(1)
+    }
+
+    /// Get the `aria2-min-split-size` config in bytes. Defaults to `5M`.
+    ///
+    /// A file is not split into segments smaller than this size.
+    pub fn aria2_min_split_size(&self) -> u64 {
+        self.aria2_min_split_size
+            .as_deref()
+            .and_then(parse_size)
+            .unwrap_or(5 * 1024 * 1024)
+    }
+
+    /// Get the `download_max_connections` config. Defaults to `6`.
+    #[inline]
+    pub fn download_max_connections(&self) -> u32 {
+        self.download_max_connections.unwrap_or(6).max(1)
+    }
+
+    /// Get the `download_max_rate` config in bytes per second.
+    ///
+    /// # Returns
+    ///
+    /// The rate limit, or `None` if downloads are not limited.
+    pub fn download_max_rate(&self) -> Option<u64> {
+        self.download_max_rate.as_deref().and_then(parse_size)
+    }
+
+    /// Get the `download_max_host_connections` config. Defaults to `4`.
+    #[inline]
+    pub fn download_max_host_connections(&self) -> u32 {
+        self.download_max_host_connections.unwrap_or(4).max(1)
+    }
+
+    /// Get the `download_retries` config. Defaults to `3`.
+    #[inline]
+    pub fn download_retries(&self) -> u32 {
+        self.download_retries.unwrap_or(3)
+    }
+
+    /// Get the `download_retry_wait` config. Defaults to 1 second.
+    #[inline]
+    pub fn download_retry_wait(&self) -> Duration {
+        Duration::from_secs(self.download_retry_wait.unwrap_or(1))
+    }
+
+    /// Get the `download_connect_timeout` config. Defaults to 30 seconds.
+    #[inline]
+    pub fn download_connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.download_connect_timeout.unwrap_or(30))
+    }
+
+    /// Get the `download_stall_timeout` config. Defaults to 60 seconds.
+    ///
+    /// # Returns
+    ///
+    /// The stall timeout, or `None` if the stall check is disabled.
+    #[inline]
+    pub fn download_stall_timeout(&self) -> Option<Duration> {
+        match self.download_stall_timeout.unwrap_or(60) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
     /// Get the `use_isoloated_path` config.
     #[inline]
     pub fn use_isolated_path(&self) -> Option<&IsolatedPath> {
@@ -307,6 +566,105 @@ impl Config {
                     Err(_) => return Err(Error::ConfigValueInvalid(value.to_owned())),
                 },
             },
+            "aria2_split" | "aria2-split" => match is_unset {
+                true => self.inner.aria2_split = None,
+                false => match value.parse::<u32>() {
+                    Ok(value) => self.inner.aria2_split = Some(value),
+                    Err(_) => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
+            "aria2_max_connection_per_server" | "aria2-max-connection-per-server" => match is_unset
+            {
+                true => self.inner.aria2_max_connection_per_server = None,
+                false => match value.parse::<u32>() {
+                    Ok(value) => self.inner.aria2_max_connection_per_server = Some(value),
+                    Err(_) => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
+            "aria2_min_split_size" | "aria2-min-split-size" => match is_unset {
+                true => self.inner.aria2_min_split_size = None,
+                false => match parse_size(value) {
+                    Some(_) => self.inner.aria2_min_split_size = Some(value.to_owned()),
+                    None => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
+            "download_max_connections" => match is_unset {
+                true => self.inner.download_max_connections = None,
+                false => match value.parse::<u32>() {
+                    Ok(value) => self.inner.download_max_connections = Some(value),
+                    Err(_) => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
+            "download_max_rate" => match is_unset {
+                true => self.inner.download_max_rate = None,
+                false => match parse_size(value) {
+                    Some(_) => self.inner.download_max_rate = Some(value.to_owned()),
+                    None => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
+            "download_max_host_connections" => match is_unset {
+                true => self.inner.download_max_host_connections = None,
+                false => match value.parse::<u32>() {
+                    Ok(value) => self.inner.download_max_host_connections = Some(value),
+                    Err(_) => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
+            "download_retries" => match is_unset {
+                true => self.inner.download_retries = None,
+                false => match value.parse::<u32>() {
+                    Ok(value) => self.inner.download_retries = Some(value),
+                    Err(_) => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
+            "download_retry_wait" => match is_unset {
+                true => self.inner.download_retry_wait = None,
+                false => match value.parse::<u64>() {
+                    Ok(value) => self.inner.download_retry_wait = Some(value),
+                    Err(_) => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
+            "download_connect_timeout" => match is_unset {
+                true => self.inner.download_connect_timeout = None,
+                false => match value.parse::<u64>() {
+                    Ok(value) => self.inner.download_connect_timeout = Some(value),
+                    Err(_) => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
+            "download_stall_timeout" => match is_unset {
+                true => self.inner.download_stall_timeout = None,
+                false => match value.parse::<u64>() {
+                    Ok(value) => self.inner.download_stall_timeout = Some(value),
+                    Err(_) => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
+            "cache_auto_prune" => match is_unset {
+                true => self.inner.cache_auto_prune = None,
+                false => match value.parse::<bool>() {
+                    Ok(value) => self.inner.cache_auto_prune = Some(value),
+                    Err(_) => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
+            "cache_keep_versions" => match is_unset {
+                true => self.inner.cache_keep_versions = None,
+                false => match value.parse::<u32>() {
+                    Ok(value) => self.inner.cache_keep_versions = Some(value),
+                    Err(_) => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
+            "cache_max_age" => match is_unset {
+                true => self.inner.cache_max_age = None,
+                false => match value.parse::<u64>() {
+                    Ok(value) => self.inner.cache_max_age = Some(value),
+                    Err(_) => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
+            "cache_max_size" => match is_unset {
+                true => self.inner.cache_max_size = None,
+                false => match parse_size(value) {
+                    Some(_) => self.inner.cache_max_size = Some(value.to_owned()),
+                    None => return Err(Error::ConfigValueInvalid(value.to_owned())),
+                },
+            },
             "cat_style" => {
                 self.inner.cat_style = match is_unset {
                     true => None,
@@ -379,16 +737,28 @@ impl Default for Config {
             aria2_split: Default::default(),
             aria2_warning_enabled: Default::default(),
             // default_cache_path: default::cache_path(),
+            cache_auto_prune: Default::default(),
+            cache_keep_versions: Default::default(),
+            cache_max_age: Default::default(),
+            cache_max_size: Default::default(),
             cache_path: default::cache_path(),
             cat_style: Default::default(),
             deafult_architecture: Default::default(),
             debug: Default::default(),
+            download_connect_timeout: Default::default(),
+            download_max_connections: Default::default(),
+            download_max_rate: Default::default(),
+            download_max_host_connections: Default::default(),
+            download_retries: Default::default(),
+            download_retry_wait: Default::default(),
+            download_stall_timeout: Default::default(),
             force_update: Default::default(),
             gh_token: Default::default(),
             // default_global_path: default::global_path(),
             global_path: default::global_path(),
             ignore_running_processes: Default::default(),
             last_update: Default::default(),
+            mirrors: Default::default(),
             show_manifest: Default::default(),
             use_isolated_path: Default::default(),
             use_lessmsi: Default::default(),
@@ -419,6 +789,26 @@ impl Deref for Config {
     }
 }
 
+/// Parse a size string in the aria2 form, e.g. `1048576`, `1024K`, `5M` or `1G`.
+///
+/// # Returns
+///
+/// The size in bytes, or `None` if the string is invalid, zero, or overflows.
+pub(crate) fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (num, unit) = match s.char_indices().last()? {
+        (idx, 'k' | 'K') => (&s[..idx], 1024),
+        (idx, 'm' | 'M') => (&s[..idx], 1024 * 1024),
+        (idx, 'g' | 'G') => (&s[..idx], 1024 * 1024 * 1024),
+        _ => (s, 1),
+    };
+
+    num.parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .filter(|&n| n > 0)
+}
+
 /// Get a list of possible config paths.
 ///
 /// There are 3 possible locations for the `config.json` file:
@@ -563,3 +953,75 @@ mod default {
         is_default(global_path().as_path(), path.as_ref())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1048576"), Some(1048576));
+        assert_eq!(parse_size(" 1024K "), Some(1024 * 1024));
+        assert_eq!(parse_size("5m"), Some(5 * 1024 * 1024));
+        assert_eq!(parse_size("1G"), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_size("0"), None);
+        assert_eq!(parse_size("K"), None);
+        assert_eq!(parse_size("-1M"), None);
+        assert_eq!(parse_size("1T"), None);
+        // overflowing sizes are rejected
+        assert_eq!(parse_size("18446744073709551615G"), None);
+    }
+
+    #[test]
+    fn test_proxy() {
+        let mut config = Config::default();
+        assert_eq!(config.proxy(), None);
+
+        config
+            .set("proxy", "user:pass@proxy.example.com:8080")
+            .unwrap();
+        assert_eq!(config.proxy(), Some("user:pass@proxy.example.com:8080"));
+
+        config.set("proxy", "none").unwrap();
+        assert_eq!(config.proxy(), None);
+        assert!(config.inner.proxy.is_none());
+
+        // `none` from the config file is normalized as well
+        config.inner.proxy = Some("None".to_owned());
+        assert_eq!(config.proxy(), None);
+    }
+
+    #[test]
+    fn test_cache_max_age() {
+        let mut config = Config::default();
+        assert_eq!(config.cache_max_age(), None);
+
+        config.set("cache_max_age", "0").unwrap();
+        assert_eq!(config.cache_max_age(), None);
+
+        config.set("cache_max_age", "2").unwrap();
+        assert_eq!(config.cache_max_age(), Some(Duration::from_secs(172_800)));
+
+        // huge ages saturate instead of wrapping around to a tiny age
+        config.set("cache_max_age", &u64::MAX.to_string()).unwrap();
+        assert_eq!(config.cache_max_age(), Some(Duration::from_secs(u64::MAX)));
+    }
+    #[test]
+    fn test_private_hosts() {
+        let hosts: Vec<PrivateHosts> = serde_json::from_value(serde_json::json!([
+            { "match": "private\\.example\\.(com|org)", "headers": "X-Foo: bar" },
+            { "match": "example.com/[", "headers": "X-Bar: baz" },
+        ]))
+        .unwrap();
+
+        // the regex is compiled on first use and kept
+        assert!(hosts[0].is_match("https://private.example.org/foo.zip"));
+        assert!(!hosts[0].is_match("https://private-example.com/foo.zip"));
+        assert!(hosts[0].regex.get().unwrap().is_some());
+
+        // invalid regexes are matched as substrings
+        assert!(hosts[1].is_match("https://example.com/[foo].zip"));
+        assert!(!hosts[1].is_match("https://example.com/foo.zip"));
+        assert!(hosts[1].regex.get().unwrap().is_none());
+    }
+}
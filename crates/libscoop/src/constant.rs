@@ -16,6 +16,10 @@ pub static DEFAULT_USER_AGENT: &str = "Scoop/1.0 (+http://scoop.sh/)";
 /// synced bucket.
 pub static ISOLATED_PACKAGE_BUCKET: &str = "__isolated__";
 
+/// Directory of manifests imported from offline bundles, relative to the
+/// root directory of Scoop.
+pub static BUNDLE_DIR: &str = "bundle";
+
 /// Built-in list of known buckets.
 pub static BUILTIN_BUCKET_LIST: Lazy<Vec<(&'static str, &'static str)>> = Lazy::new(|| {
     vec![
@@ -60,9 +64,40 @@ pub static REGEX_CACHE_FILE: Lazy<Regex> = Lazy::new(|| {
 /// - **md5**: `^md5:[a-fA-F0-9]{32}$`
 /// - **sha1**: `^sha1:[a-fA-F0-9]{40}$`
 /// - **sha256**: `^(sha256:)?[a-fA-F0-9]{64}$`
+/// - **sha384**: `^sha384:[a-fA-F0-9]{96}$`
 /// - **sha512**: `^sha512:[a-fA-F0-9]{128}$`
+/// - **sha3-256**: `^sha3-256:[a-fA-F0-9]{64}$`
+/// - **blake3**: `^blake3:[a-fA-F0-9]{64}$`
 pub static REGEX_HASH: Lazy<Regex> = Lazy::new(|| {
-    let pattern = r"^md5:[a-fA-F0-9]{32}|sha1:[a-fA-F0-9]{40}|(sha256:)?[a-fA-F0-9]{64}|sha512:[a-fA-F0-9]{128}$";
+    let pattern = concat!(
+        r"^(?:md5:[a-fA-F0-9]{32}|sha1:[a-fA-F0-9]{40}|(?:sha256:)?[a-fA-F0-9]{64}",
+        r"|sha384:[a-fA-F0-9]{96}|sha512:[a-fA-F0-9]{128}|sha3-256:[a-fA-F0-9]{64}",
+        r"|blake3:[a-fA-F0-9]{64})$"
+    );
+    RegexBuilder::new(pattern).build().unwrap()
+});
+
+/// Regex of the `github` checkver shorthand, which captures the version from
+/// the tag of the latest release.
+pub static CHECKVER_GITHUB_REGEX: &str = r"/releases/tag/(?:v|V)?([\d.]+)";
+
+/// regex to match GitHub URLs which the `gh_token` is sent to.
+pub static REGEX_GITHUB_URL: Lazy<Regex> = Lazy::new(|| {
+    let pattern = r"^https?://((api|raw|objects)\.)?github(usercontent)?\.com/";
+    RegexBuilder::new(pattern).build().unwrap()
+});
+
+/// regex to match FossHub download URLs, which captures the `filename`.
+pub static REGEX_FOSSHUB_URL: Lazy<Regex> = Lazy::new(|| {
+    let pattern = r"^(?:.*fosshub\.com/).*(?:/|\?dwl=)(?<filename>.*)$";
+    RegexBuilder::new(pattern).build().unwrap()
+});
+
+/// regex to match SourceForge download URLs, which captures the `project` and
+/// the `file` path.
+pub static REGEX_SOURCEFORGE_URL: Lazy<Regex> = Lazy::new(|| {
+    let pattern =
+        r"(?:downloads\.)?sourceforge\.net/projects?/(?<project>[^/]+)/(?:files/)?(?<file>.*)";
     RegexBuilder::new(pattern).build().unwrap()
 });
 
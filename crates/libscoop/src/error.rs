@@ -39,14 +39,38 @@ pub enum Error {
     #[error("invalid config value '{0}'")]
     ConfigValueInvalid(String),
 
+    /// Thrown when failed to detect the latest version of a package.
+    #[error("checkver failed: {0}")]
+    Checkver(String),
+
+    /// Thrown when failed to update the manifest of a package.
+    #[error("autoupdate failed: {0}")]
+    Autoupdate(String),
+
     /// Thrown when trying to set the user agent twice.
     #[error("User agent already set")]
     UserAgentAlreadySet,
 
+    /// Thrown when trying to set the HTTP client twice.
+    #[error("HTTP client already set")]
+    HttpClientAlreadySet,
+
     /// Hash mismatch error
     #[error("{0}")]
     HashMismatch(HashMismatchContext),
 
+    /// Hash mismatch errors of multiple files, along with errors of files that
+    /// failed to be checked
+    #[error("{}", fmt_hash_mismatches(.mismatches, .errors))]
+    HashMismatches {
+        mismatches: Vec<HashMismatchContext>,
+        errors: Vec<(PathBuf, Error)>,
+    },
+
+    /// Invalid bundle file error
+    #[error("invalid bundle file: {}", path.display())]
+    InvalidBundle { path: PathBuf },
+
     /// Invalid cache file error
     #[error("error")]
     InvalidCacheFile { path: PathBuf },
@@ -96,13 +120,29 @@ pub enum Error {
     Hash(#[from] scoop_hash::Error),
 
     /// Curl error
+    ///
+    /// No longer returned, requests sent through the [`HttpClient`][1] report
+    /// failures as [`Http`][2] errors instead.
+    ///
+    /// [1]: crate::http::HttpClient
+    /// [2]: Error::Http
+    #[deprecated(note = "failures of requests are reported as `Error::Http`")]
     #[error(transparent)]
     Curl(#[from] curl::Error),
 
     /// Curl Multi error
+    ///
+    /// No longer returned, see [`Curl`][1].
+    ///
+    /// [1]: Error::Curl
+    #[deprecated(note = "failures of requests are reported as `Error::Http`")]
     #[error(transparent)]
     CurlMulti(#[from] curl::MultiError),
 
+    /// HTTP error
+    #[error(transparent)]
+    Http(#[from] crate::http::HttpError),
+
     /// Git error
     #[error(transparent)]
     Git(#[from] git2::Error),
@@ -119,3 +159,17 @@ pub enum Error {
     #[error(transparent)]
     Serde(#[from] serde_json::Error),
 }
+
+/// Format hash mismatches and errors of the files failed to be checked.
+fn fmt_hash_mismatches(mismatches: &[HashMismatchContext], errors: &[(PathBuf, Error)]) -> String {
+    mismatches
+        .iter()
+        .map(|ctx| ctx.to_string())
+        .chain(
+            errors
+                .iter()
+                .map(|(path, err)| format!("Failed to check {}: {}", path.display(), err)),
+        )
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
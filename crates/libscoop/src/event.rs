@@ -3,7 +3,13 @@ use flume::{bounded, Receiver, Sender};
 use crate::{
     bucket::BucketUpdateProgressContext,
     constant::EVENT_BUS_CAPACITY,
-    package::{download::PackageDownloadProgressContext, sync::Transaction},
+    package::{
+        download::{
+            PackageDownloadFileContext, PackageDownloadOverallContext,
+            PackageDownloadProgressContext,
+        },
+        sync::Transaction,
+    },
 };
 
 /// Event bus for event transmission.
@@ -90,6 +96,13 @@ pub enum Event {
     /// Package download has made some progress.
     PackageDownloadProgress(PackageDownloadProgressContext),
 
+    /// Overall package download has made some progress.
+    PackageDownloadOverallProgress(PackageDownloadOverallContext),
+
+    /// A package file has been downloaded, the context tells which source
+    /// served the file.
+    PackageDownloadFileDone(PackageDownloadFileContext),
+
     /// Package download has finished.
     PackageDownloadDone,
 
@@ -0,0 +1,524 @@
+//! HTTP client abstraction used for networking.
+//!
+//! All HTTP requests made by libscoop, such as sizing and downloading package
+//! files, go through the [`HttpClient`] of a [`Session`][1]. A [`CurlClient`]
+//! is used by default, embedders may replace it with their own networking
+//! stack via [`Session::set_http_client`][2].
+//!
+//! [1]: crate::Session
+//! [2]: crate::Session::set_http_client
+use curl::easy::{Auth, Easy, List};
+use std::{fmt, io, time::Duration};
+
+use crate::constant::DEFAULT_USER_AGENT;
+
+/// Errors that may occur when performing HTTP requests.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum HttpError {
+    /// Failed to resolve the host or the proxy, or to connect to the server.
+    #[error("failed to connect: {0}")]
+    Connect(String),
+
+    /// The request timed out, or the transfer stalled.
+    #[error("operation timed out: {0}")]
+    Timeout(String),
+
+    /// The server responded with an error status code.
+    #[error("server responded with status code {0}")]
+    Status(u32),
+
+    /// The connection was interrupted during the transfer.
+    #[error("transfer interrupted: {0}")]
+    Interrupted(String),
+
+    /// The transfer was aborted by the caller.
+    #[error("transfer aborted")]
+    Aborted,
+
+    /// I/O error when handling the received data.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// Other errors of the HTTP client.
+    #[error("{0}")]
+    Other(String),
+}
+
+impl HttpError {
+    /// Check if the error is likely temporary and the request is worth
+    /// retrying.
+    ///
+    /// Connection failures, timeouts, interruptions and server-side errors
+    /// (5xx, 408 and 429) are considered transient.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            HttpError::Connect(_) | HttpError::Timeout(_) | HttpError::Interrupted(_) => true,
+            HttpError::Status(code) => *code >= 500 || *code == 408 || *code == 429,
+            _ => false,
+        }
+    }
+}
+
+/// A HTTP request to be sent by a [`HttpClient`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Request {
+    /// Request URL.
+    pub url: String,
+
+    /// Extra headers in the `Name: value` form.
+    pub headers: Vec<String>,
+
+    /// Inclusive byte range of the body to request, if any.
+    pub range: Option<(u64, u64)>,
+
+    /// Proxy to connect through, `None` for a direct connection.
+    pub proxy: Option<Proxy>,
+
+    /// User agent of the request.
+    pub user_agent: String,
+
+    /// Timeout for establishing the connection.
+    pub connect_timeout: Duration,
+
+    /// Abort the transfer if no data is received during this period.
+    pub stall_timeout: Option<Duration>,
+}
+
+impl Request {
+    /// Create a new request to the given `url`.
+    pub fn new<S: Into<String>>(url: S) -> Request {
+        Request {
+            url: url.into(),
+            headers: vec![],
+            range: None,
+            proxy: None,
+            user_agent: DEFAULT_USER_AGENT.to_owned(),
+            connect_timeout: Duration::from_secs(30),
+            stall_timeout: None,
+        }
+    }
+}
+
+/// A proxy to send requests through.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Proxy {
+    /// Proxy URL, with credentials if any.
+    pub url: String,
+
+    /// Whether to authenticate to the proxy as the current user, via NTLM or
+    /// Negotiate.
+    pub current_user: bool,
+}
+
+impl Proxy {
+    /// Create a new proxy of the given `url`.
+    pub fn new<S: Into<String>>(url: S, current_user: bool) -> Proxy {
+        Proxy {
+            url: url.into(),
+            current_user,
+        }
+    }
+}
+
+/// Response information of a HTTP request.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct Response {
+    /// Status code of the response, `0` for non-HTTP protocols.
+    pub code: u32,
+
+    /// Length of the response body, if known.
+    pub content_length: Option<u64>,
+
+    /// Whether the server accepts range requests.
+    pub accept_ranges: bool,
+
+    /// Headers of the response, names are in lowercase.
+    pub headers: Vec<(String, String)>,
+}
+
+impl Response {
+    /// Create a new response.
+    pub fn new(code: u32, content_length: Option<u64>, accept_ranges: bool) -> Response {
+        Response {
+            code,
+            content_length,
+            accept_ranges,
+            headers: vec![],
+        }
+    }
+
+    /// Get the value of the first header of the given `name`, the name is
+    /// matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A HTTP client performing requests for a [`Session`][1].
+///
+/// Redirects are expected to be followed by the client.
+///
+/// [1]: crate::Session
+pub trait HttpClient: Send + Sync {
+    /// Send a request for the response headers of `request` only.
+    ///
+    /// Error status codes are not treated as errors, they are returned in the
+    /// response instead.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the request failed to be sent.
+    fn head(&self, request: &Request) -> Result<Response, HttpError>;
+
+    /// Send a GET request and stream the response body to `body`.
+    ///
+    /// `progress` is called with the total and the received bytes of the body
+    /// along with the transfer, returning `false` from it aborts the transfer.
+    ///
+    /// # Errors
+    ///
+    /// A [`Status`][1] error will be returned if the server responds with an
+    /// error status code, an [`Aborted`][2] error if the transfer is aborted
+    /// by `progress`, and the error returned from `body` if it fails.
+    ///
+    /// [1]: HttpError::Status
+    /// [2]: HttpError::Aborted
+    fn get(
+        &self,
+        request: &Request,
+        body: &mut dyn FnMut(&[u8]) -> io::Result<()>,
+        progress: &mut dyn FnMut(u64, u64) -> bool,
+    ) -> Result<Response, HttpError>;
+}
+
+impl fmt::Debug for dyn HttpClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HttpClient")
+    }
+}
+
+/// The default [`HttpClient`] built on top of curl.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CurlClient;
+
+impl CurlClient {
+    /// Create a curl handle for the given `request`.
+    fn easy(&self, request: &Request) -> Result<Easy, curl::Error> {
+        let mut easy = Easy::new();
+        easy.get(true)?;
+        easy.url(&request.url)?;
+        easy.follow_location(true)?;
+        easy.useragent(&request.user_agent)?;
+        easy.connect_timeout(request.connect_timeout)?;
+
+        if let Some(stall_timeout) = request.stall_timeout {
+            // abort the transfer if less than 1 byte/s is received during the
+            // period
+            easy.low_speed_limit(1)?;
+            easy.low_speed_time(stall_timeout)?;
+        }
+
+        match request.proxy.as_ref() {
+            Some(proxy) => {
+                easy.proxy(&proxy.url)?;
+                if proxy.current_user {
+                    let mut auth = Auth::new();
+                    auth.gssnegotiate(true).ntlm(true);
+                    easy.proxy_auth(&auth)?;
+                    easy.proxy_username("")?;
+                    easy.proxy_password("")?;
+                }
+            }
+            // an empty proxy stops curl from picking one up from environment
+            None => easy.proxy("")?,
+        }
+
+        if let Some((start, end)) = request.range {
+            easy.range(&format!("{}-{}", start, end))?;
+        }
+
+        let mut list = List::new();
+        for header in request.headers.iter() {
+            list.append(header)?;
+        }
+        easy.http_headers(list)?;
+
+        Ok(easy)
+    }
+}
+
+impl HttpClient for CurlClient {
+    fn head(&self, request: &Request) -> Result<Response, HttpError> {
+        let mut easy = self.easy(request).map_err(|e| curl_error(e, 0))?;
+        easy.nobody(true).map_err(|e| curl_error(e, 0))?;
+
+        let mut headers = vec![];
+        let ret = {
+            let mut transfer = easy.transfer();
+            transfer
+                .header_function(|header| collect_header(&mut headers, header))
+                .map_err(|e| curl_error(e, 0))?;
+            transfer.perform()
+        };
+
+        let code = easy.response_code().unwrap_or_default();
+        ret.map_err(|e| curl_error(e, code))?;
+
+        Ok(response(&mut easy, code, headers))
+    }
+
+    fn get(
+        &self,
+        request: &Request,
+        body: &mut dyn FnMut(&[u8]) -> io::Result<()>,
+        progress: &mut dyn FnMut(u64, u64) -> bool,
+    ) -> Result<Response, HttpError> {
+        let mut easy = self.easy(request).map_err(|e| curl_error(e, 0))?;
+        easy.fail_on_error(true).map_err(|e| curl_error(e, 0))?;
+        easy.progress(true).map_err(|e| curl_error(e, 0))?;
+
+        let mut body_err = None;
+        let mut headers = vec![];
+        let ret = {
+            let mut transfer = easy.transfer();
+            transfer
+                .header_function(|header| collect_header(&mut headers, header))
+                .map_err(|e| curl_error(e, 0))?;
+            transfer
+                .write_function(|data| match body(data) {
+                    Ok(_) => Ok(data.len()),
+                    Err(err) => {
+                        body_err = Some(err);
+                        // a short write aborts the transfer
+                        Ok(0)
+                    }
+                })
+                .map_err(|e| curl_error(e, 0))?;
+            transfer
+                .progress_function(|dltotal, dlnow, _, _| progress(dltotal as u64, dlnow as u64))
+                .map_err(|e| curl_error(e, 0))?;
+            transfer.perform()
+        };
+
+        if let Some(err) = body_err {
+            return Err(HttpError::Io(err));
+        }
+
+        let code = easy.response_code().unwrap_or_default();
+        ret.map_err(|e| curl_error(e, code))?;
+
+        Ok(response(&mut easy, code, headers))
+    }
+}
+
+/// Collect a raw `header` line received by curl into `headers`.
+fn collect_header(headers: &mut Vec<(String, String)>, header: &[u8]) -> bool {
+    let header = String::from_utf8_lossy(header);
+    // a new status line starts the headers of a redirected response
+    if header.starts_with("HTTP/") {
+        headers.clear();
+    } else if let Some((name, value)) = header.split_once(':') {
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+    }
+    true
+}
+
+/// Build the response of a finished curl transfer.
+fn response(easy: &mut Easy, code: u32, headers: Vec<(String, String)>) -> Response {
+    let content_length = easy
+        .content_length_download()
+        .ok()
+        .filter(|&len| len >= 0f64)
+        .map(|len| len as u64);
+
+    let mut resp = Response::new(code, content_length, false);
+    resp.headers = headers;
+    resp.accept_ranges = resp.header("accept-ranges") == Some("bytes");
+    resp
+}
+
+/// Convert a curl error of a request responded with `code` to [`HttpError`].
+fn curl_error(err: curl::Error, code: u32) -> HttpError {
+    if err.is_http_returned_error() {
+        HttpError::Status(code)
+    } else if err.is_couldnt_connect()
+        || err.is_couldnt_resolve_host()
+        || err.is_couldnt_resolve_proxy()
+        || err.is_ssl_connect_error()
+    {
+        HttpError::Connect(err.to_string())
+    } else if err.is_operation_timedout() {
+        HttpError::Timeout(err.to_string())
+    } else if err.is_partial_file()
+        || err.is_got_nothing()
+        || err.is_send_error()
+        || err.is_recv_error()
+    {
+        HttpError::Interrupted(err.to_string())
+    } else if err.is_aborted_by_callback() {
+        HttpError::Aborted
+    } else {
+        HttpError::Other(err.to_string())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// A stand-in [`HttpClient`] serving files from memory, which honors
+    /// range requests, fails `GET` requests on demand and records them.
+    ///
+    /// Clones of a server share their state, so that a clone may be kept for
+    /// inspection after handing the server over to a session.
+    #[derive(Clone, Default)]
+    pub struct Server(Arc<ServerState>);
+
+    #[derive(Default)]
+    struct ServerState {
+        files: Mutex<HashMap<String, Vec<u8>>>,
+
+        /// Errors to fail the upcoming `GET` requests of each url with, in
+        /// order.
+        failures: Mutex<HashMap<String, VecDeque<HttpError>>>,
+
+        /// Received `GET` requests, in order.
+        requests: Mutex<Vec<Request>>,
+
+        /// Whether range requests are answered with the whole file.
+        ignore_ranges: AtomicBool,
+    }
+
+    impl Server {
+        /// Serve `data` at `url`.
+        pub fn serve(self, url: &str, data: &[u8]) -> Server {
+            let mut files = self.0.files.lock().unwrap();
+            files.insert(url.to_owned(), data.to_vec());
+            drop(files);
+            self
+        }
+
+        /// Create a server serving the given text pages by urls.
+        pub fn pages<'a, I>(pages: I) -> Server
+        where
+            I: IntoIterator<Item = (&'a str, &'a str)>,
+        {
+            pages
+                .into_iter()
+                .fold(Server::default(), |server, (url, page)| {
+                    server.serve(url, page.as_bytes())
+                })
+        }
+
+        /// Fail the next `GET` request of `url` not yet failed with `err`.
+        pub fn fail(self, url: &str, err: HttpError) -> Server {
+            let mut failures = self.0.failures.lock().unwrap();
+            failures.entry(url.to_owned()).or_default().push_back(err);
+            drop(failures);
+            self
+        }
+
+        /// Answer range requests with the whole file, like a server which
+        /// advertises range support but does not honor it.
+        pub fn ignore_ranges(self) -> Server {
+            self.0.ignore_ranges.store(true, Ordering::Relaxed);
+            self
+        }
+
+        /// Get the `GET` requests received so far.
+        pub fn requests(&self) -> Vec<Request> {
+            self.0.requests.lock().unwrap().clone()
+        }
+
+        /// Take the failure `request` is due to get, if any.
+        fn receive(&self, request: &Request) -> Result<(), HttpError> {
+            let mut failures = self.0.failures.lock().unwrap();
+            match failures.get_mut(&request.url).and_then(|f| f.pop_front()) {
+                Some(err) => Err(err),
+                None => Ok(()),
+            }
+        }
+
+        /// Get the file served at `url`.
+        fn file(&self, url: &str) -> Option<Vec<u8>> {
+            self.0.files.lock().unwrap().get(url).cloned()
+        }
+    }
+
+    impl HttpClient for Server {
+        fn head(&self, request: &Request) -> Result<Response, HttpError> {
+            Ok(match self.file(&request.url) {
+                Some(data) => Response::new(200, Some(data.len() as u64), true),
+                None => Response::new(404, None, false),
+            })
+        }
+
+        fn get(
+            &self,
+            request: &Request,
+            body: &mut dyn FnMut(&[u8]) -> io::Result<()>,
+            progress: &mut dyn FnMut(u64, u64) -> bool,
+        ) -> Result<Response, HttpError> {
+            self.0.requests.lock().unwrap().push(request.clone());
+            self.receive(request)?;
+            let data = self.file(&request.url).ok_or(HttpError::Status(404))?;
+
+            let range = request
+                .range
+                .filter(|_| !self.0.ignore_ranges.load(Ordering::Relaxed));
+            let (code, start, end) = match range {
+                Some((start, end)) => (206, start as usize, (end as usize + 1).min(data.len())),
+                None => (200, 0, data.len()),
+            };
+            let content = &data[start..end];
+            let total = content.len() as u64;
+
+            // deliver the content in small chunks like a real transfer
+            let mut now = 0;
+            for chunk in content.chunks(16) {
+                body(chunk)?;
+                now += chunk.len() as u64;
+                if !progress(total, now) {
+                    return Err(HttpError::Aborted);
+                }
+            }
+
+            let mut resp = Response::new(code, Some(total), true);
+            if code == 206 {
+                let range = format!("bytes {}-{}/{}", start, end - 1, data.len());
+                resp.headers.push(("content-range".to_owned(), range));
+            }
+            Ok(resp)
+        }
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let err = |msg: &str| msg.to_owned();
+        assert!(HttpError::Connect(err("refused")).is_transient());
+        assert!(HttpError::Timeout(err("timed out")).is_transient());
+        assert!(HttpError::Interrupted(err("reset")).is_transient());
+        for code in [408, 429, 500, 502, 503] {
+            assert!(HttpError::Status(code).is_transient(), "{}", code);
+        }
+
+        for code in [400, 401, 403, 404, 416] {
+            assert!(!HttpError::Status(code).is_transient(), "{}", code);
+        }
+        assert!(!HttpError::Aborted.is_transient());
+        assert!(!HttpError::Other(err("unsupported protocol")).is_transient());
+        let io = io::Error::other("disk full");
+        assert!(!HttpError::Io(io).is_transient());
+    }
+}
@@ -1,9 +1,13 @@
-use git2::{CredentialType, FetchOptions, Repository};
+use git2::{build::TreeUpdateBuilder, CredentialType, FetchOptions, FileMode, Repository};
 use std::{path::Path, result::Result};
 
 use crate::error::Fallible;
 
-fn fetch_options(proxy: Option<&str>) -> FetchOptions<'static> {
+use super::proxy;
+
+/// Build fetch options for fetching from `remote_url`, with the proxy resolved
+/// from the given `proxy` config.
+fn fetch_options(remote_url: &str, proxy: Option<&str>) -> FetchOptions<'static> {
     let mut fo = FetchOptions::new();
     let mut cb = git2::RemoteCallbacks::new();
 
@@ -26,15 +30,11 @@ fn fetch_options(proxy: Option<&str>) -> FetchOptions<'static> {
 
     fo.remote_callbacks(cb);
 
-    if let Some(proxy) = proxy {
-        let mut proxy = proxy.to_owned();
-
-        if !(proxy.starts_with("http") || proxy.starts_with("socks")) {
-            proxy.insert_str(0, "http://");
-        }
-
+    // libgit2 has no way to authenticate to a proxy as the current user, the
+    // proxy is used as is then.
+    if let Some(proxy) = proxy::resolve(proxy, remote_url) {
         let mut po = git2::ProxyOptions::new();
-        po.url(proxy.as_str());
+        po.url(proxy.url.as_str());
         fo.proxy_options(po);
     }
 
@@ -48,7 +48,7 @@ where
 {
     let proxy = proxy.as_ref().map(|s| s.as_ref());
     let mut repo_builder = git2::build::RepoBuilder::new();
-    repo_builder.fetch_options(fetch_options(proxy));
+    repo_builder.fetch_options(fetch_options(remote_url.as_ref(), proxy));
 
     repo_builder
         .clone(remote_url.as_ref(), path.as_ref())
@@ -65,11 +65,12 @@ where
     let proxy = proxy.as_ref().map(|s| s.as_ref());
     let repo = Repository::open(path.as_ref())?;
     let mut origin = repo.find_remote("origin")?;
+    let remote_url = origin.url().unwrap_or_default().to_owned();
 
     // fetch all refs
     origin.fetch(
         &["refs/heads/*:refs/heads/*"],
-        Some(&mut fetch_options(proxy)),
+        Some(&mut fetch_options(&remote_url, proxy)),
         None,
     )?;
 
@@ -89,3 +90,99 @@ where
     let remote = repo.find_remote(remote.as_ref())?;
     Ok(remote.url().map(|s| s.to_owned()))
 }
+
+/// Commit the file at `path` to the repository it belongs to, with the given
+/// commit `message`.
+///
+/// The commit is signed with the `user.name` and `user.email` of the git
+/// config, and only the file is committed, other changes of the repository,
+/// staged or not, are left as is.
+pub fn commit_file(path: &Path, message: &str) -> Fallible<()> {
+    let repo = Repository::discover(path)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("bare repository has no working directory"))?;
+    let workdir = std::fs::canonicalize(workdir)?;
+    let path = std::fs::canonicalize(path)?;
+    let relative = path
+        .strip_prefix(&workdir)
+        .map_err(|_| git2::Error::from_str("file is outside of the repository"))?;
+
+    let parent = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(_) => None,
+    };
+
+    // Build the tree from the one of HEAD rather than from the index, so
+    // that changes already staged by the user are not committed along.
+    let base = match parent.as_ref() {
+        Some(parent) => parent.tree()?,
+        None => repo.find_tree(repo.treebuilder(None)?.write()?)?,
+    };
+    let blob = repo.blob_path(&path)?;
+    let mut update = TreeUpdateBuilder::new();
+    update.upsert(relative, blob, FileMode::Blob);
+    let tree = repo.find_tree(update.create_updated(&repo, &base)?)?;
+
+    let mut index = repo.index()?;
+    index.add_path(relative)?;
+    index.write()?;
+
+    let signature = repo.signature()?;
+    let parents = parent.iter().collect::<Vec<_>>();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_commit_file() {
+        let dir = std::env::temp_dir().join("libscoop-git-commit");
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "hok").unwrap();
+        config.set_str("user.email", "hok@example.com").unwrap();
+
+        let path = dir.join("foo.json");
+        std::fs::write(&path, "{}").unwrap();
+        std::fs::write(dir.join("bar.json"), "{}").unwrap();
+        commit_file(&path, "foo: Update to version 1.0").unwrap();
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.message(), Some("foo: Update to version 1.0"));
+        let tree = head.tree().unwrap();
+        assert!(tree.get_name("foo.json").is_some());
+        assert!(tree.get_name("bar.json").is_none());
+
+        // changes staged by the user are not committed along
+        std::fs::write(&path, r#"{"version": "2.0"}"#).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("bar.json")).unwrap();
+        index.write().unwrap();
+        commit_file(&path, "foo: Update to version 2.0").unwrap();
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.parent_count(), 1);
+        let tree = head.tree().unwrap();
+        let blob = tree.get_name("foo.json").unwrap().to_object(&repo).unwrap();
+        assert_eq!(blob.as_blob().unwrap().content(), br#"{"version": "2.0"}"#);
+        assert!(tree.get_name("bar.json").is_none());
+        // the staged change is kept staged
+        let index = repo.index().unwrap();
+        assert!(index.get_path(Path::new("bar.json"), 0).is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
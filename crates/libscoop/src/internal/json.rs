@@ -0,0 +1,279 @@
+//! Edit JSON documents in place.
+//!
+//! Only the values being set are rewritten, the rest of the document, such as
+//! the order of keys, the indentation and the line endings, is kept as is.
+//! New values are pretty printed in the indentation style of the document.
+use serde::Serialize;
+use serde_json::{ser::PrettyFormatter, Serializer, Value};
+use std::ops::Range;
+
+/// A JSON value located in the document.
+#[derive(Debug)]
+struct Node {
+    /// Byte range of the value in the document.
+    span: Range<usize>,
+    kind: Kind,
+}
+
+#[derive(Debug)]
+enum Kind {
+    /// Members of an object with the starting position of their keys.
+    Object(Vec<(String, usize, Node)>),
+    Array,
+    Scalar,
+}
+
+/// A minimal parser locating values of a valid JSON document.
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_ws(&mut self) {
+        while self.pos < self.text.len() && self.text[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> Option<()> {
+        self.skip_ws();
+        match self.text.get(self.pos) == Some(&byte) {
+            true => {
+                self.pos += 1;
+                Some(())
+            }
+            false => None,
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let start = self.pos;
+        self.eat(b'"')?;
+        loop {
+            match self.text.get(self.pos)? {
+                b'\\' => self.pos += 2,
+                b'"' => break,
+                _ => self.pos += 1,
+            }
+        }
+        self.pos += 1;
+        serde_json::from_slice(&self.text[start..self.pos]).ok()
+    }
+
+    fn value(&mut self) -> Option<Node> {
+        self.skip_ws();
+        let start = self.pos;
+        let kind = match self.text.get(self.pos)? {
+            b'{' => {
+                self.pos += 1;
+                let mut members = vec![];
+                if self.eat(b'}').is_none() {
+                    loop {
+                        self.skip_ws();
+                        let key_start = self.pos;
+                        let key = self.string()?;
+                        self.eat(b':')?;
+                        members.push((key, key_start, self.value()?));
+                        if self.eat(b',').is_none() {
+                            self.eat(b'}')?;
+                            break;
+                        }
+                    }
+                }
+                Kind::Object(members)
+            }
+            b'[' => {
+                self.pos += 1;
+                if self.eat(b']').is_none() {
+                    loop {
+                        self.value()?;
+                        if self.eat(b',').is_none() {
+                            self.eat(b']')?;
+                            break;
+                        }
+                    }
+                }
+                Kind::Array
+            }
+            b'"' => {
+                self.string()?;
+                Kind::Scalar
+            }
+            _ => {
+                while self.pos < self.text.len()
+                    && !matches!(self.text[self.pos], b',' | b']' | b'}')
+                    && !self.text[self.pos].is_ascii_whitespace()
+                {
+                    self.pos += 1;
+                }
+                Kind::Scalar
+            }
+        };
+
+        Some(Node {
+            span: start..self.pos,
+            kind,
+        })
+    }
+}
+
+/// Set the value at the path of object keys `pointer` of the JSON document
+/// `text`, missing keys along the path are inserted.
+///
+/// # Returns
+///
+/// The edited document, `None` if `text` is not a valid JSON document or a
+/// value along the path is not an object.
+pub fn set(text: &str, pointer: &[&str], value: &Value) -> Option<String> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        pos: 0,
+    };
+    let root = parser.value()?;
+    let style = Style::detect(text);
+
+    let mut node = &root;
+    let mut depth = 0;
+    while depth < pointer.len() {
+        let members = match &node.kind {
+            Kind::Object(members) => members,
+            _ => return None,
+        };
+        match members.iter().find(|(k, _, _)| k == pointer[depth]) {
+            Some((_, _, child)) => node = child,
+            // the node is an object lacking the key
+            None => break,
+        }
+        depth += 1;
+    }
+
+    let mut ret = String::with_capacity(text.len());
+    ret.push_str(&text[..node.span.start]);
+
+    if depth == pointer.len() {
+        let indent = style.indent_at(text, node.span.start);
+        ret.push_str(&style.format(value, indent));
+        ret.push_str(&text[node.span.end..]);
+        return Some(ret);
+    }
+
+    // wrap the value into objects of the missing keys
+    let mut value = value.clone();
+    for key in pointer[depth + 1..].iter().rev() {
+        let mut map = serde_json::Map::new();
+        map.insert(key.to_string(), value);
+        value = Value::Object(map);
+    }
+    let key = pointer[depth];
+
+    let members = match &node.kind {
+        Kind::Object(members) => members,
+        _ => unreachable!(),
+    };
+    match (members.first(), members.last()) {
+        (Some((_, first_key, _)), Some((_, _, last))) => {
+            let inline = !text[node.span.start..*first_key].contains('\n');
+            let indent = style.indent_at(text, *first_key);
+            ret.push_str(&text[node.span.start..last.span.end]);
+            ret.push(',');
+            match inline {
+                true => ret.push(' '),
+                false => {
+                    ret.push_str(style.newline);
+                    ret.push_str(indent);
+                }
+            }
+            ret.push_str(&serde_json::to_string(key).ok()?);
+            ret.push_str(": ");
+            ret.push_str(&style.format(&value, indent));
+            ret.push_str(&text[last.span.end..]);
+        }
+        _ => {
+            // rewrite the empty object
+            let mut map = serde_json::Map::new();
+            map.insert(key.to_owned(), value);
+            let indent = style.indent_at(text, node.span.start);
+            ret.push_str(&style.format(&Value::Object(map), indent));
+            ret.push_str(&text[node.span.end..]);
+        }
+    }
+
+    Some(ret)
+}
+
+/// Formatting style of a JSON document.
+struct Style {
+    /// Indentation of a level.
+    indent: String,
+    newline: &'static str,
+}
+
+impl Style {
+    fn detect(text: &str) -> Style {
+        let indent = text
+            .lines()
+            .skip(1)
+            .map(|line| &line[..line.len() - line.trim_start().len()])
+            .find(|ws| !ws.is_empty())
+            .unwrap_or("    ")
+            .to_owned();
+        let newline = match text.contains("\r\n") {
+            true => "\r\n",
+            false => "\n",
+        };
+        Style { indent, newline }
+    }
+
+    /// Get the indentation of the line at `pos`.
+    fn indent_at<'a>(&self, text: &'a str, pos: usize) -> &'a str {
+        let start = text[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line = &text[start..];
+        let len = line.len() - line.trim_start_matches([' ', '\t']).len();
+        &line[..len]
+    }
+
+    /// Pretty print `value` to be placed at a line indented with `indent`.
+    fn format(&self, value: &Value, indent: &str) -> String {
+        let mut buf = vec![];
+        let formatter = PrettyFormatter::with_indent(self.indent.as_bytes());
+        let mut ser = Serializer::with_formatter(&mut buf, formatter);
+        value.serialize(&mut ser).unwrap();
+
+        let mut newline = self.newline.to_owned();
+        newline.push_str(indent);
+        String::from_utf8(buf).unwrap().replace('\n', &newline)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::set;
+    use serde_json::json;
+
+    #[test]
+    fn test_set() {
+        let text = "{\r\n  \"version\": \"1.0\",\r\n  \"url\": [\"a\", \"b\"],\r\n  \"architecture\": {\r\n    \"64bit\": {\r\n      \"url\": \"c\"\r\n    }\r\n  }\r\n}\r\n";
+
+        let text = set(text, &["version"], &json!("2.0")).unwrap();
+        assert!(text.contains("\"version\": \"2.0\",\r\n"));
+
+        let text = set(text.as_str(), &["url"], &json!(["d", "e"])).unwrap();
+        assert!(text.contains("\"url\": [\r\n    \"d\",\r\n    \"e\"\r\n  ],\r\n"));
+
+        let text = set(&text, &["architecture", "64bit", "hash"], &json!("f")).unwrap();
+        assert!(text.contains("\"url\": \"c\",\r\n      \"hash\": \"f\"\r\n    }"));
+
+        let text = set(&text, &["architecture", "32bit", "url"], &json!("g")).unwrap();
+        assert!(text.contains("    },\r\n    \"32bit\": {\r\n      \"url\": \"g\"\r\n    }\r\n  }"));
+
+        let value = serde_json::from_str::<serde_json::Value>(&text).unwrap();
+        assert_eq!(value["architecture"]["64bit"]["hash"], "f");
+        assert_eq!(set(&text, &["version", "x"], &json!(1)), None);
+
+        let text = set("{\"a\": {}, \"b\": 1}", &["a", "c"], &json!(2)).unwrap();
+        assert_eq!(text, "{\"a\": {\n    \"c\": 2\n}, \"b\": 1}");
+        let text = set("{\"a\": 1}", &["b"], &json!("x\"y")).unwrap();
+        assert_eq!(text, "{\"a\": 1, \"b\": \"x\\\"y\"}");
+    }
+}
@@ -3,9 +3,33 @@ pub mod dag;
 pub mod env;
 pub mod fs;
 pub mod git;
-pub mod network;
+pub mod json;
 pub mod os;
 pub mod path;
+pub mod proxy;
+pub mod select;
+
+/// Decode percent-encoded characters of the given input.
+pub fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut ret = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' {
+            let hex = input.get(idx + 1..idx + 3);
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                ret.push(byte);
+                idx += 3;
+                continue;
+            }
+        }
+        ret.push(bytes[idx]);
+        idx += 1;
+    }
+
+    String::from_utf8_lossy(&ret).into_owned()
+}
 
 /// FIXME: there is a wide knowledge of version comparsion,
 /// And of cause I can't implement all of them at one commit, so plese fix me.
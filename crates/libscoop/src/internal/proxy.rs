@@ -0,0 +1,230 @@
+//! Proxy resolution shared by the downloader and git operations.
+//!
+//! The `proxy` config follows the syntax of Scoop:
+//!
+//! - `default`: use the proxy of the environment;
+//! - `currentuser@default`: use the proxy of the environment, authenticating
+//!   as the current user;
+//! - `[user:pass@]host:port`: use the given proxy, optionally with
+//!   credentials.
+//!
+//! The proxy of the environment is read from the `HTTP_PROXY`, `HTTPS_PROXY`
+//! and `ALL_PROXY` environment variables, which is also what an unset `proxy`
+//! config falls back to. Note that this differs from Scoop, where `default`
+//! refers to the Windows system proxy settings. `none` is normalized to an
+//! unset config by [`Config::proxy`]. Hosts listed in the `NO_PROXY`
+//! environment variable are always connected directly.
+//!
+//! [`Config::proxy`]: crate::config::Config::proxy
+use crate::http::Proxy;
+
+use super::percent_decode;
+
+/// Resolve the proxy to connect to `url` through, according to the `proxy`
+/// config.
+///
+/// # Returns
+///
+/// The proxy to use, or `None` if `url` should be connected directly.
+pub fn resolve(config: Option<&str>, url: &str) -> Option<Proxy> {
+    resolve_with(config, url, |key| std::env::var(key).ok())
+}
+
+/// Resolve the proxy with environment variables looked up by `env`.
+fn resolve_with<F>(config: Option<&str>, url: &str, env: F) -> Option<Proxy>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let config = config.map(str::trim).filter(|s| !s.is_empty());
+
+    let lookup = |key: &str| {
+        env(key)
+            .or_else(|| env(&key.to_ascii_lowercase()))
+            .filter(|s| !s.trim().is_empty())
+    };
+
+    if let Some(no_proxy) = lookup("NO_PROXY") {
+        if is_bypassed(&no_proxy, url) {
+            return None;
+        }
+    }
+
+    let (current_user, custom) = match config {
+        None => (false, None),
+        Some(s) if s.eq_ignore_ascii_case("default") => (false, None),
+        Some(s) if s.eq_ignore_ascii_case("currentuser@default") => (true, None),
+        Some(s) => (false, Some(s)),
+    };
+
+    let url = match custom {
+        Some(proxy) => proxy_url(proxy),
+        None => {
+            let key = match url.get(..8) {
+                Some(scheme) if scheme.eq_ignore_ascii_case("https://") => "HTTPS_PROXY",
+                _ => "HTTP_PROXY",
+            };
+            proxy_url(&lookup(key).or_else(|| lookup("ALL_PROXY"))?)
+        }
+    };
+
+    Some(Proxy::new(url, current_user))
+}
+
+/// Normalize a proxy of the `[user:pass@]host:port` form into a URL.
+///
+/// Credentials are percent-encoded so that reserved characters in them are
+/// not mistaken as delimiters of the URL.
+fn proxy_url(proxy: &str) -> String {
+    let proxy = proxy.trim();
+    let (scheme, rest) = match proxy.split_once("://") {
+        Some((scheme, rest)) => (scheme, rest),
+        None => ("http", proxy),
+    };
+
+    match rest.rsplit_once('@') {
+        None => format!("{}://{}", scheme, rest),
+        Some((userinfo, host)) => {
+            let (user, pass) = userinfo.split_once(':').unwrap_or((userinfo, ""));
+            let user = percent_encode(&percent_decode(user));
+            let pass = percent_encode(&percent_decode(pass));
+            format!("{}://{}:{}@{}", scheme, user, pass, host)
+        }
+    }
+}
+
+/// Check if `url` matches the given `NO_PROXY` list.
+///
+/// Entries are separated by commas, an entry matches the host itself and all
+/// of its subdomains, with or without a leading dot. An entry with a port
+/// only matches the given port, and `*` matches all hosts.
+fn is_bypassed(no_proxy: &str, url: &str) -> bool {
+    let (host, port) = host_port(url);
+    let host = host.to_ascii_lowercase();
+
+    no_proxy
+        .split(',')
+        .map(|entry| entry.trim().to_ascii_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+
+            let (entry_host, entry_port) = host_port(&entry);
+            if entry_port.is_some() && entry_port != port {
+                return false;
+            }
+
+            let entry_host = entry_host.trim_start_matches("*.").trim_start_matches('.');
+            host == entry_host
+                || host
+                    .strip_suffix(entry_host)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+}
+
+/// Split the host and the port of `url`, the port is inferred from the scheme
+/// if not given.
+fn host_port(url: &str) -> (&str, Option<u16>) {
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, url),
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, a)| a);
+
+    let (host, port) = match authority.strip_prefix('[') {
+        // IPv6 address
+        Some(rest) => match rest.split_once(']') {
+            Some((host, port)) => (host, port.strip_prefix(':')),
+            None => (rest, None),
+        },
+        // bare IPv6 address
+        None if authority.matches(':').count() > 1 => (authority, None),
+        None => match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+
+    let port = port.and_then(|p| p.parse().ok()).or_else(|| {
+        match scheme.map(|s| s.to_ascii_lowercase()).as_deref() {
+            Some("http") => Some(80),
+            Some("https") => Some(443),
+            _ => None,
+        }
+    });
+
+    (host, port)
+}
+
+/// Percent-encode characters of the given input other than the unreserved
+/// ones.
+fn percent_encode(input: &str) -> String {
+    let mut ret = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                ret.push(byte as char)
+            }
+            _ => ret.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let url = "https://example.com/a.zip";
+        let proxy = |config, vars| resolve_with(config, url, env(vars)).map(|p| p.url);
+
+        assert_eq!(proxy(None, &[]), None);
+        assert_eq!(
+            proxy(None, &[("https_proxy", "p:1")]),
+            Some("http://p:1".to_owned())
+        );
+        assert_eq!(
+            proxy(Some("default"), &[("ALL_PROXY", "socks5://p:1")]),
+            Some("socks5://p:1".to_owned())
+        );
+        assert_eq!(
+            proxy(Some("u:p@ss@p:1"), &[]),
+            Some("http://u:p%40ss@p:1".to_owned())
+        );
+        assert_eq!(
+            proxy(Some("p:1"), &[("NO_PROXY", "other.com,.example.com")]),
+            None
+        );
+
+        let current_user = resolve_with(
+            Some("currentuser@default"),
+            url,
+            env(&[("HTTPS_PROXY", "p:1")]),
+        );
+        assert!(current_user.is_some_and(|p| p.current_user));
+    }
+
+    #[test]
+    fn test_is_bypassed() {
+        assert!(is_bypassed("*", "https://example.com"));
+        assert!(is_bypassed("example.com", "https://example.com/a"));
+        assert!(is_bypassed("example.com", "https://dl.example.com/a"));
+        assert!(is_bypassed(".example.com", "https://dl.example.com/a"));
+        assert!(is_bypassed("example.com:443", "https://example.com/a"));
+        assert!(is_bypassed("::1", "http://[::1]:8080/a"));
+        assert!(!is_bypassed("example.com:80", "https://example.com/a"));
+        assert!(!is_bypassed("example.com", "https://badexample.com/a"));
+    }
+}
@@ -0,0 +1,136 @@
+//! Select values from JSON and XML documents, as what `jsonpath` and `xpath`
+//! of manifests do.
+use std::borrow::Cow;
+use sxd_document::dom::ChildOfRoot;
+use sxd_xpath::{Context, Factory};
+
+use crate::error::{Error, Fallible};
+
+/// Select a value from the JSON document `json` with the JSONPath `path`.
+///
+/// # Returns
+///
+/// The first selected value, non-string values are serialized to JSON. `None`
+/// if nothing is selected.
+///
+/// # Errors
+///
+/// A [`Serde`][1] error will be returned if `json` is not a valid JSON
+/// document, and a [`Custom`][2] error if `path` is not a valid JSONPath.
+///
+/// [1]: crate::Error::Serde
+/// [2]: crate::Error::Custom
+pub fn json_path(json: &str, path: &str) -> Fallible<Option<String>> {
+    let json = serde_json::from_str::<serde_json::Value>(json)?;
+    let selected = jsonpath_lib::select(&json, path)
+        .map_err(|e| Error::Custom(format!("invalid jsonpath '{}': {}", path, e)))?;
+
+    let ret = selected.first().map(|value| match value {
+        serde_json::Value::String(s) => s.to_owned(),
+        value => value.to_string(),
+    });
+    Ok(ret.filter(|s| !s.is_empty()))
+}
+
+/// Select a value from the XML document `xml` with the XPath `path`.
+///
+/// Namespaces declared on the root element can be used in `path` with their
+/// prefixes. Names in `path` are bound to the default namespace, if declared,
+/// as unprefixed names otherwise never match elements in that namespace.
+///
+/// # Returns
+///
+/// The string value of the selection, `None` if it is empty.
+///
+/// # Errors
+///
+/// A [`Custom`][1] error will be returned if `xml` is not a valid XML
+/// document or `path` is not a valid XPath.
+///
+/// [1]: crate::Error::Custom
+pub fn xpath(xml: &str, path: &str) -> Fallible<Option<String>> {
+    let package = sxd_document::parser::parse(xml)
+        .map_err(|e| Error::Custom(format!("invalid XML document: {}", e)))?;
+    let document = package.as_document();
+
+    let mut context = Context::new();
+    let mut path = Cow::Borrowed(path);
+    let root = document
+        .root()
+        .children()
+        .into_iter()
+        .find_map(|c| match c {
+            ChildOfRoot::Element(e) => Some(e),
+            _ => None,
+        });
+    if let Some(root) = root {
+        for ns in root.namespaces_in_scope() {
+            context.set_namespace(ns.prefix(), ns.uri());
+        }
+
+        if let Some(uri) = root.default_namespace_uri() {
+            context.set_namespace("ns", uri);
+            path = Cow::Owned(prefix_names(&path, "ns"));
+        }
+    }
+
+    let invalid =
+        |e: &dyn std::fmt::Display| Error::Custom(format!("invalid xpath '{}': {}", path, e));
+    let expr = Factory::new()
+        .build(&path)
+        .map_err(|e| invalid(&e))?
+        .ok_or_else(|| invalid(&"empty xpath"))?;
+    let value = expr
+        .evaluate(&context, document.root())
+        .map_err(|e| invalid(&e))?;
+
+    Ok(Some(value.string()).filter(|s| !s.is_empty()))
+}
+
+/// Qualify unprefixed element names of the location steps in `path` with
+/// `prefix`.
+fn prefix_names(path: &str, prefix: &str) -> String {
+    path.split('/')
+        .map(|step| {
+            let name = step.split('[').next().unwrap_or_default();
+            let is_name = name
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_alphabetic() || c == '_');
+            let is_qualified = name.contains(':') || name.contains('(');
+
+            match is_name && !is_qualified {
+                true => format!("{}:{}", prefix, step),
+                false => step.to_owned(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_json_path() {
+        let json = r#"{"tag_name":"v1.2.3","assets":[{"name":"a.zip","size":10}]}"#;
+        assert_eq!(json_path(json, "$.tag_name").unwrap().unwrap(), "v1.2.3");
+        assert_eq!(json_path(json, "$.assets[0].size").unwrap().unwrap(), "10");
+        assert_eq!(json_path(json, "$..name").unwrap().unwrap(), "a.zip");
+        assert_eq!(json_path(json, "$.missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_xpath() {
+        let xml = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+            <entry><title>v2.0</title></entry><entry><title>v1.0</title></entry>
+        </feed>"#;
+        assert_eq!(xpath(xml, "/feed/entry/title").unwrap().unwrap(), "v2.0");
+        assert_eq!(xpath(xml, "/feed/entry[2]/title").unwrap().unwrap(), "v1.0");
+
+        let xml = r#"<a:root xmlns:a="urn:a"><a:v>1.0</a:v></a:root>"#;
+        assert_eq!(xpath(xml, "//a:v").unwrap().unwrap(), "1.0");
+        assert_eq!(xpath(xml, "//a:missing").unwrap(), None);
+    }
+}
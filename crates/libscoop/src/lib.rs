@@ -33,6 +33,7 @@
 extern crate serde;
 
 mod bucket;
+mod bundle;
 mod cache;
 mod config;
 mod constant;
@@ -47,9 +48,16 @@ mod session;
 mod shim;
 mod shortcut;
 
+pub mod http;
 pub mod operation;
 
+pub use bundle::{BundleLock, BundlePackage};
+pub use cache::CacheState;
 pub use error::Error;
 pub use event::Event;
-pub use package::{QueryOption, SyncOption};
+pub use package::{
+    CheckverMatch, Diagnostic, DownloadState, PackageDownloadFileContext,
+    PackageDownloadOverallContext, PackageDownloadProgressContext, QueryOption, Severity,
+    SyncOption,
+};
 pub use session::Session;
@@ -18,20 +18,23 @@
 //! ```
 use chrono::{SecondsFormat, Utc};
 use futures::{executor::ThreadPool, task::SpawnExt};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashMap, HashSet},
     iter::FromIterator,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 use tracing::{debug, info};
 
 use crate::{
     bucket::{Bucket, BucketUpdateProgressContext},
-    cache::CacheFile,
+    bundle::BundleLock,
+    cache::{CacheFile, CacheState},
     error::{Error, Fallible},
     event::Event,
     internal, package,
-    package::{InstallInfo, Package, QueryOption},
+    package::{CheckverMatch, Diagnostic, InstallInfo, Package, QueryOption},
     Session, SyncOption,
 };
 
@@ -85,6 +88,41 @@ pub fn bucket_list(session: &Session) -> Fallible<Vec<Bucket>> {
     })
 }
 
+/// Lint manifests of a bucket.
+///
+/// `bucket` is either the name of an added bucket or the path to a bucket
+/// directory.
+///
+/// # Returns
+///
+/// Diagnostics of each manifest of the bucket, sorted by manifest paths.
+///
+/// # Errors
+///
+/// A [`BucketNotFound`][1] error will be returned if the bucket does not
+/// exist. I/O errors will be returned if the bucket directory is not readable.
+///
+/// [1]: crate::Error::BucketNotFound
+pub fn bucket_lint(session: &Session, bucket: &str) -> Fallible<Vec<(PathBuf, Vec<Diagnostic>)>> {
+    let path = Path::new(bucket);
+    let bucket = match path.is_dir() {
+        true => Bucket::from(path)?,
+        false => crate::bucket::bucket_added(session)?
+            .into_iter()
+            .find(|b| b.name() == bucket)
+            .ok_or_else(|| Error::BucketNotFound(bucket.to_owned()))?,
+    };
+
+    let mut ret = vec![];
+    for entry in bucket.manifests()? {
+        let path = entry.path();
+        let diagnostics = manifest_lint(&path)?;
+        ret.push((path, diagnostics));
+    }
+    ret.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(ret)
+}
+
 /// Get a list of known (built-in) buckets.
 ///
 /// # Returns
@@ -199,6 +237,73 @@ pub fn bucket_remove(session: &Session, name: &str) -> Fallible<()> {
     Ok(remove_dir_all::remove_dir_all(path.as_path())?)
 }
 
+/// Create an offline bundle of packages.
+///
+/// The given packages and their dependencies are resolved, downloaded if not
+/// cached yet, and written to a single archive at `path` along with the
+/// manifests used and a lock of versions and hashes.
+///
+/// # Returns
+///
+/// The lock of the bundled packages.
+///
+/// # Errors
+///
+/// A [`PackageNotFound`][1] error will be returned if no package is found for
+/// a query, and a [`HashMismatch`][2] error if a cached file is corrupted.
+///
+/// I/O errors will be returned if failed to write the bundle.
+///
+/// [1]: crate::Error::PackageNotFound
+/// [2]: crate::Error::HashMismatch
+pub fn bundle_create(session: &Session, queries: Vec<&str>, path: &Path) -> Fallible<BundleLock> {
+    let queries = HashSet::<&str>::from_iter(queries)
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    crate::bundle::create(session, &queries, path)
+}
+
+/// Install packages from an offline bundle.
+///
+/// Cache files of the bundle are verified and imported into the cache, and
+/// the bundled packages are then installed offline without network access.
+/// Installed packages are recorded under the buckets they were bundled from.
+///
+/// # Errors
+///
+/// An [`InvalidBundle`][1] error will be returned if the given file is not a
+/// valid bundle, and a [`HashMismatch`][2] error if a bundled file is
+/// corrupted.
+///
+/// [1]: crate::Error::InvalidBundle
+/// [2]: crate::Error::HashMismatch
+pub fn bundle_install(session: &Session, path: &Path, options: Vec<SyncOption>) -> Fallible<()> {
+    let (_, packages) = crate::bundle::import(session, path)?;
+
+    // all dependencies are bundled
+    let mut options = options;
+    options.push(SyncOption::Offline);
+    options.push(SyncOption::NoDependencies);
+
+    if let Some(tx) = session.emitter() {
+        let _ = tx.send(Event::PackageResolveStart);
+    }
+
+    package::sync::install_packages(session, packages, &options)?;
+
+    if session.config().cache_auto_prune() {
+        let removed = cache_prune(session)?;
+        info!("pruned {} cache file(s)", removed.len());
+    }
+
+    if let Some(tx) = session.emitter() {
+        let _ = tx.send(Event::PackageSyncDone);
+    }
+
+    Ok(())
+}
+
 /// Get a list of downloaded cache files.
 ///
 /// # Returns
@@ -272,6 +377,181 @@ pub fn cache_remove(session: &Session, query: &str) -> Fallible<()> {
     }
 }
 
+/// Verify cache files by query.
+///
+/// Each cache file is matched to the manifest of its package by name, version
+/// and the url hash part of its file name, and then checked against the hash
+/// defined in the manifest. Empty files are always considered corrupt.
+///
+/// # Note
+///
+/// Set `remove` to `true` to remove cache files that are not valid.
+///
+/// # Returns
+///
+/// A list of cache files along with their verification state.
+///
+/// # Errors
+///
+/// I/O errors will be returned if the cache directory is not readable or failed
+/// to remove the cache files.
+pub fn cache_verify(
+    session: &Session,
+    query: &str,
+    remove: bool,
+) -> Fallible<Vec<(CacheFile, CacheState)>> {
+    let files = cache_list(session, query)?;
+    if files.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let names = HashSet::<&str>::from_iter(files.iter().map(|f| f.package_name()))
+        .into_iter()
+        .collect::<Vec<_>>();
+    let packages = package::query::query_synced(session, &names, &[QueryOption::Explicit])?;
+
+    let cache_root = session.config().cache_path().to_owned();
+    let key = |file: &CacheFile| {
+        let (name, version) = (file.package_name(), file.version());
+        (
+            name.to_owned(),
+            version.to_owned(),
+            file.url_hash().to_owned(),
+        )
+    };
+
+    // expected hashes of cache files of current manifests, keyed by package
+    // name, version and url hash, `None` for files that can't be verified by
+    // hash
+    let mut known_names = HashSet::new();
+    let mut expected = HashMap::new();
+    for pkg in packages.iter() {
+        known_names.insert(pkg.name());
+
+        let nightly = pkg.version() == "nightly";
+        let hashes = pkg.download_hashes();
+        for (idx, filename) in pkg.download_filenames().into_iter().enumerate() {
+            let hash = hashes.get(idx).filter(|_| !nightly).map(|&h| h.clone());
+            let file = CacheFile::from(cache_root.join(filename))?;
+            expected.insert(key(&file), hash);
+        }
+    }
+
+    let ret = files
+        .into_par_iter()
+        .map(|file| {
+            let state = match expected.get(&key(&file)) {
+                None if known_names.contains(file.package_name()) => CacheState::Outdated,
+                None => CacheState::Orphaned,
+                Some(_) if file.path().metadata()?.len() == 0 => CacheState::Corrupt,
+                Some(None) => CacheState::Valid,
+                Some(Some(hash)) => match package::file_checksum(file.path(), hash)? {
+                    actual if actual == hash.value() => CacheState::Valid,
+                    _ => CacheState::Corrupt,
+                },
+            };
+
+            if remove && state != CacheState::Valid {
+                std::fs::remove_file(file.path())?;
+            }
+
+            Ok((file, state))
+        })
+        .collect::<Fallible<Vec<_>>>()?;
+
+    Ok(ret)
+}
+
+/// Prune cache files according to the cache retention policy.
+///
+/// The policy is defined by the following configs, all of them are optional:
+///
+/// - `cache_max_age`: files older than the given days are removed.
+/// - `cache_keep_versions`: only the given number of newest versions of each
+///   package are kept.
+/// - `cache_max_size`: least recently used files are removed until the total
+///   size of the cache fits in the budget.
+///
+/// # Returns
+///
+/// A list of removed cache files.
+///
+/// # Errors
+///
+/// I/O errors will be returned if the cache directory is not readable or failed
+/// to remove the cache files.
+pub fn cache_prune(session: &Session) -> Fallible<Vec<CacheFile>> {
+    let config = session.config();
+    let max_age = config.cache_max_age();
+    let keep_versions = config.cache_keep_versions();
+    let max_size = config.cache_max_size();
+    drop(config);
+
+    let mut files = vec![];
+    for file in cache_list(session, "*")? {
+        let metadata = file.path().metadata()?;
+        let modified = metadata.modified()?;
+        let used = metadata.accessed().unwrap_or(modified).max(modified);
+        files.push((file, metadata.len(), modified, used));
+    }
+
+    let mut removed = vec![];
+
+    if let Some(max_age) = max_age {
+        let now = std::time::SystemTime::now();
+        let (expired, rest): (Vec<_>, Vec<_>) =
+            files.into_iter().partition(|(_, _, modified, _)| {
+                now.duration_since(*modified).unwrap_or_default() > max_age
+            });
+        removed.extend(expired);
+        files = rest;
+    }
+
+    if let Some(keep_versions) = keep_versions {
+        let mut versions = HashMap::<String, Vec<String>>::new();
+        for (file, _, _, _) in files.iter() {
+            let entry = versions.entry(file.package_name().to_owned()).or_default();
+            if !entry.iter().any(|v| v == file.version()) {
+                entry.push(file.version().to_owned());
+            }
+        }
+
+        for versions in versions.values_mut() {
+            versions.sort_by(|a, b| internal::compare_versions(b, a));
+            versions.truncate(keep_versions as usize);
+        }
+
+        let (kept, outdated): (Vec<_>, Vec<_>) = files.into_iter().partition(|(file, _, _, _)| {
+            versions[file.package_name()]
+                .iter()
+                .any(|v| v == file.version())
+        });
+        removed.extend(outdated);
+        files = kept;
+    }
+
+    if let Some(max_size) = max_size {
+        // evict least recently used files first
+        files.sort_by_key(|(_, _, _, used)| std::cmp::Reverse(*used));
+
+        let mut total = 0;
+        let (_, evicted): (Vec<_>, Vec<_>) = files.into_iter().partition(|(_, size, _, _)| {
+            total += size;
+            total <= max_size
+        });
+        removed.extend(evicted);
+    }
+
+    let mut ret = vec![];
+    for (file, _, _, _) in removed {
+        debug!("pruning cache file {}", file.file_name());
+        std::fs::remove_file(file.path())?;
+        ret.push(file);
+    }
+
+    Ok(ret)
+}
+
 /// Get the configuation list.
 ///
 /// # Returns
@@ -337,6 +617,143 @@ pub fn package_hold(session: &Session, name: &str, flag: bool) -> Fallible<()> {
     }
 }
 
+/// Detect the latest upstream version of a package.
+///
+/// The version is detected with the `checkver` spec of the package manifest,
+/// the url checked is fetched with the HTTP client of the session.
+///
+/// # Returns
+///
+/// The detected version along with the captured groups of the match.
+///
+/// # Errors
+///
+/// A [`Checkver`][1] error will be returned if the manifest has no checkver,
+/// the checkver is not supported, or no version is matched. An [`Http`][2]
+/// error will be returned if failed to fetch the url.
+///
+/// [1]: crate::Error::Checkver
+/// [2]: crate::Error::Http
+pub fn package_checkver(session: &Session, package: &Package) -> Fallible<CheckverMatch> {
+    package::checkver::checkver(session, package.manifest())
+}
+
+/// Update the manifest of the given package to `version` with its autoupdate
+/// spec.
+///
+/// `captures` are the captured groups of the checkver match, if any, see
+/// [`CheckverMatch::captures`]. The manifest file is rewritten, while fields
+/// not being updated and the formatting of the file are preserved.
+///
+/// # Errors
+///
+/// An [`Autoupdate`][1] error will be returned if the manifest has no
+/// autoupdate. An [`Http`][2] error will be returned if failed to download
+/// files to compute their hashes.
+///
+/// [1]: crate::Error::Autoupdate
+/// [2]: crate::Error::Http
+pub fn package_autoupdate(
+    session: &Session,
+    package: &Package,
+    version: &str,
+    captures: Option<&BTreeMap<String, String>>,
+) -> Fallible<()> {
+    let manifest = package.manifest();
+    let text = package::autoupdate::autoupdate(session, manifest, version, captures)?;
+    std::fs::write(manifest.path(), text)?;
+    Ok(())
+}
+
+/// Run `f` on each of the given packages concurrently.
+///
+/// This is meant for operations mostly waiting for the network, such as
+/// [`package_checkver`] and [`package_autoupdate`] of a whole bucket. `f` is
+/// given a session sharing the config, the user agent and the HTTP client of
+/// `session`, events emitted through it are not delivered.
+///
+/// # Returns
+///
+/// The packages along with the results of `f`, in the given order.
+pub fn package_concurrent_map<F, R>(
+    session: &Session,
+    packages: Vec<Package>,
+    f: F,
+) -> Vec<(Package, R)>
+where
+    F: Fn(&Session, &Package) -> R + Sync,
+    R: Send,
+{
+    let workers = (session.config().download_max_connections() as usize).min(packages.len());
+    let queue = Mutex::new(packages.into_iter().enumerate().collect::<Vec<_>>());
+    let results = Mutex::new(Vec::new());
+    let sessions = (0..workers).map(|_| session.fork()).collect::<Vec<_>>();
+
+    std::thread::scope(|s| {
+        for session in sessions {
+            s.spawn(|| {
+                let session = session;
+                loop {
+                    let next = queue.lock().unwrap().pop();
+                    let (idx, package) = match next {
+                        Some(job) => job,
+                        None => break,
+                    };
+                    let ret = f(&session, &package);
+                    results.lock().unwrap().push((idx, package, ret));
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(idx, ..)| *idx);
+    results
+        .into_iter()
+        .map(|(_, package, ret)| (package, ret))
+        .collect()
+}
+
+/// Commit the manifest of the given package to its bucket repository.
+///
+/// Only the manifest file is committed, other changes of the bucket are left
+/// as is.
+///
+/// # Errors
+///
+/// A [`Git`][1] error will be returned if the bucket is not a git repository
+/// or failed to commit, such as the git user is not configured.
+///
+/// [1]: crate::Error::Git
+pub fn package_commit(package: &Package, message: &str) -> Fallible<()> {
+    internal::git::commit_file(package.manifest().path(), message)
+}
+
+/// Lint the manifest at the given path.
+///
+/// Manifests failing to parse are linted as well, see [`Diagnostic`] for the
+/// problems found.
+///
+/// # Returns
+///
+/// Diagnostics of the problems found, empty if the manifest looks good.
+///
+/// # Errors
+///
+/// I/O errors will be returned if the manifest file is not readable.
+pub fn manifest_lint(path: &Path) -> Fallible<Vec<Diagnostic>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(package::lint::lint(&text))
+}
+
+/// Compare two package versions.
+///
+/// Versions are compared part by part, numerically where both parts are
+/// numbers, e.g. `1.10` is greater than `1.9`.
+pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    internal::compare_versions(a, b)
+}
+
 /// Query packages.
 ///
 /// # Note
@@ -419,9 +836,219 @@ pub fn package_sync(
         package::sync::install(session, &queries, &options)?;
     }
 
+    if session.config().cache_auto_prune() {
+        let removed = cache_prune(session)?;
+        info!("pruned {} cache file(s)", removed.len());
+    }
+
     if let Some(tx) = session.emitter() {
         let _ = tx.send(Event::PackageSyncDone);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{package::manifest::Manifest, session::test};
+
+    /// Add package `name` of `version` downloading a file of `content`.
+    ///
+    /// # Returns
+    ///
+    /// Cache filename of the package file.
+    fn add_package(session: &Session, name: &str, version: &str, content: &str) -> String {
+        let mut checksum = scoop_hash::ChecksumBuilder::new().sha256().build();
+        checksum.consume(content.as_bytes());
+        let json = serde_json::json!({
+            "version": version,
+            "homepage": "https://example.com",
+            "license": "MIT",
+            "url": format!("https://example.com/{}.zip", name),
+            "hash": checksum.finalize(),
+        });
+        let path = test::add_manifest(session, "main", name, &json);
+        let package = Package::from(name, "main", Manifest::parse(path).unwrap());
+        package.download_filenames().pop().unwrap()
+    }
+
+    /// Put a file of `content` into the cache directory.
+    fn add_cache(session: &Session, filename: &str, content: &str) {
+        let dir = session.config().cache_path().to_owned();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(filename), content).unwrap();
+    }
+
+    #[test]
+    fn test_cache_verify() {
+        let session = test::temp_session("cache-verify", serde_json::json!({}));
+        let valid = add_package(&session, "foo", "1.0", "foo");
+        let corrupt = add_package(&session, "bar", "1.0", "bar");
+        let empty = add_package(&session, "baz", "1.0", "");
+        add_cache(&session, &valid, "foo");
+        add_cache(&session, &corrupt, "rab");
+        add_cache(&session, &empty, "");
+        // a previous version, and a previous url of the current version
+        let outdated = valid.replace("#1.0#", "#0.9#");
+        let moved = format!("foo#1.0#{}.zip", "0".repeat(7));
+        add_cache(&session, &outdated, "foo");
+        add_cache(&session, &moved, "foo");
+        // no manifest of the package
+        let orphaned = format!("qux#1.0#{}.zip", "0".repeat(7));
+        add_cache(&session, &orphaned, "qux");
+
+        let states = |remove: bool| {
+            let mut states = cache_verify(&session, "*", remove)
+                .unwrap()
+                .into_iter()
+                .map(|(file, state)| (file.file_name().to_owned(), state))
+                .collect::<Vec<_>>();
+            states.sort_by(|a, b| a.0.cmp(&b.0));
+            states
+        };
+
+        let mut expected = vec![
+            (valid.clone(), CacheState::Valid),
+            (corrupt, CacheState::Corrupt),
+            (empty, CacheState::Corrupt),
+            (outdated, CacheState::Outdated),
+            (moved, CacheState::Outdated),
+            (orphaned, CacheState::Orphaned),
+        ];
+        expected.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(states(false), expected);
+        // nothing is removed without the remove option
+        assert_eq!(states(false), expected);
+
+        assert_eq!(states(true), expected);
+        assert_eq!(states(false), vec![(valid, CacheState::Valid)]);
+    }
+
+    #[test]
+    fn test_cache_prune() {
+        let hour = std::time::Duration::from_secs(60 * 60);
+        let prune = |name: &str, config: serde_json::Value, files: &[(&str, u32)]| {
+            let session = test::temp_session(name, config);
+            let now = std::time::SystemTime::now();
+            for &(filename, hours) in files {
+                add_cache(&session, filename, "foo");
+                let path = session.config().cache_path().join(filename);
+                let file = std::fs::File::options().write(true).open(path).unwrap();
+                let time = now - hour * hours;
+                let times = std::fs::FileTimes::new()
+                    .set_accessed(time)
+                    .set_modified(time);
+                file.set_times(times).unwrap();
+            }
+
+            let mut removed = cache_prune(&session)
+                .unwrap()
+                .into_iter()
+                .map(|file| file.file_name().to_owned())
+                .collect::<Vec<_>>();
+            removed.sort();
+            let mut kept = cache_list(&session, "*")
+                .unwrap()
+                .into_iter()
+                .map(|file| file.file_name().to_owned())
+                .collect::<Vec<_>>();
+            kept.sort();
+            (removed, kept)
+        };
+
+        // nothing is pruned without a policy
+        let files = [("foo#1.0#abcdef0.zip", 0), ("foo#2.0#abcdef0.zip", 0)];
+        let (removed, kept) = prune("cache-prune", serde_json::json!({}), &files);
+        assert!(removed.is_empty());
+        assert_eq!(kept.len(), 2);
+
+        // only the newest versions of each package are kept
+        let files = [
+            ("foo#1.9#abcdef0.zip", 0),
+            ("foo#1.10#abcdef0.zip", 0),
+            ("foo#1.10#1234567.zip", 0),
+            ("foo#1.2#abcdef0.zip", 0),
+            ("bar#1.0#abcdef0.zip", 0),
+        ];
+        let config = serde_json::json!({ "cache_keep_versions": 2 });
+        let (removed, kept) = prune("cache-prune-versions", config, &files);
+        assert_eq!(removed, ["foo#1.2#abcdef0.zip"]);
+        assert_eq!(kept.len(), 4);
+
+        // files older than the max age are removed
+        let files = [("foo#1.0#abcdef0.zip", 49), ("foo#2.0#abcdef0.zip", 47)];
+        let config = serde_json::json!({ "cache_max_age": 2 });
+        let (removed, kept) = prune("cache-prune-age", config, &files);
+        assert_eq!(removed, ["foo#1.0#abcdef0.zip"]);
+        assert_eq!(kept, ["foo#2.0#abcdef0.zip"]);
+
+        // least recently used files are evicted to fit in the budget
+        let files = [
+            ("foo#1.0#abcdef0.zip", 3),
+            ("bar#1.0#abcdef0.zip", 1),
+            ("baz#1.0#abcdef0.zip", 2),
+            ("qux#1.0#abcdef0.zip", 4),
+        ];
+        let config = serde_json::json!({ "cache_max_size": "7" });
+        let (removed, kept) = prune("cache-prune-size", config, &files);
+        assert_eq!(removed, ["foo#1.0#abcdef0.zip", "qux#1.0#abcdef0.zip"]);
+        assert_eq!(kept, ["bar#1.0#abcdef0.zip", "baz#1.0#abcdef0.zip"]);
+    }
+
+    #[test]
+    fn test_package_concurrent_map() {
+        let config = serde_json::json!({ "download_max_connections": 3 });
+        let session = test::temp_session("concurrent-map", config);
+        let _ = session.set_user_agent("hok-test");
+
+        let mut pages = vec![];
+        let mut packages = vec![];
+        for idx in 0..10 {
+            let name = format!("app{}", idx);
+            let url = format!("https://example.com/{}", name);
+            let json = serde_json::json!({
+                "version": "1.0",
+                "homepage": "https://example.com",
+                "license": "MIT",
+                "checkver": { "url": url, "regex": "v([\\d.]+)" },
+            });
+            let path = test::add_manifest(&session, "main", &name, &json);
+            packages.push(Package::from(&name, "main", Manifest::parse(path).unwrap()));
+            pages.push((url, format!("v2.{}", idx)));
+        }
+        let pages = pages.iter().map(|(u, p)| (u.as_str(), p.as_str()));
+        session
+            .set_http_client(crate::http::test::Server::pages(pages))
+            .unwrap();
+
+        let threads = Mutex::new(HashSet::new());
+        let ret = package_concurrent_map(&session, packages, |session, package| {
+            threads.lock().unwrap().insert(std::thread::current().id());
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            assert_eq!(session.user_agent.get().unwrap(), "hok-test");
+            package_checkver(session, package).map(|m| m.version().to_owned())
+        });
+
+        assert_eq!(ret.len(), 10);
+        for (idx, (package, version)) in ret.into_iter().enumerate() {
+            assert_eq!(package.name(), format!("app{}", idx));
+            assert_eq!(version.unwrap(), format!("2.{}", idx));
+        }
+        let threads = threads.into_inner().unwrap();
+        assert!((2..=3).contains(&threads.len()), "{:?}", threads);
+        assert!(!threads.contains(&std::thread::current().id()));
+
+        assert!(package_concurrent_map(&session, vec![], |_, _| ()).is_empty());
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        use std::cmp::Ordering;
+
+        assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.9", "1.10"), Ordering::Less);
+        assert_eq!(compare_versions("2.0", "2.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.2.1", "1.2"), Ordering::Greater);
+    }
+}
@@ -0,0 +1,774 @@
+//! Update the manifest of a package to a new version from the `autoupdate`
+//! spec of its manifest.
+//!
+//! Fields of the `autoupdate` spec, with the `$version` family variables and
+//! the `$match<Name>` variables of checkver captures substituted, are written
+//! to the manifest, so are those of `autoupdate.architecture` to the
+//! corresponding architectures. Hashes of the new urls are found with the
+//! `hash` extractions of the spec, such as from checksum files, JSON or XML
+//! documents, RDF and metalink files, FossHub and SourceForge pages, and are
+//! computed by downloading the files if not found.
+//!
+//! The manifest is edited in place, fields not being updated and the
+//! formatting of the manifest are left untouched.
+use regex::{Regex, RegexBuilder};
+use scoop_hash::{Algorithm, ChecksumBuilder};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tracing::{debug, warn};
+
+use crate::{
+    constant::{REGEX_FOSSHUB_URL, REGEX_SOURCEFORGE_URL},
+    error::{Error, Fallible},
+    internal, Session,
+};
+
+use super::manifest::{HashExtraction, HashExtractionMode, HashString, Manifest};
+
+/// Get the variables to be substituted for the given `version` and checkver
+/// `captures`.
+///
+/// Variables are sorted by their lengths in descending order, so that a
+/// variable is never substituted partially by a shorter one.
+pub(crate) fn substitutions(
+    version: &str,
+    captures: Option<&BTreeMap<String, String>>,
+) -> Vec<(String, String)> {
+    let separators = Regex::new(r"[._-]").unwrap();
+    let first = version.split('-').next().unwrap_or_default();
+    let last = version.rsplit('-').next().unwrap_or_default();
+    let mut parts = first.split('.');
+
+    let mut subs = vec![
+        ("$version", version.to_owned()),
+        ("$dotVersion", separators.replace_all(version, ".").into()),
+        (
+            "$underscoreVersion",
+            separators.replace_all(version, "_").into(),
+        ),
+        ("$dashVersion", separators.replace_all(version, "-").into()),
+        ("$cleanVersion", separators.replace_all(version, "").into()),
+        ("$majorVersion", parts.next().unwrap_or_default().to_owned()),
+        ("$minorVersion", parts.next().unwrap_or_default().to_owned()),
+        ("$patchVersion", parts.next().unwrap_or_default().to_owned()),
+        ("$buildVersion", parts.next().unwrap_or_default().to_owned()),
+        ("$preReleaseVersion", last.to_owned()),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_owned(), v))
+    .collect::<Vec<_>>();
+
+    let re = Regex::new(r"(?<head>\d+\.\d+(?:\.\d+)?)(?<tail>.*)").unwrap();
+    if let Some(caps) = re.captures(version) {
+        subs.push(("$matchHead".to_owned(), caps["head"].to_owned()));
+        subs.push(("$matchTail".to_owned(), caps["tail"].to_owned()));
+    }
+
+    for (name, value) in captures.into_iter().flatten() {
+        let mut chars = name.chars();
+        let name = match chars.next() {
+            Some(c) => c.to_uppercase().chain(chars).collect::<String>(),
+            None => continue,
+        };
+        let key = format!("$match{}", name);
+        subs.retain(|(k, _)| k != &key);
+        subs.push((key, value.to_owned()));
+    }
+
+    subs.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+    subs
+}
+
+/// Substitute the variables `subs` in `text`.
+pub(crate) fn substitute(text: &str, subs: &[(String, String)]) -> String {
+    subs.iter()
+        .fold(text.to_owned(), |text, (k, v)| text.replace(k, v))
+}
+
+/// Substitute the variables `subs` in all strings of the JSON `value`.
+fn substitute_value(value: &Value, subs: &[(String, String)]) -> Value {
+    match value {
+        Value::String(s) => Value::String(substitute(s, subs)),
+        Value::Array(a) => Value::Array(a.iter().map(|v| substitute_value(v, subs)).collect()),
+        Value::Object(o) => Value::Object(
+            o.iter()
+                .map(|(k, v)| (k.to_owned(), substitute_value(v, subs)))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+/// Get the strings of a single string or an array of strings.
+fn strings(value: Option<&Value>) -> Vec<&str> {
+    match value {
+        Some(Value::String(s)) => vec![s.as_str()],
+        Some(Value::Array(a)) => a.iter().filter_map(|v| v.as_str()).collect(),
+        _ => vec![],
+    }
+}
+
+/// Compute the hash of the file at `url` with `algo` by downloading it.
+fn download_hash(session: &Session, url: &str, algo: Algorithm) -> Fallible<String> {
+    let url = strip_fragment(url);
+    debug!("computing {} hash of {}", algo, url);
+
+    let mut checksum = ChecksumBuilder::new().algo(algo).build();
+    super::download::fetch_with(session, url, None, &mut |data| {
+        checksum.consume(data);
+        Ok(())
+    })?;
+
+    let hex = checksum.finalize();
+    Ok(match algo {
+        Algorithm::Sha256 => hex,
+        algo => format!("{}:{}", algo, hex),
+    })
+}
+
+/// Strip the renaming fragment of `url`.
+fn strip_fragment(url: &str) -> &str {
+    url.split_once('#').map(|(url, _)| url).unwrap_or(url)
+}
+
+/// Format the hash found in a hash file as what manifests use, namely bare
+/// hex for SHA-256 and prefixed with the algorithm otherwise.
+///
+/// The algorithm of an unprefixed hash is guessed from its length.
+fn format_hash(hash: &str) -> Option<String> {
+    let hash = hash.trim().to_lowercase();
+    let hash = match (hash.contains(':'), hash.len()) {
+        (true, _) | (false, 64) => hash,
+        (false, 32) => format!("md5:{}", hash),
+        (false, 40) => format!("sha1:{}", hash),
+        (false, 96) => format!("sha384:{}", hash),
+        (false, 128) => format!("sha512:{}", hash),
+        _ => return None,
+    };
+    match HashString::new(&hash).ok()? {
+        HashString::Sha256(hash) => Some(hash),
+        hash => Some(hash.to_string()),
+    }
+}
+
+/// Get the variables of the download `url` to be substituted in the hash
+/// extraction, along with the given `subs`.
+fn url_substitutions(url: &str, subs: &[(String, String)]) -> Vec<(String, String)> {
+    let url = strip_fragment(url);
+    let (baseurl, basename) = url.rsplit_once('/').unwrap_or(("", url));
+    let basename = basename.split('?').next().unwrap_or_default();
+    let basename_no_ext = basename
+        .rsplit_once('.')
+        .map(|(name, _)| name)
+        .unwrap_or(basename);
+    let url_no_ext = format!("{}/{}", baseurl, basename_no_ext);
+
+    let mut subs = subs.to_vec();
+    subs.extend(
+        [
+            ("$url", url),
+            ("$baseurl", baseurl),
+            ("$basename", basename),
+            ("$urlNoExt", &url_no_ext),
+            ("$basenameNoExt", basename_no_ext),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_owned(), v.to_owned())),
+    );
+    subs.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+    subs
+}
+
+/// Find the hash in `text` with `regex`, in which the variables `subs` are
+/// substituted as literals.
+///
+/// The `$checksum` variable matches a hex hash. A hash on the same line as
+/// the `$basename` is looked for if `regex` matches nothing.
+fn find_hash_in_text(
+    text: &str,
+    regex: Option<&str>,
+    subs: &[(String, String)],
+) -> Fallible<Option<String>> {
+    let mut subs = subs
+        .iter()
+        .map(|(k, v)| (k.to_owned(), regex::escape(v)))
+        .collect::<Vec<_>>();
+    subs.push(("$checksum".to_owned(), "([a-fA-F0-9]{32,128})".to_owned()));
+    subs.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+
+    let regex = regex.unwrap_or(r"^\s*([a-fA-F0-9]+)\s*$");
+    let fallback = r"([a-fA-F0-9]{32,128})[\x20\t]+.*$basename(?:\s|$)|$basename[\x20\t]+.*?([a-fA-F0-9]{32,128})";
+
+    for regex in [regex, fallback] {
+        let re = RegexBuilder::new(&substitute(regex, &subs))
+            .multi_line(true)
+            .build()?;
+        let found = re.captures(text).and_then(|caps| {
+            caps.iter()
+                .skip(1)
+                .flatten()
+                .next()
+                .map(|m| m.as_str().to_owned())
+        });
+        if found.is_some() {
+            return Ok(found);
+        }
+    }
+    Ok(None)
+}
+
+/// Detect the mode of the hash `extraction` for the download `url`.
+fn extraction_mode(extraction: &HashExtraction, url: &str) -> HashExtractionMode {
+    if let Some(mode) = &extraction.mode {
+        return mode.clone();
+    }
+
+    if extraction.jsonpath.is_some() {
+        HashExtractionMode::Json
+    } else if extraction.xpath.is_some() {
+        HashExtractionMode::Xpath
+    } else if extraction.find.is_some() || extraction.regex.is_some() || extraction.url.is_some() {
+        HashExtractionMode::Extract
+    } else if REGEX_FOSSHUB_URL.is_match(url) {
+        HashExtractionMode::Fosshub
+    } else if REGEX_SOURCEFORGE_URL.is_match(url) {
+        HashExtractionMode::Sourceforge
+    } else {
+        HashExtractionMode::Download
+    }
+}
+
+/// Quote `s` as an XPath string literal.
+///
+/// XPath 1.0 has no escapes in string literals, a string containing both
+/// kinds of quotes is built with `concat()`.
+fn xpath_literal(s: &str) -> String {
+    if !s.contains('\'') {
+        format!("'{}'", s)
+    } else if !s.contains('"') {
+        format!("\"{}\"", s)
+    } else {
+        let parts = s
+            .split('\'')
+            .map(|part| format!("'{}'", part))
+            .collect::<Vec<_>>();
+        format!("concat({})", parts.join(r#", "'", "#))
+    }
+}
+
+/// Find the hash of the file at `url` with the hash `extraction`, without
+/// downloading the file.
+///
+/// # Returns
+///
+/// The formatted hash, `None` if it is not found, the page to find the hash
+/// in fails to be fetched, or the extraction mode is `download`.
+///
+/// # Errors
+///
+/// Errors of the extraction itself, e.g. an invalid regex or path, or a
+/// malformed document.
+fn find_hash(
+    session: &Session,
+    url: &str,
+    extraction: &HashExtraction,
+    subs: &[(String, String)],
+) -> Fallible<Option<String>> {
+    let mode = extraction_mode(extraction, url);
+    let subs = url_substitutions(url, subs);
+    let hashfile = match &extraction.url {
+        Some(hashfile) => substitute(hashfile, &subs),
+        None => strip_fragment(url).to_owned(),
+    };
+    // extract the hash from the page at `url` with `f`
+    let extract = |url: &str, f: &dyn Fn(&str) -> Fallible<Option<String>>| {
+        debug!("finding hash in {}", url);
+        match super::download::fetch(session, url, None) {
+            Ok(text) => f(&String::from_utf8_lossy(&text)),
+            Err(e) => {
+                warn!("failed to fetch {} (err: {})", url, e);
+                Ok(None)
+            }
+        }
+    };
+    let basename = xpath_literal(&substitute("$basename", &subs));
+
+    let found = match mode {
+        HashExtractionMode::Download => None,
+        HashExtractionMode::Extract => {
+            let regex = extraction.find.as_deref().or(extraction.regex.as_deref());
+            extract(&hashfile, &|text| find_hash_in_text(text, regex, &subs))?
+        }
+        HashExtractionMode::Json => {
+            let path = substitute(extraction.jsonpath.as_deref().unwrap_or("$"), &subs);
+            extract(&hashfile, &|text| internal::select::json_path(text, &path))?
+        }
+        HashExtractionMode::Xpath => {
+            let path = substitute(extraction.xpath.as_deref().unwrap_or("/"), &subs);
+            extract(&hashfile, &|text| internal::select::xpath(text, &path))?
+        }
+        HashExtractionMode::Rdf => {
+            let path = format!(
+                "//*[local-name()='Content'][@*[local-name()='about']={}]/*[local-name()='sha256']",
+                basename
+            );
+            extract(&hashfile, &|text| internal::select::xpath(text, &path))?
+        }
+        HashExtractionMode::Metalink => {
+            let hashfile = match &extraction.url {
+                Some(_) => hashfile,
+                None => format!("{}.meta4", hashfile),
+            };
+            let path = format!(
+                "//*[local-name()='file'][@name={}]/*[local-name()='hash'][@type='sha-256']",
+                basename
+            );
+            extract(&hashfile, &|text| internal::select::xpath(text, &path))?
+        }
+        HashExtractionMode::Fosshub => match REGEX_FOSSHUB_URL.captures(&hashfile) {
+            Some(caps) => {
+                let regex = format!(
+                    r#"{}.*?"sha256":"([a-fA-F0-9]{{64}})""#,
+                    regex::escape(&caps["filename"])
+                );
+                extract(&hashfile, &|text| {
+                    find_hash_in_text(text, Some(&regex), &subs)
+                })?
+            }
+            None => None,
+        },
+        HashExtractionMode::Sourceforge => match REGEX_SOURCEFORGE_URL.captures(&hashfile) {
+            Some(caps) => {
+                let file = caps["file"].rsplit_once('/').map(|(dir, _)| dir);
+                let page = format!(
+                    "https://sourceforge.net/projects/{}/files/{}",
+                    &caps["project"],
+                    file.unwrap_or_default()
+                );
+                let regex = r#""$basename":.*?"sha1":\s*"([a-fA-F0-9]{40})""#;
+                extract(page.trim_end_matches('/'), &|text| {
+                    find_hash_in_text(text, Some(regex), &subs)
+                })?
+            }
+            None => None,
+        },
+    };
+
+    Ok(found.and_then(|hash| format_hash(&hash)))
+}
+
+/// Get the hash of the file at `url`.
+///
+/// The hash is found with the hash `extraction` if given, and the file is
+/// downloaded to compute the hash with `algo` if the hash is not found.
+///
+/// # Errors
+///
+/// Errors of the hash extraction are returned rather than falling back to
+/// downloading the file, for they mean the extraction is broken.
+fn hash(
+    session: &Session,
+    url: &str,
+    extraction: Option<&HashExtraction>,
+    algo: Algorithm,
+    subs: &[(String, String)],
+) -> Fallible<String> {
+    let default = HashExtraction::default();
+    let extraction = extraction.unwrap_or(&default);
+    match find_hash(session, url, extraction, subs)? {
+        Some(hash) => Ok(hash),
+        None => download_hash(session, url, algo),
+    }
+}
+
+/// Parse the `hash` of an autoupdate spec, either a single hash extraction or
+/// an array of them.
+fn extractions(value: Option<&Value>) -> Fallible<Vec<HashExtraction>> {
+    let values = match value {
+        Some(Value::Array(values)) => values.iter().collect(),
+        Some(value) => vec![value],
+        None => vec![],
+    };
+    let mut ret = vec![];
+    for value in values {
+        ret.push(HashExtraction::deserialize(value)?);
+    }
+    Ok(ret)
+}
+
+/// Edits of the manifest, pairs of the object keys path and the new value.
+type Edits = Vec<(Vec<String>, Value)>;
+
+/// Collect edits of the manifest node at `path` from the autoupdate `spec`.
+///
+/// `current` is the node being updated, used to keep the algorithms of the
+/// existing hashes. Hash extractions are paired with urls by their indices if
+/// their counts match, otherwise the first extraction applies to all urls.
+fn collect(
+    session: &Session,
+    spec: &serde_json::Map<String, Value>,
+    extraction: Option<&Value>,
+    current: Option<&Value>,
+    path: &[&str],
+    subs: &[(String, String)],
+    edits: &mut Edits,
+) -> Fallible<()> {
+    let key_path = |key: &str| {
+        path.iter()
+            .copied()
+            .chain(std::iter::once(key))
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+    };
+
+    for (key, value) in spec {
+        if key == "architecture" || key == "hash" {
+            continue;
+        }
+        edits.push((key_path(key), substitute_value(value, subs)));
+    }
+
+    let url = match spec.get("url") {
+        Some(url) => substitute_value(url, subs),
+        None => return Ok(()),
+    };
+    let urls = strings(Some(&url));
+    let current_hashes = strings(current.and_then(|c| c.get("hash")));
+    let extractions = extractions(extraction)?;
+
+    let mut hashes = vec![];
+    for (idx, url) in urls.iter().enumerate() {
+        let algo = current_hashes
+            .get(idx)
+            .and_then(|h| HashString::new(h).ok())
+            .map(|h| h.algorithm())
+            .unwrap_or(Algorithm::Sha256);
+        let extraction = match extractions.len() == urls.len() {
+            true => extractions.get(idx),
+            false => extractions.first(),
+        };
+        let hash = hash(session, url, extraction, algo, subs)?;
+        hashes.push(Value::String(hash));
+    }
+
+    let hash = match (url, hashes.len()) {
+        (Value::String(_), 1) => hashes.remove(0),
+        _ => Value::Array(hashes),
+    };
+    edits.push((key_path("hash"), hash));
+    Ok(())
+}
+
+/// Update the given manifest to `version` with its autoupdate spec.
+///
+/// `captures` are the captured groups of the checkver match, if any, which
+/// are substituted as the `$match<Name>` variables.
+///
+/// # Returns
+///
+/// The text of the updated manifest.
+///
+/// # Errors
+///
+/// An [`Autoupdate`][1] error will be returned if the manifest has no
+/// autoupdate or failed to be edited. An [`Http`][2] error will be returned
+/// if failed to download files to compute their hashes.
+///
+/// [1]: crate::Error::Autoupdate
+/// [2]: crate::Error::Http
+pub(crate) fn autoupdate(
+    session: &Session,
+    manifest: &Manifest,
+    version: &str,
+    captures: Option<&BTreeMap<String, String>>,
+) -> Fallible<String> {
+    let text = std::fs::read_to_string(manifest.path())?;
+    let json = serde_json::from_str::<Value>(&text)?;
+    let spec = json
+        .get("autoupdate")
+        .and_then(|v| v.as_object())
+        .ok_or_else(|| Error::Autoupdate("autoupdate is not defined".to_owned()))?;
+
+    let subs = substitutions(version, captures);
+    let mut edits = vec![(vec!["version".to_owned()], Value::from(version))];
+
+    let extraction = spec.get("hash");
+    collect(
+        session,
+        spec,
+        extraction,
+        Some(&json),
+        &[],
+        &subs,
+        &mut edits,
+    )?;
+    if let Some(archs) = spec.get("architecture").and_then(|v| v.as_object()) {
+        for (arch, spec) in archs {
+            let spec = match spec.as_object() {
+                Some(spec) => spec,
+                None => continue,
+            };
+            let extraction = spec.get("hash").or(extraction);
+            let current = json.get("architecture").and_then(|a| a.get(arch));
+            let path = ["architecture", arch.as_str()];
+            collect(session, spec, extraction, current, &path, &subs, &mut edits)?;
+        }
+    }
+
+    let mut text = text;
+    for (path, value) in edits {
+        let path = path.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+        text = internal::json::set(&text, &path, &value).ok_or_else(|| {
+            let msg = format!("could not set '{}'", path.join("."));
+            Error::Autoupdate(msg)
+        })?;
+    }
+
+    Ok(text)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::http::test::Server;
+
+    #[test]
+    fn test_substitutions() {
+        let captures = BTreeMap::from([
+            ("1".to_owned(), "2024".to_owned()),
+            ("build".to_owned(), "42".to_owned()),
+        ]);
+        let subs = substitutions("1.2.3.4-beta", Some(&captures));
+        let sub = |text: &str| substitute(text, &subs);
+
+        assert_eq!(sub("$version"), "1.2.3.4-beta");
+        assert_eq!(sub("$dotVersion"), "1.2.3.4.beta");
+        assert_eq!(sub("$underscoreVersion"), "1_2_3_4_beta");
+        assert_eq!(sub("$dashVersion"), "1-2-3-4-beta");
+        assert_eq!(sub("$cleanVersion"), "1234beta");
+        assert_eq!(
+            sub("$majorVersion/$minorVersion/$patchVersion/$buildVersion"),
+            "1/2/3/4"
+        );
+        assert_eq!(sub("$preReleaseVersion"), "beta");
+        assert_eq!(sub("$matchHead|$matchTail"), "1.2.3|.4-beta");
+        assert_eq!(sub("$match1-$matchBuild"), "2024-42");
+    }
+
+    #[test]
+    fn test_hash() {
+        let sha256 = "d2e53aff4d5685340fe37d6637751e025018c38771d868e9bd0cba2d6b0ea25f";
+        let sha1 = "2fd4e1c67a2d28fced849ee1bb76e7391b93eb12";
+        let sums = format!("{}  other.zip\n{}  foo-1.0.zip\n", sha1, sha256);
+        let json = format!(
+            r#"{{"assets":{{"foo-1.0.zip":{{"sha256":"{}"}}}}}}"#,
+            sha256
+        );
+        let xml = format!(
+            r#"<files xmlns="urn:x"><file><sha1>{}</sha1></file></files>"#,
+            sha1
+        );
+        let rdf = format!(
+            r#"<r:RDF xmlns:r="http://www.w3.org/1999/02/22-rdf-syntax-ns#" xmlns="urn:c">
+                <Content r:about="foo-1.0.zip"><sha256>{}</sha256></Content></r:RDF>"#,
+            sha256.to_uppercase()
+        );
+        let meta4 = format!(
+            r#"<metalink xmlns="urn:ietf:params:xml:ns:metalink">
+                <file name="foo-1.0.zip"><hash type="sha-256">{}</hash></file></metalink>"#,
+            sha256
+        );
+        let fosshub = format!(
+            r#"{{"n":"foo-1.0.zip","hashes":{{"sha256":"{}"}}}}"#,
+            sha256
+        );
+        let sourceforge = format!(r#"{{"foo-1.0.zip":{{"sha1": "{}"}}}}"#, sha1);
+
+        let sha256_file = format!("{}\n", sha256);
+        let pages = HashMap::from([
+            ("https://example.com/foo-1.0.zip", "foo32"),
+            ("https://example.com/SHA256SUMS", &sums),
+            ("https://example.com/foo-1.0.zip.sha256", &sha256_file),
+            ("https://example.com/api.json", &json),
+            ("https://example.com/files.xml", &xml),
+            ("https://example.com/foo.rdf", &rdf),
+            ("https://example.com/foo-1.0.zip.meta4", &meta4),
+            ("https://www.fosshub.com/Foo.html?dwl=foo-1.0.zip", &fosshub),
+            (
+                "https://sourceforge.net/projects/foo/files/1.0",
+                &sourceforge,
+            ),
+        ]);
+        let session = Session::new();
+        session.set_http_client(Server::pages(pages)).unwrap();
+
+        let subs = substitutions("1.0", None);
+        let url = "https://example.com/foo-1.0.zip#/dl.zip";
+        let hash = |url: &str, extraction: &str| {
+            let extraction = serde_json::from_str::<HashExtraction>(extraction).unwrap();
+            hash(&session, url, Some(&extraction), Algorithm::Sha256, &subs).unwrap()
+        };
+        let sha1 = format!("sha1:{}", sha1);
+
+        assert_eq!(hash(url, r#"{"url":"$baseurl/SHA256SUMS"}"#), sha256);
+        assert_eq!(hash(url, r#"{"url":"$url.sha256"}"#), sha256);
+        assert_eq!(
+            hash(
+                url,
+                r#"{"url":"$baseurl/SHA256SUMS","find":"^$checksum\\s+other"}"#
+            ),
+            sha1
+        );
+        assert_eq!(
+            hash(
+                url,
+                r#"{"url":"$baseurl/api.json","jp":"$.assets['$basename'].sha256"}"#
+            ),
+            sha256
+        );
+        assert_eq!(
+            hash(
+                url,
+                r#"{"url":"$baseurl/files.xml","xpath":"/files/file/sha1"}"#
+            ),
+            sha1
+        );
+        assert_eq!(
+            hash(url, r#"{"url":"$baseurl/foo.rdf","mode":"rdf"}"#),
+            sha256
+        );
+        assert_eq!(hash(url, r#"{"mode":"metalink"}"#), sha256);
+        assert_eq!(
+            hash("https://www.fosshub.com/Foo.html?dwl=foo-1.0.zip", "{}"),
+            sha256
+        );
+        assert_eq!(
+            hash(
+                "https://downloads.sourceforge.net/project/foo/1.0/foo-1.0.zip",
+                "{}"
+            ),
+            sha1
+        );
+
+        // fall back to downloading the file
+        assert_eq!(hash(url, r#"{"url":"$baseurl/missing"}"#), sha256);
+        assert_eq!(hash(url, r#"{"mode":"download"}"#), sha256);
+
+        // broken extractions are reported
+        let hash_err = |extraction: &str| {
+            let extraction = serde_json::from_str::<HashExtraction>(extraction).unwrap();
+            super::hash(&session, url, Some(&extraction), Algorithm::Sha256, &subs).unwrap_err()
+        };
+        let err = hash_err(r#"{"url":"$baseurl/SHA256SUMS","regex":"($checksum"}"#);
+        assert!(matches!(err, Error::Regex(_)), "{:?}", err);
+        let err = hash_err(r#"{"url":"$baseurl/api.json","jsonpath":"$.assets[["}"#);
+        assert!(matches!(err, Error::Custom(_)), "{:?}", err);
+        let err = hash_err(r#"{"url":"$baseurl/SHA256SUMS","jsonpath":"$.sha256"}"#);
+        assert!(matches!(err, Error::Serde(_)), "{:?}", err);
+        let err = hash_err(r#"{"url":"$baseurl/SHA256SUMS","xpath":"/files"}"#);
+        assert!(matches!(err, Error::Custom(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn test_xpath_literal() {
+        assert_eq!(xpath_literal("foo-1.0.zip"), "'foo-1.0.zip'");
+        assert_eq!(xpath_literal("foo's.zip"), r#""foo's.zip""#);
+        assert_eq!(xpath_literal(r#"a'b"c"#), r#"concat('a', "'", 'b"c')"#);
+
+        let xml = r#"<files><file name="it's &quot;foo&quot;.zip">abc</file></files>"#;
+        let path = format!("/files/file[@name={}]", xpath_literal(r#"it's "foo".zip"#));
+        assert_eq!(
+            internal::select::xpath(xml, &path).unwrap().as_deref(),
+            Some("abc")
+        );
+    }
+
+    #[test]
+    fn test_autoupdate() {
+        let session = Session::new();
+        let pages = HashMap::from([
+            ("https://example.com/foo-2.0-x64.zip", "foo64"),
+            ("https://example.com/foo-2.0-x86.zip", "foo32"),
+            ("https://example.com/foo-2.0.txt", "notes"),
+        ]);
+        session.set_http_client(Server::pages(pages)).unwrap();
+
+        let text = r#"{
+  "version": "1.0",
+  "description": "Foo",
+  "homepage": "https://example.com",
+  "license": "MIT",
+  "architecture": {
+    "64bit": {
+      "url": "https://example.com/foo-1.0-x64.zip",
+      "hash": "md5:00000000000000000000000000000000"
+    },
+    "32bit": {
+      "url": "https://example.com/foo-1.0-x86.zip"
+    }
+  },
+  "autoupdate": {
+    "architecture": {
+      "64bit": {
+        "url": "https://example.com/foo-$version-x64.zip"
+      },
+      "32bit": {
+        "url": "https://example.com/foo-$version-x86.zip"
+      }
+    },
+    "notes": ["$matchBuild"]
+  }
+}
+"#;
+        let path = std::env::temp_dir().join("libscoop-autoupdate-foo.json");
+        std::fs::write(&path, text).unwrap();
+        let manifest = Manifest::parse(&path).unwrap();
+
+        let captures = BTreeMap::from([("build".to_owned(), "7".to_owned())]);
+        let text = autoupdate(&session, &manifest, "2.0", Some(&captures)).unwrap();
+        let expected = r#"{
+  "version": "2.0",
+  "description": "Foo",
+  "homepage": "https://example.com",
+  "license": "MIT",
+  "architecture": {
+    "64bit": {
+      "url": "https://example.com/foo-2.0-x64.zip",
+      "hash": "md5:d57a5fcc8ad329d9e8ad00425d98b14d"
+    },
+    "32bit": {
+      "url": "https://example.com/foo-2.0-x86.zip",
+      "hash": "d2e53aff4d5685340fe37d6637751e025018c38771d868e9bd0cba2d6b0ea25f"
+    }
+  },
+  "autoupdate": {
+    "architecture": {
+      "64bit": {
+        "url": "https://example.com/foo-$version-x64.zip"
+      },
+      "32bit": {
+        "url": "https://example.com/foo-$version-x86.zip"
+      }
+    },
+    "notes": ["$matchBuild"]
+  },
+  "notes": [
+    "7"
+  ]
+}
+"#;
+        assert_eq!(text, expected);
+
+        let text = r#"{"version":"1.0","homepage":"https://example.com","license":"MIT"}"#;
+        std::fs::write(&path, text).unwrap();
+        let manifest = Manifest::parse(&path).unwrap();
+        assert!(matches!(
+            autoupdate(&session, &manifest, "2.0", None),
+            Err(Error::Autoupdate(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
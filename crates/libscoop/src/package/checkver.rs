@@ -0,0 +1,373 @@
+//! Detect the latest upstream version of a package from the `checkver` spec
+//! of its manifest.
+//!
+//! The page at the checkver `url`, which is the homepage by default, is
+//! fetched, and the version is then matched as follows:
+//!
+//! - `jsonpath`/`xpath` selects a value from the page if given;
+//! - `regex` is matched against the selected value, or the page if nothing is
+//!   selected. The first unnamed group, or the group named `version`, is the
+//!   version. The last match is used instead of the first if `reverse` is set;
+//! - `replace` rewrites the match into the version if given.
+//!
+//! The `github` and `sourceforge` shorthands are expanded into the url and the
+//! regex of the latest release.
+use regex::{Regex, RegexBuilder};
+use std::collections::BTreeMap;
+use tracing::debug;
+
+use crate::{
+    constant::CHECKVER_GITHUB_REGEX,
+    error::{Error, Fallible},
+    internal, Session,
+};
+
+use super::manifest::{Checkver, Manifest};
+
+/// Default regex of the `sourceforge` shorthand to match versions.
+const SOURCEFORGE_VERSION_REGEX: &str = r"(\d+(?:\.\d+)*)";
+
+/// The latest version detected by a checkver.
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckverMatch {
+    /// The detected version.
+    version: String,
+
+    /// The url checked.
+    url: String,
+
+    /// Captured groups of the regex match, by group names or numbers.
+    captures: BTreeMap<String, String>,
+}
+
+impl CheckverMatch {
+    /// Return the detected version.
+    #[inline]
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Return the url checked for the version.
+    #[inline]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Return the captured groups of the regex match.
+    ///
+    /// Named groups are keyed by their names and unnamed groups by their
+    /// numbers, starting from `1`. Groups that did not participate in the
+    /// match are omitted. These are exposed as the `$match<Name>` variables
+    /// to autoupdate.
+    #[inline]
+    pub fn captures(&self) -> &BTreeMap<String, String> {
+        &self.captures
+    }
+}
+
+/// A checkver spec with the shorthands and defaults resolved.
+#[derive(Debug)]
+struct Spec<'a> {
+    url: String,
+    regex: Option<String>,
+    jsonpath: Option<&'a str>,
+    xpath: Option<&'a str>,
+    reverse: bool,
+    replace: Option<&'a str>,
+    useragent: Option<&'a str>,
+}
+
+impl<'a> Spec<'a> {
+    fn new(checkver: &'a Checkver, manifest: &Manifest) -> Fallible<Spec<'a>> {
+        if checkver.script.is_some() {
+            let msg = "checkver script is not supported".to_owned();
+            return Err(Error::Checkver(msg));
+        }
+
+        let homepage = manifest.homepage().trim_end_matches('/');
+        let mut url = checkver.url.clone();
+        let mut regex = checkver.regex.clone();
+
+        // the `github` shorthand without a repository refers to the homepage
+        if url.is_none() && regex.as_deref() == Some(CHECKVER_GITHUB_REGEX) {
+            if !homepage.starts_with("https://github.com/") {
+                let msg = "checkver 'github' expects the homepage to be a GitHub repository";
+                return Err(Error::Checkver(msg.to_owned()));
+            }
+            url = Some(format!("{}/releases/latest", homepage));
+        }
+
+        if let Some(sourceforge) = checkver.sourceforge.as_ref() {
+            let project = sourceforge
+                .project
+                .clone()
+                .or_else(|| sourceforge_project(manifest))
+                .ok_or_else(|| {
+                    let msg = "sourceforge project is unknown".to_owned();
+                    Error::Checkver(msg)
+                })?;
+            let path = sourceforge.path.trim_matches('/');
+
+            url = Some(format!(
+                "https://sourceforge.net/projects/{}/rss?path=/{}",
+                project, path
+            ));
+
+            let dir = match path.is_empty() {
+                true => String::new(),
+                false => format!("{}/", regex::escape(path)),
+            };
+            let version = regex.as_deref().unwrap_or(SOURCEFORGE_VERSION_REGEX);
+            regex = Some(format!(r"CDATA\[/{}.*?{}.*?\]\]", dir, version));
+        }
+
+        if regex.is_none() && checkver.jsonpath.is_none() && checkver.xpath.is_none() {
+            let msg = "checkver has none of regex, jsonpath and xpath".to_owned();
+            return Err(Error::Checkver(msg));
+        }
+
+        Ok(Spec {
+            url: url.unwrap_or_else(|| manifest.homepage().to_owned()),
+            regex,
+            jsonpath: checkver.jsonpath.as_deref(),
+            xpath: checkver.xpath.as_deref(),
+            reverse: checkver.reverse.unwrap_or_default(),
+            replace: checkver.replace.as_deref(),
+            useragent: checkver.useragent.as_deref(),
+        })
+    }
+
+    /// Match the version from the fetched `page`.
+    fn matches(&self, page: &str) -> Fallible<CheckverMatch> {
+        let mut captures = BTreeMap::new();
+
+        let selected = match (self.jsonpath, self.xpath) {
+            (Some(path), _) => Some(internal::select::json_path(page, path)?),
+            (None, Some(path)) => Some(internal::select::xpath(page, path)?),
+            (None, None) => None,
+        };
+
+        let version = match (selected, self.regex.as_deref()) {
+            (Some(None), _) => None,
+            (Some(Some(value)), None) => Some(value),
+            (Some(Some(value)), Some(regex)) => self.match_regex(regex, &value, &mut captures)?,
+            (None, Some(regex)) => self.match_regex(regex, page, &mut captures)?,
+            (None, None) => unreachable!(),
+        };
+
+        let version = version
+            .map(|v| v.trim().to_owned())
+            .filter(|v| !v.is_empty());
+        match version {
+            Some(version) => Ok(CheckverMatch {
+                version,
+                url: self.url.clone(),
+                captures,
+            }),
+            None => {
+                let msg = format!("could not match version from {}", self.url);
+                Err(Error::Checkver(msg))
+            }
+        }
+    }
+
+    /// Match the version from `text` with `regex`, collecting the captured
+    /// groups into `captures`.
+    fn match_regex(
+        &self,
+        regex: &str,
+        text: &str,
+        captures: &mut BTreeMap<String, String>,
+    ) -> Fallible<Option<String>> {
+        let re = RegexBuilder::new(regex).build()?;
+        let caps = match self.reverse {
+            true => re.captures_iter(text).last(),
+            false => re.captures(text),
+        };
+        let caps = match caps {
+            Some(caps) => caps,
+            None => return Ok(None),
+        };
+
+        // Unnamed groups are numbered before named ones, as what Scoop does.
+        let mut unnamed = 0;
+        for (idx, name) in re.capture_names().enumerate().skip(1) {
+            let key = match name {
+                Some(name) => name.to_owned(),
+                None => {
+                    unnamed += 1;
+                    unnamed.to_string()
+                }
+            };
+            if let Some(m) = caps.get(idx) {
+                captures.insert(key, m.as_str().to_owned());
+            }
+        }
+
+        let version = match self.replace {
+            Some(replace) => Some(re.replace(&caps[0], replace).into_owned()),
+            None => captures
+                .get("1")
+                .or_else(|| captures.get("version"))
+                .cloned(),
+        };
+        Ok(version)
+    }
+}
+
+/// Guess the SourceForge project of a package from its download urls.
+fn sourceforge_project(manifest: &Manifest) -> Option<String> {
+    // project pages and downloads, or project web hostings
+    let project = Regex::new(r"sourceforge\.net/projects?/([^/]+)").unwrap();
+    let web = Regex::new(r"//([^/.]+)\.sourceforge\.(?:net|io)").unwrap();
+
+    let urls = std::iter::once(manifest.homepage())
+        .chain(manifest.url())
+        .collect::<Vec<_>>();
+    let matched = |re: &Regex| {
+        urls.iter()
+            .filter_map(|url| re.captures(url))
+            .map(|caps| caps[1].to_owned())
+            .find(|name| name != "downloads")
+    };
+    matched(&project).or_else(|| matched(&web))
+}
+
+/// Detect the latest version of the given manifest with its checkver spec.
+///
+/// # Errors
+///
+/// A [`Checkver`][1] error will be returned if the manifest has no checkver,
+/// the checkver is not supported, or no version is matched. An [`Http`][2]
+/// error will be returned if failed to fetch the page.
+///
+/// [1]: crate::Error::Checkver
+/// [2]: crate::Error::Http
+pub(crate) fn checkver(session: &Session, manifest: &Manifest) -> Fallible<CheckverMatch> {
+    let checkver = manifest.checkver().ok_or_else(|| {
+        let msg = "checkver is not defined".to_owned();
+        Error::Checkver(msg)
+    })?;
+    let spec = Spec::new(checkver, manifest)?;
+
+    debug!("checking version from {}", spec.url);
+    let page = super::download::fetch(session, &spec.url, spec.useragent)?;
+    let page = String::from_utf8_lossy(&page);
+    spec.matches(&page)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::http::{test::Server, HttpError};
+
+    fn manifest(name: &str, json: &str) -> Manifest {
+        let path = std::env::temp_dir().join(format!("libscoop-checkver-{}.json", name));
+        std::fs::write(&path, json).unwrap();
+        let manifest = Manifest::parse(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        manifest
+    }
+
+    fn session() -> Session {
+        let pages = HashMap::from([
+            (
+                "https://github.com/foo/bar/releases/latest",
+                r#"<a href="/foo/bar/releases/tag/v1.2.3">v1.2.3</a>"#,
+            ),
+            (
+                "https://example.com/downloads",
+                "foo-1.0.zip foo-1.1.zip foo-2.0-beta.zip",
+            ),
+            (
+                "https://example.com/api",
+                r#"{"releases":[{"tag":"build-42","version":"3.1"}]}"#,
+            ),
+            (
+                "https://example.com/feed.xml",
+                r#"<feed xmlns="http://www.w3.org/2005/Atom"><entry><title>v4.5</title></entry></feed>"#,
+            ),
+            (
+                "https://sourceforge.net/projects/foo/rss?path=/foo",
+                "<title><![CDATA[/foo/5.6.7/foo-5.6.7.zip]]></title>",
+            ),
+        ]);
+
+        let session = Session::new();
+        session.set_http_client(Server::pages(pages)).unwrap();
+        session
+    }
+
+    #[test]
+    fn test_checkver() {
+        let session = session();
+        let check = |json: &str| {
+            let json = format!(
+                r#"{{"version":"0.1","homepage":"https://github.com/foo/bar","license":"MIT",
+                    "url":"https://downloads.sourceforge.net/project/foo/foo.zip","checkver":{}}}"#,
+                json
+            );
+            checkver(&session, &manifest("test", &json))
+        };
+
+        assert_eq!(check(r#""github""#).unwrap().version(), "1.2.3");
+        assert_eq!(
+            check(r#"{"github":"https://github.com/foo/bar/"}"#)
+                .unwrap()
+                .url(),
+            "https://github.com/foo/bar/releases/latest"
+        );
+
+        let ret = check(
+            r#"{"url":"https://example.com/downloads","regex":"foo-(?<major>\\d+)\\.(\\d+)\\.zip"}"#,
+        )
+        .unwrap();
+        assert_eq!(ret.version(), "0");
+        assert_eq!(ret.captures().get("major").unwrap(), "1");
+        assert_eq!(ret.captures().get("1").unwrap(), "0");
+
+        let ret = check(
+            r#"{"url":"https://example.com/downloads","re":"foo-(?<version>[\\d.]+)\\.zip","reverse":true}"#,
+        )
+        .unwrap();
+        assert_eq!(ret.version(), "1.1");
+
+        let ret = check(
+            r#"{"url":"https://example.com/downloads","re":"foo-(\\d+)\\.(\\d+)\\.zip","replace":"$1.$2.0"}"#,
+        )
+        .unwrap();
+        assert_eq!(ret.version(), "1.0.0");
+
+        let ret = check(r#"{"url":"https://example.com/api","jp":"$.releases[0].version"}"#);
+        assert_eq!(ret.unwrap().version(), "3.1");
+        let ret = check(
+            r#"{"url":"https://example.com/api","jsonpath":"$..tag","regex":"build-(\\d+)"}"#,
+        );
+        assert_eq!(ret.unwrap().version(), "42");
+
+        let ret = check(
+            r#"{"url":"https://example.com/feed.xml","xpath":"/feed/entry/title","regex":"v([\\d.]+)"}"#,
+        );
+        assert_eq!(ret.unwrap().version(), "4.5");
+
+        assert_eq!(
+            check(r#"{"sourceforge":"foo"}"#).unwrap().version(),
+            "5.6.7"
+        );
+
+        assert!(matches!(
+            check(r#"{"url":"https://example.com/downloads","re":"bar-([\\d.]+)"}"#),
+            Err(Error::Checkver(_))
+        ));
+        assert!(matches!(
+            check(r#"{"url":"https://example.com/missing","re":"([\\d.]+)"}"#),
+            Err(Error::Http(HttpError::Status(404)))
+        ));
+        assert!(matches!(
+            check(r#"{"script":"echo 1"}"#),
+            Err(Error::Checkver(_))
+        ));
+    }
+}
@@ -1,19 +1,28 @@
-use curl::easy::{Easy, List};
-use curl::multi::Multi;
 use flume::Sender;
 use once_cell::unsync::OnceCell;
+use scoop_hash::{Checksum, ChecksumBuilder};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet, VecDeque},
     fs::{File, OpenOptions},
-    io::Write,
-    time::Duration,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{debug, info};
+
+use crate::constant::{DEFAULT_USER_AGENT, REGEX_GITHUB_URL};
+use crate::{
+    config::Config,
+    error::{Error, Fallible},
+    http::{HttpClient, HttpError, Request},
+    internal, Event, Session,
 };
-use tracing::debug;
-
-use crate::constant::DEFAULT_USER_AGENT;
-use crate::{error::Fallible, internal, Event, Session};
 
-use super::Package;
+use super::{manifest::HashString, HashMismatchContext, Package};
 
 /// Download size information.
 #[derive(Clone, Copy)]
@@ -33,13 +42,19 @@ pub struct PackageSet<'a> {
     /// Packages with intent to download.
     pub packages: &'a [&'a Package],
 
-    /// Multi handle for curl.
-    multi: Multi,
-
     caches: OnceCell<HashMap<String, PackageCache<'a>>>,
 
     /// Whether to reuse cached files.
     reuse_cache: bool,
+
+    /// Whether to verify hashes of downloaded files.
+    hash_check: bool,
+
+    /// Filenames of downloaded files that have been verified.
+    verified: HashSet<String>,
+
+    /// Download rate limit in bytes per second, overriding the config.
+    max_rate: Option<u64>,
 }
 
 /// Stores download information of a file.
@@ -55,6 +70,82 @@ struct FileDownloadInfo<'a> {
 
     /// Whether the remote file size is estimated.
     estimated: bool,
+
+    /// Whether the remote server accepts range requests for the file.
+    accept_ranges: bool,
+
+    /// Expected hash of the file, `None` if the file can't be verified.
+    hash: Option<&'a HashString>,
+}
+
+/// A file being downloaded.
+struct PendingFile<'a> {
+    /// Identifier of the package the file belongs to.
+    ident: String,
+
+    /// Name of the package the file belongs to.
+    name: String,
+
+    /// Cache filename.
+    filename: String,
+
+    /// Original download URL.
+    url: String,
+
+    /// Mirror URL of the download, if any.
+    mirror: Option<String>,
+
+    /// Segments to be stitched, empty if the file is not segmented.
+    parts: Vec<PathBuf>,
+
+    /// Temporary download path.
+    tmp: PathBuf,
+
+    /// Final cache path.
+    path: PathBuf,
+
+    /// Expected hash of the file, `None` if hash check is not needed.
+    hash: Option<&'a HashString>,
+
+    /// Checksum fed by the downloaded data, segmented files are hashed when
+    /// their segments are stitched instead.
+    checksum: Option<Mutex<Option<Checksum>>>,
+
+    /// Segment which received the whole file from a server ignoring range
+    /// requests, the file is then taken from it instead of being stitched.
+    whole: Mutex<Option<PathBuf>>,
+}
+
+impl PendingFile<'_> {
+    /// Verify the downloaded file against its expected hash.
+    ///
+    /// The file is discarded on mismatch.
+    fn verify(&self, checksum: Checksum) -> Fallible<()> {
+        let hash = match self.hash {
+            None => return Ok(()),
+            Some(hash) => hash,
+        };
+
+        let actual = checksum.finalize();
+        let expected = hash.value();
+        if actual != expected {
+            let _ = std::fs::remove_file(&self.tmp);
+            let ctx = HashMismatchContext::new(
+                self.name.to_owned(),
+                self.url.to_owned(),
+                expected.to_owned(),
+                actual,
+            );
+            return Err(Error::HashMismatch(ctx));
+        }
+
+        Ok(())
+    }
+
+    /// Whether a segment of the file has received the whole file.
+    fn is_whole(&self) -> bool {
+        self.whole.lock().unwrap().is_some()
+    }
 }
 
 /// Possible cache state of a package.
@@ -89,7 +180,7 @@ impl PackageCache<'_> {
     fn update_valid_state(&mut self) {
         let mut cnt = 0;
         for (_, cache) in self.inner.iter() {
-            if cache.local_size == cache.remote_size {
+            if cache.local_size > 0 && cache.local_size == cache.remote_size {
                 cnt += 1;
             }
         }
@@ -109,23 +200,33 @@ impl<'a> PackageSet<'a> {
         session: &'a Session,
         packages: &'a [&Package],
         reuse_cache: bool,
+        hash_check: bool,
     ) -> Fallible<PackageSet<'a>> {
-        let mut multi = Multi::new();
-
-        // TODO: configurable max connections
-        multi.set_max_total_connections(6)?;
-        multi.set_max_host_connections(4)?;
-        multi.pipelining(false, true)?;
-
         Ok(PackageSet {
             session,
             packages,
-            multi,
             caches: OnceCell::new(),
             reuse_cache,
+            hash_check,
+            verified: HashSet::new(),
+            max_rate: None,
         })
     }
 
+    /// Limit the overall download rate to `rate` bytes per second, overriding
+    /// the `download_max_rate` config.
+    pub fn set_max_rate(&mut self, rate: u64) {
+        self.max_rate = Some(rate).filter(|&r| r > 0);
+    }
+
+    /// Check if the given file has been verified while downloading.
+    ///
+    /// Files verified during download don't need to be verified again, while
+    /// cached files are never verified here.
+    pub fn is_verified(&self, filename: &str) -> bool {
+        self.verified.contains(filename)
+    }
+
     fn load_cache(&self) {
         if self.caches.get().is_some() {
             return;
@@ -142,6 +243,11 @@ impl<'a> PackageSet<'a> {
 
             let urls = pkg.download_urls();
             let filenames = pkg.download_filenames();
+            // nightly packages have no hash to verify
+            let hashes = match pkg.version() {
+                "nightly" => vec![],
+                _ => pkg.download_hashes(),
+            };
 
             let mut pacakge_cache = PackageCache {
                 package: pkg,
@@ -150,7 +256,7 @@ impl<'a> PackageSet<'a> {
             };
 
             let mut file_cached_count = 0;
-            for (url, filename) in urls.iter().zip(filenames.iter()) {
+            for (idx, (url, filename)) in urls.iter().zip(filenames.iter()).enumerate() {
                 let remote_size = 0u64;
                 let mut local_size = 0u64;
 
@@ -168,6 +274,8 @@ impl<'a> PackageSet<'a> {
                     local_size,
                     remote_size,
                     estimated: false,
+                    accept_ranges: false,
+                    hash: hashes.get(idx).copied(),
                 };
 
                 pacakge_cache.inner.insert(filename.to_owned(), dlinfo);
@@ -188,6 +296,20 @@ impl<'a> PackageSet<'a> {
     }
 
     /// Download packages.
+    ///
+    /// # Note
+    ///
+    /// When `aria2-enabled` is set and the remote server accepts range requests,
+    /// large files are split into segments according to the `aria2-split`,
+    /// `aria2-max-connection-per-server` and `aria2-min-split-size` configs.
+    /// Segments are downloaded in parallel and stitched into the final cache
+    /// file once all of them are done.
+    ///
+    /// When hash check is enabled, files are hashed while being downloaded,
+    /// so a hash mismatch is only known once the whole file is received. The
+    /// mismatch then aborts the transfers still in progress. Segmented files
+    /// are hashed while their segments are stitched, after all transfers are
+    /// finished.
     pub fn download(&mut self) -> Fallible<()> {
         if self.caches.get().is_none() {
             self.load_cache();
@@ -203,25 +325,44 @@ impl<'a> PackageSet<'a> {
             .map(|s| s.as_str())
             .unwrap_or(DEFAULT_USER_AGENT);
 
-        let mut handles = HashMap::new();
-        let mut token_ctx = HashMap::new();
-        let package_caches = self.caches.get_mut().unwrap();
+        let segmented = config.aria2_enabled();
+        let split = config
+            .aria2_split()
+            .min(config.aria2_max_connection_per_server());
+        let min_split_size = config.aria2_min_split_size();
+        let connect_timeout = config.download_connect_timeout();
+        let stall_timeout = config.download_stall_timeout();
+        let retries = config.download_retries();
+        let retry_wait = config.download_retry_wait();
+        let limiter = self
+            .max_rate
+            .or_else(|| config.download_max_rate())
+            .map(RateLimiter::new);
+        let max_connections = config.download_max_connections() as usize;
+        let max_host_connections = config.download_max_host_connections() as usize;
+
+        let client = self.session.http_client();
+        let emitter = self.session.emitter();
+        let tracker = ProgressTracker::new(emitter.clone());
+        let package_caches = self.caches.get().unwrap();
 
         // map download tmp files to their final names
-        let mut filepaths = vec![];
+        let mut pending = vec![];
+        let mut transfers = vec![];
 
         // ensure cache dir exists
-        internal::fs::ensure_dir(&cache_root)?;
+        internal::fs::ensure_dir(cache_root)?;
 
-        for (pidx, (_, cache)) in package_caches.iter().enumerate() {
+        for (_, cache) in package_caches.iter() {
             // skip download if all files are cached and valid
             if self.reuse_cache && cache.valid == CacheMaybeValid::Full {
                 continue;
             }
 
+            let name = cache.package.name();
             let cookie = cache.package.cookie().unwrap_or_default();
 
-            for (uidx, (filename, dlinfo)) in cache.inner.iter().enumerate() {
+            for (filename, dlinfo) in cache.inner.iter() {
                 if self.reuse_cache
                     && dlinfo.local_size > 0
                     && dlinfo.local_size == dlinfo.remote_size
@@ -229,34 +370,6 @@ impl<'a> PackageSet<'a> {
                     continue;
                 }
 
-                let mut easy = Easy::new();
-                easy.get(true)?;
-                easy.url(dlinfo.url)?;
-                easy.follow_location(true)?;
-                easy.useragent(user_agent)?;
-                easy.fail_on_error(true)?;
-                if let Some(proxy) = proxy {
-                    easy.proxy(proxy)?;
-                }
-                set_cookie(&mut easy, &cookie)?;
-
-                if let Some(tx) = self.session.emitter() {
-                    let ident = cache.package.ident();
-                    let url = dlinfo.url.to_owned();
-                    let fname = filename.to_owned();
-                    easy.progress(true)?;
-                    easy.progress_function(move |dltotal, dlnow, _, _| {
-                        progress(
-                            tx.clone(),
-                            ident.to_owned(),
-                            url.to_owned(),
-                            fname.to_owned(),
-                            dltotal,
-                            dlnow,
-                        )
-                    })?;
-                }
-
                 let path = cache_root.join(filename);
                 let tmp = cache_root.join(format!("{}.download", filename));
 
@@ -264,52 +377,203 @@ impl<'a> PackageSet<'a> {
                 let _ = std::fs::remove_file(&path);
                 let _ = std::fs::remove_file(&tmp);
 
-                filepaths.push((tmp.clone(), path.clone()));
+                let hash = dlinfo.hash.filter(|_| self.hash_check);
+
+                // local files are copied into the cache by a transfer job
+                if let Some(src) = local_path(dlinfo.url) {
+                    let fidx = tracker.add(
+                        &cache.package.ident(),
+                        dlinfo.url,
+                        filename,
+                        dlinfo.remote_size,
+                        1,
+                    );
+                    let transfer = Transfer {
+                        fidx,
+                        sidx: 0,
+                        request: Request::new(dlinfo.url),
+                        fallback: None,
+                        local: Some(src),
+                        target: tmp.clone(),
+                    };
+                    transfers.push((LOCAL_HOST.to_owned(), transfer));
+
+                    pending.push(PendingFile {
+                        ident: cache.package.ident(),
+                        name: name.to_owned(),
+                        filename: filename.to_owned(),
+                        url: dlinfo.url.to_owned(),
+                        mirror: None,
+                        parts: vec![],
+                        tmp,
+                        path,
+                        hash,
+                        checksum: hash.map(|hash| Mutex::new(Some(new_checksum(hash)))),
+                        whole: Mutex::new(None),
+                    });
+                    continue;
+                }
+
+                let mirror = config.mirror_url(dlinfo.url);
+                let url = mirror.as_deref().unwrap_or(dlinfo.url);
 
-                // TODO: Fragmented download support could be added to improve
-                // download speed.
-                let mut file = OpenOptions::new().create(true).append(true).open(&tmp)?;
-                easy.write_function(move |data| {
-                    file.write_all(data).unwrap();
-                    Ok(data.len())
-                })?;
+                let ranges = match segmented && dlinfo.accept_ranges {
+                    true => split_ranges(dlinfo.remote_size, split, min_split_size),
+                    false => vec![],
+                };
+
+                let checksum = match hash {
+                    Some(hash) if ranges.is_empty() => Some(Mutex::new(Some(new_checksum(hash)))),
+                    _ => None,
+                };
 
-                let mut easyhandle = self.multi.add(easy)?;
-                let token = pidx * 100 + uidx;
-                let _ = easyhandle.set_token(token);
-                handles.insert(token, easyhandle);
+                let fidx = tracker.add(
+                    &cache.package.ident(),
+                    dlinfo.url,
+                    filename,
+                    dlinfo.remote_size,
+                    ranges.len(),
+                );
+                let mut parts = vec![];
+
+                // A plain download is treated as a file with a single segment.
+                for sidx in 0..ranges.len().max(1) {
+                    let mut request = Request::new(url);
+                    request.headers = request_headers(&config, url, &cookie);
+                    request.proxy = internal::proxy::resolve(proxy, url);
+                    request.user_agent = user_agent.to_owned();
+                    request.connect_timeout = connect_timeout;
+                    request.stall_timeout = stall_timeout;
+                    request.range = ranges.get(sidx).copied();
+
+                    let target = match request.range {
+                        None => tmp.clone(),
+                        Some(_) => {
+                            let part = cache_root.join(format!("{}.download.{}", filename, sidx));
+                            let _ = std::fs::remove_file(&part);
+                            parts.push(part.clone());
+                            part
+                        }
+                    };
+
+                    let fallback = mirror.as_ref().map(|_| {
+                        let mut fallback = request.clone();
+                        fallback.url = dlinfo.url.to_owned();
+                        fallback.headers = request_headers(&config, dlinfo.url, &cookie);
+                        fallback.proxy = internal::proxy::resolve(proxy, dlinfo.url);
+                        fallback
+                    });
+
+                    let transfer = Transfer {
+                        fidx,
+                        sidx,
+                        request,
+                        fallback,
+                        local: None,
+                        target,
+                    };
+                    transfers.push((url_host(url).to_owned(), transfer));
+                }
+
+                if !parts.is_empty() {
+                    debug!("downloading {} in {} segments", filename, parts.len());
+                }
 
-                token_ctx.insert(token, (cache.package.ident(), filename.to_owned()));
+                pending.push(PendingFile {
+                    ident: cache.package.ident(),
+                    name: name.to_owned(),
+                    filename: filename.to_owned(),
+                    url: dlinfo.url.to_owned(),
+                    mirror,
+                    parts,
+                    tmp,
+                    path,
+                    hash,
+                    checksum,
+                    whole: Mutex::new(None),
+                });
             }
         }
 
-        let mut alive = true;
-        while alive {
-            alive = self.multi.perform()? > 0;
+        drop(config);
+
+        let abort = AtomicBool::new(false);
+        let ctx = TransferContext {
+            client: client.as_ref(),
+            tracker: &tracker,
+            limiter: limiter.as_ref(),
+            retries,
+            retry_wait,
+            abort: &abort,
+        };
+        let done = run_jobs(
+            transfers,
+            max_connections,
+            max_host_connections,
+            &abort,
+            |transfer| {
+                let file = &pending[transfer.fidx];
+                let fallen_back = transfer.run(file, &ctx)?;
+                tracker.finish(transfer.fidx);
+
+                // verify finished files right away
+                let checksum = file
+                    .checksum
+                    .as_ref()
+                    .and_then(|c| c.lock().unwrap().take());
+                if let Some(checksum) = checksum {
+                    file.verify(checksum)?;
+                }
 
-            let mut handle_err = None;
+                Ok((transfer.fidx, fallen_back))
+            },
+        )?;
 
-            self.multi.messages(|message| {
-                let token = message.token().expect("failed to get token");
-                let handle = handles.get_mut(&token).expect("failed to get handle");
+        // number of segments of each file fallen back to the original url
+        let mut fallen_back = HashMap::<usize, usize>::new();
+        for (fidx, _) in done.into_iter().filter(|(_, fallen_back)| *fallen_back) {
+            *fallen_back.entry(fidx).or_default() += 1;
+        }
 
-                // catch and propagate curl error
-                if let Some(Err(e)) = message.result_for(handle) {
-                    handle_err = Some(e);
+        for (fidx, file) in pending.into_iter().enumerate() {
+            if !file.parts.is_empty() {
+                let mut checksum = file.hash.map(new_checksum);
+                let whole = file.whole.lock().unwrap().take();
+                let parts = match whole.as_ref() {
+                    Some(whole) => std::slice::from_ref(whole),
+                    None => file.parts.as_slice(),
+                };
+                stitch_segments(parts, &file.tmp, checksum.as_mut())?;
+                for part in file.parts.iter() {
+                    let _ = std::fs::remove_file(part);
+                }
+                if let Some(checksum) = checksum {
+                    file.verify(checksum)?;
                 }
-            });
-
-            if let Some(err) = handle_err {
-                return Err(err.into());
             }
+            std::fs::rename(&file.tmp, &file.path)?;
 
-            if alive {
-                self.multi.wait(&mut [], Duration::from_secs(5))?;
+            if file.hash.is_some() {
+                self.verified.insert(file.filename.to_owned());
             }
-        }
 
-        for (tmp, path) in filepaths.iter() {
-            std::fs::rename(tmp, path)?;
+            if let Some(tx) = emitter.as_ref() {
+                let segments = file.parts.len().max(1);
+                let fallen_back = fallen_back.get(&fidx).copied().unwrap_or_default();
+                let source = match file.mirror {
+                    Some(mirror) if fallen_back < segments => mirror,
+                    _ => file.url.clone(),
+                };
+                let ctx = PackageDownloadFileContext {
+                    mirrored: source != file.url,
+                    mixed: fallen_back > 0 && fallen_back < segments,
+                    ident: file.ident,
+                    url: file.url,
+                    filename: file.filename,
+                    source,
+                };
+                let _ = tx.send(Event::PackageDownloadFileDone(ctx));
+            }
         }
 
         Ok(())
@@ -332,12 +596,19 @@ impl<'a> PackageSet<'a> {
             .get()
             .map(|s| s.as_str())
             .unwrap_or(DEFAULT_USER_AGENT);
+        let connect_timeout = config.download_connect_timeout();
+        let max_connections = config.download_max_connections() as usize;
+        let max_host_connections = config.download_max_host_connections() as usize;
 
-        let mut handles = HashMap::new();
-        let mut token_ctx = HashMap::new();
+        let client = self.session.http_client();
         let package_caches = self.caches.get_mut().unwrap();
+        let mut total = 0;
+        let mut estimated = false;
+        let mut queries = vec![];
+        let mut sizes = vec![];
+        let remote_sizes = self.session.remote_sizes.borrow().clone();
 
-        for (pidx, &pkg) in self.packages.iter().enumerate() {
+        for &pkg in self.packages.iter() {
             // if the package is upgradable, use the upgradable reference instead
             let pkg = pkg.upgradable().unwrap_or(pkg);
 
@@ -345,92 +616,656 @@ impl<'a> PackageSet<'a> {
             let filenames = pkg.download_filenames();
             let cookie = pkg.cookie().unwrap_or_default();
 
-            for (uidx, (url, filename)) in urls.iter().zip(filenames.iter()).enumerate() {
-                let mut easy = Easy::new();
-                easy.get(true)?;
-                easy.url(url)?;
-                easy.follow_location(true)?;
-                easy.nobody(true)?;
-                easy.useragent(user_agent)?;
-                if let Some(proxy) = proxy {
-                    easy.proxy(proxy)?;
+            for (&url, filename) in urls.iter().zip(filenames) {
+                // local files are sized without a network round trip
+                if let Some(src) = local_path(url) {
+                    let size = std::fs::metadata(src)?.len();
+                    let package_cache = package_caches.get_mut(&pkg.ident()).unwrap();
+                    let info = package_cache
+                        .inner
+                        .get_mut(&filename)
+                        .expect("failed to get cache info");
+                    info.remote_size = size;
+                    if size != info.local_size {
+                        total += size;
+                    }
+                    package_cache.update_valid_state();
+                    continue;
+                }
+
+                if let Some(&size) = remote_sizes.get(url) {
+                    sizes.push((pkg.ident(), url, filename, Some(size)));
+                    continue;
                 }
-                set_cookie(&mut easy, &cookie)?;
 
-                let mut easyhandle = self.multi.add(easy)?;
-                let token = pidx * 100 + uidx;
-                let _ = easyhandle.set_token(token);
-                handles.insert(token, easyhandle);
+                let mirror = config.mirror_url(url);
+                let mirror_or_url = mirror.as_deref().unwrap_or(url);
+
+                let mut request = Request::new(mirror_or_url);
+                request.headers = request_headers(&config, mirror_or_url, &cookie);
+                request.proxy = internal::proxy::resolve(proxy, mirror_or_url);
+                request.user_agent = user_agent.to_owned();
+                request.connect_timeout = connect_timeout;
+
+                // query the original url if the mirror fails
+                let fallback = mirror.as_ref().map(|_| {
+                    let mut fallback = request.clone();
+                    fallback.url = url.to_owned();
+                    fallback.headers = request_headers(&config, url, &cookie);
+                    fallback.proxy = internal::proxy::resolve(proxy, url);
+                    fallback
+                });
+
+                let host = url_host(mirror_or_url).to_owned();
+                queries.push((host, (pkg.ident(), url, filename, request, fallback)));
+            }
+        }
 
-                token_ctx.insert(token, (pkg.ident(), url.to_string(), filename.to_owned()));
+        drop(config);
+
+        let abort = AtomicBool::new(false);
+        let probed = run_jobs(
+            queries,
+            max_connections,
+            max_host_connections,
+            &abort,
+            |(ident, url, filename, request, fallback)| {
+                let mut ret = probe_size(client.as_ref(), &request);
+                if let Some(fallback) = fallback {
+                    // query the original url if the mirror fails
+                    if !matches!(ret, Ok(Some(_))) {
+                        ret = probe_size(client.as_ref(), &fallback);
+                    }
+                }
+                Ok((ident, url, filename, ret?))
+            },
+        )?;
+
+        let mut remote_sizes = self.session.remote_sizes.borrow_mut();
+        for (_, url, _, size) in probed.iter() {
+            if let Some(size) = size {
+                remote_sizes.insert(url.to_string(), *size);
             }
         }
+        sizes.extend(probed);
+
+        for (ident, url, filename, size) in sizes {
+            let package_cache = package_caches.get_mut(&ident).unwrap();
+            let info = package_cache
+                .inner
+                .get_mut(&filename)
+                .expect("failed to get cache info");
+
+            match size {
+                Some(size) => {
+                    info.remote_size = size.size;
+                    info.accept_ranges = size.accept_ranges;
+                    info.estimated = size.estimated;
+                    if size.size != info.local_size {
+                        total += size.size;
+                    }
+                }
+                None => {
+                    debug!("failed to get remote size, ident: {}, url: {}", ident, url);
+                    info.estimated = true;
+                }
+            }
 
-        let mut total = 0;
-        let mut estimated = false;
+            estimated |= info.estimated;
+            package_cache.update_valid_state();
+        }
 
-        let mut alive = true;
-        while alive {
-            alive = self.multi.perform()? > 0;
-
-            let mut handle_err = None;
-
-            self.multi.messages(|message| {
-                let token = message.token().expect("failed to get token");
-                let handle = handles.get_mut(&token).expect("failed to get handle");
-
-                if let Some(handle_ret) = message.result_for(handle) {
-                    match handle_ret {
-                        Err(e) => handle_err = Some(e),
-                        Ok(_) => {
-                            let (ident, url, filename) = token_ctx.get(&token).unwrap();
-                            let package_cache = package_caches.get_mut(ident).unwrap();
-                            let info = package_cache
-                                .inner
-                                .get_mut(filename)
-                                .expect("failed to get cache info");
-
-                            if let Ok(code) = handle.response_code() {
-                                let mut content_length = 0u64;
-                                if code == 200 {
-                                    content_length =
-                                        handle.content_length_download().unwrap_or(0f64) as u64;
-                                    info.remote_size = content_length;
-                                    if content_length != info.local_size {
-                                        total += content_length;
-                                    }
-                                } else {
-                                    debug!("code: {}, ident: {}, url: {}", code, ident, url)
-                                }
-
-                                if content_length == 0 {
-                                    info.estimated = true;
-                                    estimated = true;
-                                }
-
-                                package_cache.update_valid_state();
-                            } else {
-                                debug!("failed to get response code for {}", url);
-                            }
+        Ok(DownloadSize { total, estimated })
+    }
+}
+
+/// Remote size of a download url.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RemoteSize {
+    /// Size of the remote file.
+    size: u64,
+
+    /// Whether the remote server accepts range requests for the file.
+    accept_ranges: bool,
+
+    /// Whether the size is obtained from a fallback request.
+    estimated: bool,
+}
+
+/// Probe the remote size of the file requested by `request`.
+///
+/// A `HEAD` request is sent first, servers rejecting it, or not telling the
+/// size, are then asked for the first byte of the file with a `GET` request,
+/// whose `Content-Range` header tells the full size.
+///
+/// # Returns
+///
+/// The remote size, or `None` if the size is unknown.
+fn probe_size(client: &dyn HttpClient, request: &Request) -> Fallible<Option<RemoteSize>> {
+    let head = client.head(request);
+    match head {
+        Ok(ref resp) if resp.code == 200 && resp.content_length.unwrap_or(0) > 0 => {
+            return Ok(Some(RemoteSize {
+                size: resp.content_length.unwrap_or(0),
+                accept_ranges: resp.accept_ranges,
+                estimated: false,
+            }));
+        }
+        Ok(ref resp) => debug!("HEAD {} responded with {}", request.url, resp.code),
+        Err(ref err) => debug!("HEAD {} failed: {}", request.url, err),
+    }
+
+    let mut request = request.clone();
+    request.range = Some((0, 0));
+
+    // Servers ignoring the range respond with the whole file, the transfer is
+    // aborted once the size is known.
+    let mut full_size = 0;
+    let ret = client.get(&request, &mut |_| Ok(()), &mut |dltotal, dlnow| {
+        full_size = dltotal;
+        dlnow <= 1
+    });
+
+    let size = match ret {
+        Ok(resp) if resp.code == 206 => resp
+            .header("content-range")
+            .and_then(parse_content_range)
+            .map(|size| (size, true)),
+        Ok(resp) => resp
+            .content_length
+            .filter(|&len| len > 0)
+            .map(|l| (l, false)),
+        Err(HttpError::Aborted) => Some((full_size, false)).filter(|_| full_size > 1),
+        Err(err) => {
+            // report the error of the `HEAD` request if both failed
+            head?;
+            return match err {
+                HttpError::Status(_) => Ok(None),
+                err => Err(err.into()),
+            };
+        }
+    };
+
+    Ok(size.map(|(size, accept_ranges)| RemoteSize {
+        size,
+        accept_ranges,
+        estimated: true,
+    }))
+}
+
+/// Parse the full size of a `Content-Range` header value, e.g.
+/// `bytes 0-0/1234`.
+fn parse_content_range(value: &str) -> Option<u64> {
+    let (unit, range) = value.trim().split_once(' ')?;
+    if !unit.eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+    range.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+/// A transfer of a file, or a segment of a file.
+struct Transfer {
+    /// Index of the pending file the transfer belongs to.
+    fidx: usize,
+
+    /// Index of the segment of the file.
+    sidx: usize,
+
+    /// Request of the transfer.
+    request: Request,
+
+    /// Request to the original url when the transfer is from a mirror.
+    fallback: Option<Request>,
+
+    /// Local file to copy instead of sending the request, if any.
+    local: Option<PathBuf>,
+
+    /// File the received data is written to.
+    target: PathBuf,
+}
+
+/// Shared context of running transfers.
+#[derive(Clone, Copy)]
+struct TransferContext<'a> {
+    client: &'a dyn HttpClient,
+    tracker: &'a ProgressTracker,
+    limiter: Option<&'a RateLimiter>,
+
+    /// Maximum number of retries of a transfer.
+    retries: u32,
+
+    /// Initial wait before retrying a transfer.
+    retry_wait: Duration,
+
+    /// Whether all transfers should be aborted.
+    abort: &'a AtomicBool,
+}
+
+/// Rate limiter shared by all transfers of a download.
+///
+/// The limiter works as a token bucket allowing a burst of one second. A
+/// transfer consuming more than available goes into debt and is put to sleep
+/// until the debt is paid, so the overall rate holds no matter how many
+/// connections are open.
+struct RateLimiter {
+    /// Rate limit in bytes per second.
+    rate: u64,
+
+    /// Time of the last refill and the available bytes, negative when in
+    /// debt.
+    state: Mutex<(Instant, f64)>,
+}
+
+impl RateLimiter {
+    fn new(rate: u64) -> RateLimiter {
+        RateLimiter {
+            rate,
+            state: Mutex::new((Instant::now(), 0f64)),
+        }
+    }
+
+    /// Consume `len` bytes, sleeping if the rate limit is exceeded.
+    fn consume(&self, len: usize) {
+        let wait = self.take(len, Instant::now());
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Take `len` bytes from the bucket at the time `now`.
+    ///
+    /// # Returns
+    ///
+    /// The wait until the debt is paid, zero if not in debt.
+    fn take(&self, len: usize, now: Instant) -> Duration {
+        let rate = self.rate as f64;
+        let mut state = self.state.lock().unwrap();
+        let (last, available) = *state;
+        let elapsed = now.saturating_duration_since(last).as_secs_f64();
+        let available = (available + elapsed * rate).min(rate) - len as f64;
+        *state = (now.max(last), available);
+        match available < 0f64 {
+            true => Duration::from_secs_f64(-available / rate),
+            false => Duration::ZERO,
+        }
+    }
+}
+
+impl Transfer {
+    /// Run the transfer, retrying on transient failures.
+    ///
+    /// Falls back to the original url immediately when the mirror fails,
+    /// without consuming a retry attempt.
+    ///
+    /// A server ignoring the range request of a segment responds with the
+    /// whole file, which is then taken for the file so that it falls back to
+    /// an unsegmented transfer. Other segments of the file are skipped, or
+    /// aborted if already running.
+    ///
+    /// # Returns
+    ///
+    /// Whether the transfer has fallen back to the original url.
+    fn run(&self, file: &PendingFile<'_>, ctx: &TransferContext<'_>) -> Fallible<bool> {
+        let TransferContext {
+            client,
+            tracker,
+            limiter,
+            retries,
+            retry_wait,
+            abort,
+        } = *ctx;
+
+        if let Some(src) = self.local.as_ref() {
+            reset_checksum(file);
+            let mut checksum = file.checksum.as_ref().map(|c| c.lock().unwrap());
+            let checksum = checksum.as_mut().and_then(|c| c.as_mut());
+            copy_local(src, &self.target, checksum, |total, now| {
+                !abort.load(Ordering::Relaxed) && tracker.update(self.fidx, self.sidx, total, now)
+            })?;
+            return Ok(false);
+        }
+
+        let mut request = &self.request;
+        let mut fallen_back = false;
+        let mut attempt = 0;
+
+        loop {
+            if file.is_whole() {
+                return Ok(fallen_back);
+            }
+
+            // start over with an empty file, discarding data of failed attempts
+            let mut target = File::create(&self.target)?;
+            reset_checksum(file);
+
+            let ret = client.get(
+                request,
+                &mut |data| {
+                    if let Some(limiter) = limiter {
+                        limiter.consume(data.len());
+                    }
+                    target.write_all(data)?;
+                    if let Some(checksum) = file.checksum.as_ref() {
+                        if let Some(checksum) = checksum.lock().unwrap().as_mut() {
+                            checksum.consume(data);
+                        }
+                    }
+                    Ok(())
+                },
+                &mut |dltotal, dlnow| {
+                    !abort.load(Ordering::Relaxed)
+                        && !file.is_whole()
+                        && tracker.update(self.fidx, self.sidx, dltotal, dlnow)
+                },
+            );
+
+            let err = match ret {
+                Ok(resp) => {
+                    if request.range.is_some() && resp.code != 206 {
+                        if resp.code != 200 {
+                            let msg =
+                                format!("unexpected response code {} for range request", resp.code);
+                            return Err(Error::Custom(msg));
+                        }
+
+                        info!(
+                            "range request ignored by {}, downloading {} unsegmented",
+                            request.url, file.filename
+                        );
+                        let mut whole = file.whole.lock().unwrap();
+                        if whole.is_none() {
+                            *whole = Some(self.target.clone());
+                            tracker.whole(self.fidx, self.sidx);
                         }
                     }
+                    return Ok(fallen_back);
                 }
-            });
+                // aborted in favor of another segment received the whole file
+                Err(_) if file.is_whole() => return Ok(fallen_back),
+                Err(err) => err,
+            };
 
-            if let Some(err) = handle_err {
+            if let (Some(fallback), false) = (self.fallback.as_ref(), fallen_back) {
+                info!(
+                    "mirror failed, falling back to {} (err: {})",
+                    fallback.url, err
+                );
+                request = fallback;
+                fallen_back = true;
+                continue;
+            }
+
+            if attempt >= retries || !err.is_transient() || abort.load(Ordering::Relaxed) {
                 return Err(err.into());
             }
 
-            if alive {
-                self.multi.wait(&mut [], Duration::from_secs(5))?;
+            attempt += 1;
+            let wait = backoff(retry_wait, attempt);
+            info!(
+                "retrying download in {:?} ({}/{}, err: {})",
+                wait, attempt, retries, err
+            );
+            tracker.retrying(self.fidx, self.sidx, attempt, retries, wait);
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// Maximum wait before retrying a transfer.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Get the wait before the `attempt`th retry of a transfer, which is
+/// `retry_wait` doubled on every further retry, up to [`MAX_BACKOFF`].
+fn backoff(retry_wait: Duration, attempt: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+    retry_wait
+        .checked_mul(factor)
+        .unwrap_or(MAX_BACKOFF)
+        .min(MAX_BACKOFF)
+}
+
+/// Run `jobs` on up to `connections` worker threads.
+///
+/// Requests of a [`HttpClient`] are blocking so that any networking stack can
+/// be plugged in, hence transfers are run on worker threads instead of being
+/// driven by a curl multi handle, which could only drive curl's own handles.
+///
+/// Each job is paired with the host it connects to, no more than
+/// `host_connections` jobs of the same host are run at the same time. Once a
+/// job fails, `abort` is set and no further jobs are started.
+///
+/// # Returns
+///
+/// Results of the jobs, in the order they are finished.
+///
+/// # Errors
+///
+/// The error of the first failed job is returned.
+fn run_jobs<T, R, F>(
+    jobs: Vec<(String, T)>,
+    connections: usize,
+    host_connections: usize,
+    abort: &AtomicBool,
+    f: F,
+) -> Fallible<Vec<R>>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> Fallible<R> + Sync,
+{
+    let workers = connections.min(jobs.len());
+    // queued jobs, and the number of running jobs of each host
+    let queue = Mutex::new((VecDeque::from(jobs), HashMap::<String, usize>::new()));
+    let cond = Condvar::new();
+    let results = Mutex::new(vec![]);
+    let errors = Mutex::new(vec![]);
+
+    std::thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| loop {
+                let (host, job) = {
+                    let mut guard = queue.lock().unwrap();
+                    loop {
+                        if abort.load(Ordering::Relaxed) || guard.0.is_empty() {
+                            return;
+                        }
+
+                        let (jobs, running) = &mut *guard;
+                        let next = jobs.iter().position(|(host, _)| {
+                            running.get(host).copied().unwrap_or_default() < host_connections
+                        });
+
+                        if let Some(pos) = next {
+                            let (host, job) = jobs.remove(pos).unwrap();
+                            *running.entry(host.clone()).or_default() += 1;
+                            break (host, job);
+                        }
+
+                        guard = cond.wait(guard).unwrap();
+                    }
+                };
+
+                let ret = f(job);
+
+                if let Some(cnt) = queue.lock().unwrap().1.get_mut(&host) {
+                    *cnt -= 1;
+                }
+
+                match ret {
+                    Ok(ret) => results.lock().unwrap().push(ret),
+                    Err(err) => {
+                        abort.store(true, Ordering::Relaxed);
+                        errors.lock().unwrap().push(err);
+                    }
+                }
+
+                cond.notify_all();
+            });
+        }
+    });
+
+    // errors of transfers aborted due to another failure are not the cause
+    let mut errors = errors.into_inner().unwrap();
+    let pos = errors
+        .iter()
+        .position(|e| !matches!(e, Error::Http(HttpError::Aborted)));
+    if let Some(pos) = pos.or(if errors.is_empty() { None } else { Some(0) }) {
+        return Err(errors.swap_remove(pos));
+    }
+
+    Ok(results.into_inner().unwrap())
+}
+
+/// Host of transfers copying local files.
+const LOCAL_HOST: &str = "localhost";
+
+/// Get the host part of the given `url`.
+fn url_host(url: &str) -> &str {
+    let rest = url.split_once("://").map(|s| s.1).unwrap_or(url);
+    rest.split(['/', '?', '#']).next().unwrap_or(rest)
+}
+
+/// Split a file of `size` bytes into at most `split` inclusive byte ranges,
+/// none of which is smaller than `min_split_size`.
+///
+/// An empty list is returned when the file is not worth splitting.
+fn split_ranges(size: u64, split: u32, min_split_size: u64) -> Vec<(u64, u64)> {
+    let count = (size / min_split_size.max(1)).min(split as u64);
+    if count < 2 {
+        return vec![];
+    }
+
+    let chunk = size.div_ceil(count);
+    (0..count)
+        .map(|i| i * chunk)
+        .take_while(|&start| start < size)
+        .map(|start| (start, (start + chunk).min(size) - 1))
+        .collect()
+}
+
+/// Stitch downloaded segments into `dst` in order, removing the segments.
+fn stitch_segments(
+    parts: &[PathBuf],
+    dst: &Path,
+    mut checksum: Option<&mut Checksum>,
+) -> Fallible<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(dst)?;
+
+    let mut buf = [0; 1024 * 64];
+    for part in parts.iter() {
+        let mut part = File::open(part)?;
+        loop {
+            let len = part.read(&mut buf)?;
+            if len == 0 {
+                break;
+            }
+            file.write_all(&buf[..len])?;
+            if let Some(checksum) = checksum.as_mut() {
+                checksum.consume(&buf[..len]);
             }
         }
+    }
 
-        Ok(DownloadSize { total, estimated })
+    for part in parts.iter() {
+        let _ = std::fs::remove_file(part);
+    }
+
+    Ok(())
+}
+
+/// Get the local path of a download `url`.
+///
+/// `file://` URLs and local or UNC paths are considered local, and are copied
+/// into the cache instead of being downloaded.
+///
+/// # Returns
+///
+/// The local path, or `None` if the url is a remote one.
+fn local_path(url: &str) -> Option<PathBuf> {
+    if let Some(path) = url.strip_prefix("file://") {
+        let path = internal::percent_decode(path);
+        let path = match path.strip_prefix('/') {
+            // `file:///C:/path` on Windows
+            Some(rest) if rest.get(1..2) == Some(":") => rest.to_owned(),
+            Some(_) => path,
+            // `file://server/share/path` refers to a UNC path
+            None => format!("//{}", path),
+        };
+        return Some(PathBuf::from(path));
+    }
+
+    let is_unc = url.starts_with(r"\\");
+    let is_drive = matches!(url.get(1..3), Some(r":\" | ":/"));
+    if is_unc || is_drive || url.starts_with('/') {
+        return Some(PathBuf::from(url));
+    }
+
+    None
+}
+
+/// Copy a local file `src` to `dst`, reporting progress with `(total, now)`.
+///
+/// Returning `false` from `progress` aborts the copy with an
+/// [`Aborted`][1] error.
+///
+/// [1]: HttpError::Aborted
+fn copy_local<F>(
+    src: &Path,
+    dst: &Path,
+    mut checksum: Option<&mut Checksum>,
+    mut progress: F,
+) -> Fallible<()>
+where
+    F: FnMut(u64, u64) -> bool,
+{
+    let mut src = File::open(src)?;
+    let total = src.metadata()?.len();
+    let mut dst = File::create(dst)?;
+
+    let mut buf = [0; 1024 * 64];
+    let mut now = 0;
+    loop {
+        let len = src.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        dst.write_all(&buf[..len])?;
+        if let Some(checksum) = checksum.as_mut() {
+            checksum.consume(&buf[..len]);
+        }
+        now += len as u64;
+        if !progress(total, now) {
+            return Err(Error::Http(HttpError::Aborted));
+        }
+    }
+
+    Ok(())
+}
+
+/// Create a checksum for verifying data against the given `hash`.
+fn new_checksum(hash: &HashString) -> Checksum {
+    ChecksumBuilder::new().algo(hash.algorithm()).build()
+}
+
+/// Restart the streaming checksum of a file whose data has been discarded.
+fn reset_checksum(file: &PendingFile<'_>) {
+    if let (Some(hash), Some(checksum)) = (file.hash, &file.checksum) {
+        *checksum.lock().unwrap() = Some(new_checksum(hash));
     }
 }
 
-fn set_cookie(easy: &mut Easy, cookie: &[(&str, &str)]) -> Fallible<()> {
+/// Get HTTP headers to be sent along with requests to `url`.
+///
+/// Besides the cookie of the package, headers of all `private_hosts` entries
+/// matching the url are included, and the `gh_token` is sent as a bearer
+/// token to GitHub.
+fn request_headers(config: &Config, url: &str, cookie: &[(&str, &str)]) -> Vec<String> {
+    let mut headers = vec![];
+
     if !cookie.is_empty() {
         let mut header_cookie = String::from("Cookie: ");
         header_cookie.push_str(
@@ -440,14 +1275,99 @@ fn set_cookie(easy: &mut Easy, cookie: &[(&str, &str)]) -> Fallible<()> {
                 .collect::<Vec<_>>()
                 .join("; "),
         );
-        let mut list = List::new();
-        list.append(&header_cookie)?;
-        easy.http_headers(list)?;
+        headers.push(header_cookie);
+    }
+
+    headers.extend(
+        config
+            .private_hosts()
+            .iter()
+            .filter(|h| h.is_match(url))
+            .flat_map(|h| h.headers())
+            .map(|(name, value)| format!("{}: {}", name, value)),
+    );
+
+    if REGEX_GITHUB_URL.is_match(url) {
+        if let Some(token) = config.gh_token() {
+            headers.push(format!("Authorization: Bearer {}", token));
+        }
     }
 
+    headers
+}
+
+/// Fetch the content of `url` into memory, such as a web page or an API
+/// response.
+///
+/// The `user_agent` overrides the user agent of the session if given.
+///
+/// # Errors
+///
+/// An [`Http`][1] error will be returned if the request failed.
+///
+/// [1]: crate::Error::Http
+pub(crate) fn fetch(session: &Session, url: &str, user_agent: Option<&str>) -> Fallible<Vec<u8>> {
+    let mut content = vec![];
+    fetch_with(session, url, user_agent, &mut |data| {
+        content.extend_from_slice(data);
+        Ok(())
+    })?;
+    Ok(content)
+}
+
+/// Fetch the content of `url`, streaming it to `body`.
+///
+/// See [`fetch`] for details.
+pub(crate) fn fetch_with(
+    session: &Session,
+    url: &str,
+    user_agent: Option<&str>,
+    body: &mut dyn FnMut(&[u8]) -> std::io::Result<()>,
+) -> Fallible<()> {
+    let config = session.config();
+    let user_agent = user_agent
+        .or_else(|| session.user_agent.get().map(|s| s.as_str()))
+        .unwrap_or(DEFAULT_USER_AGENT);
+
+    let mut request = Request::new(url);
+    request.headers = request_headers(&config, url, &[]);
+    request.proxy = internal::proxy::resolve(config.proxy(), url);
+    request.user_agent = user_agent.to_owned();
+    request.connect_timeout = config.download_connect_timeout();
+    drop(config);
+
+    session
+        .http_client()
+        .get(&request, body, &mut |_, _| true)?;
     Ok(())
 }
 
+/// State of a file transfer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DownloadState {
+    /// The file is being downloaded.
+    Downloading,
+
+    /// The transfer has failed and is going to be retried after `wait`.
+    Retrying {
+        /// The upcoming attempt, starting from 1.
+        attempt: u32,
+
+        /// Maximum number of retries.
+        retries: u32,
+
+        /// Time to wait before retrying.
+        wait: Duration,
+    },
+
+    /// The transfer has been resumed after a retry.
+    Resumed,
+
+    /// The file has been downloaded.
+    Done,
+}
+
 /// Progress context for package download.
 #[derive(Clone, Debug)]
 pub struct PackageDownloadProgressContext {
@@ -460,34 +1380,354 @@ pub struct PackageDownloadProgressContext {
     /// Download filename.
     pub filename: String,
 
-    /// Total bytes to download.
+    /// Total bytes to download, `0` if unknown.
     pub dltotal: u64,
 
     /// Downloaded bytes.
     pub dlnow: u64,
+
+    /// Transfer rate in bytes per second.
+    pub rate: u64,
+
+    /// Estimated time remaining, `None` if unknown.
+    pub eta: Option<Duration>,
+
+    /// State of the transfer.
+    pub state: DownloadState,
+}
+
+/// Overall progress context of a package download.
+#[derive(Clone, Debug)]
+pub struct PackageDownloadOverallContext {
+    /// Total bytes to download of all files, `0` if unknown.
+    pub dltotal: u64,
+
+    /// Downloaded bytes of all files.
+    pub dlnow: u64,
+
+    /// Overall transfer rate in bytes per second.
+    pub rate: u64,
+
+    /// Estimated time remaining, `None` if unknown.
+    pub eta: Option<Duration>,
+
+    /// Number of files downloaded.
+    pub files_done: usize,
+
+    /// Number of files to download.
+    pub files_total: usize,
+}
+
+/// Context of a downloaded package file.
+#[derive(Clone, Debug)]
+pub struct PackageDownloadFileContext {
+    /// Package identifier.
+    pub ident: String,
+
+    /// Original download URL.
+    pub url: String,
+
+    /// Download filename.
+    pub filename: String,
+
+    /// URL the file was actually downloaded from.
+    ///
+    /// This is the mirror URL if any segment of the file was served by the
+    /// mirror, see `mixed`.
+    pub source: String,
+
+    /// Whether the file was served by a mirror.
+    pub mirrored: bool,
+
+    /// Whether some segments of the file were served by the mirror, while the
+    /// others fell back to the original URL.
+    pub mixed: bool,
+}
+
+/// Minimum interval between two progress events of the same file.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Window of samples the transfer rate is calculated from.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// Transfer rate meter over a sliding window.
+struct RateMeter {
+    /// Samples of the received bytes, oldest first.
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl RateMeter {
+    fn new() -> RateMeter {
+        RateMeter {
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Record the total number of bytes received so far.
+    fn record(&mut self, received: u64) {
+        let now = Instant::now();
+        while self.samples.len() > 1 && now - self.samples[1].0 >= RATE_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((now, received));
+    }
+
+    /// Get the transfer rate in bytes per second.
+    fn rate(&self) -> u64 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some(&(start, first)), Some(&(end, last))) => {
+                let elapsed = (end - start).as_secs_f64();
+                if elapsed < PROGRESS_INTERVAL.as_secs_f64() {
+                    return 0;
+                }
+                ((last - first) as f64 / elapsed) as u64
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Estimate time remaining of a transfer.
+fn eta(total: u64, now: u64, rate: u64) -> Option<Duration> {
+    match total > 0 && rate > 0 {
+        true => Some(Duration::from_secs(
+            total.saturating_sub(now).div_ceil(rate),
+        )),
+        false => None,
+    }
 }
 
-/// Report package download progress.
-fn progress(
-    tx: Sender<Event>,
+/// Progress of a file being downloaded.
+struct FileProgress {
     ident: String,
     url: String,
     filename: String,
-    dltotal: f64,
-    dlnow: f64,
-) -> bool {
-    let ctx = PackageDownloadProgressContext {
-        ident,
-        url,
-        filename,
-        dltotal: dltotal as u64,
-        dlnow: dlnow as u64,
-    };
 
-    // TODO: progress threshold
-    // it's not that efficient to send progress event to report every progress
-    // change.
-    tx.send(Event::PackageDownloadProgress(ctx)).is_ok()
+    /// Total bytes of the file, `0` if unknown.
+    total: u64,
+
+    /// Downloaded bytes of each segment.
+    segments: Vec<u64>,
+
+    /// Number of segments finished.
+    segments_done: usize,
+
+    /// Segment which received the whole file, if any.
+    whole: Option<usize>,
+
+    /// Received bytes including data discarded by retries.
+    received: u64,
+
+    meter: RateMeter,
+    state: DownloadState,
+    last_emit: Option<Instant>,
+}
+
+impl FileProgress {
+    fn now(&self) -> u64 {
+        self.segments.iter().sum()
+    }
+
+    fn context(&self) -> PackageDownloadProgressContext {
+        let rate = self.meter.rate();
+        PackageDownloadProgressContext {
+            ident: self.ident.clone(),
+            url: self.url.clone(),
+            filename: self.filename.clone(),
+            dltotal: self.total,
+            dlnow: self.now(),
+            rate,
+            eta: eta(self.total, self.now(), rate),
+            state: self.state,
+        }
+    }
+}
+
+/// Inner state of a [`ProgressTracker`].
+struct TrackerState {
+    files: Vec<FileProgress>,
+    files_done: usize,
+
+    /// Received bytes of all files including data discarded by retries.
+    received: u64,
+
+    meter: RateMeter,
+    last_emit: Option<Instant>,
+}
+
+/// Bookkeeping of the download progress, emitting per-file and overall
+/// progress events.
+///
+/// Events are throttled to one per [`PROGRESS_INTERVAL`], except for state
+/// changes which are always reported.
+struct ProgressTracker {
+    emitter: Option<Sender<Event>>,
+    state: Mutex<TrackerState>,
+}
+
+impl ProgressTracker {
+    fn new(emitter: Option<Sender<Event>>) -> ProgressTracker {
+        ProgressTracker {
+            emitter,
+            state: Mutex::new(TrackerState {
+                files: vec![],
+                files_done: 0,
+                received: 0,
+                meter: RateMeter::new(),
+                last_emit: None,
+            }),
+        }
+    }
+
+    /// Add a file of `total` bytes downloaded in `segments` segments.
+    ///
+    /// # Returns
+    ///
+    /// Index of the file in the tracker.
+    fn add(&self, ident: &str, url: &str, filename: &str, total: u64, segments: usize) -> usize {
+        let mut state = self.state.lock().unwrap();
+        state.files.push(FileProgress {
+            ident: ident.to_owned(),
+            url: url.to_owned(),
+            filename: filename.to_owned(),
+            total,
+            segments: vec![0; segments.max(1)],
+            segments_done: 0,
+            whole: None,
+            received: 0,
+            meter: RateMeter::new(),
+            state: DownloadState::Downloading,
+            last_emit: None,
+        });
+        state.files.len() - 1
+    }
+
+    /// Update the downloaded bytes of a segment of a file.
+    ///
+    /// `dltotal` is the total bytes of the segment reported by the transfer,
+    /// which is taken as the size of the file if it is not segmented.
+    ///
+    /// # Returns
+    ///
+    /// `false` if events can no longer be delivered.
+    fn update(&self, fidx: usize, sidx: usize, dltotal: u64, dlnow: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let file = &mut state.files[fidx];
+
+        let prev = file.segments[sidx];
+        if dlnow == prev || file.whole.is_some() {
+            return true;
+        }
+
+        // a restarted transfer reports from zero again
+        let delta = dlnow.saturating_sub(prev);
+        file.segments[sidx] = dlnow;
+        if file.segments.len() == 1 && dltotal > 0 {
+            file.total = dltotal;
+        }
+        file.received += delta;
+        let received = file.received;
+        file.meter.record(received);
+        if let DownloadState::Retrying { .. } = file.state {
+            file.state = DownloadState::Resumed;
+            file.last_emit = None;
+        }
+
+        state.received += delta;
+        let received = state.received;
+        state.meter.record(received);
+
+        self.emit(&mut state, fidx, false)
+    }
+
+    /// Mark a segment of a file as failed and about to be retried, the data
+    /// received by the segment is discarded.
+    fn retrying(&self, fidx: usize, sidx: usize, attempt: u32, retries: u32, wait: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let file = &mut state.files[fidx];
+        file.segments[sidx] = 0;
+        file.state = DownloadState::Retrying {
+            attempt,
+            retries,
+            wait,
+        };
+        self.emit(&mut state, fidx, true);
+    }
+
+    /// Mark a segment of a file as having received the whole file, the data
+    /// received by other segments is discarded.
+    fn whole(&self, fidx: usize, sidx: usize) {
+        let mut state = self.state.lock().unwrap();
+        let file = &mut state.files[fidx];
+        for (idx, segment) in file.segments.iter_mut().enumerate() {
+            if idx != sidx {
+                *segment = 0;
+            }
+        }
+        file.whole = Some(sidx);
+    }
+
+    /// Mark a segment of a file as finished.
+    fn finish(&self, fidx: usize) {
+        let mut state = self.state.lock().unwrap();
+        let file = &mut state.files[fidx];
+        file.segments_done += 1;
+        if file.segments_done < file.segments.len() {
+            return;
+        }
+
+        file.state = DownloadState::Done;
+        // the size of a file is known once it is downloaded
+        file.total = file.now();
+        state.files_done += 1;
+        self.emit(&mut state, fidx, true);
+    }
+
+    /// Emit progress events of the given file and the overall progress.
+    fn emit(&self, state: &mut TrackerState, fidx: usize, force: bool) -> bool {
+        let tx = match self.emitter.as_ref() {
+            None => return true,
+            Some(tx) => tx,
+        };
+
+        let due = |last: Option<Instant>| match last {
+            None => true,
+            Some(last) => last.elapsed() >= PROGRESS_INTERVAL,
+        };
+
+        let file = &mut state.files[fidx];
+        if force || due(file.last_emit) {
+            file.last_emit = Some(Instant::now());
+            if tx
+                .send(Event::PackageDownloadProgress(file.context()))
+                .is_err()
+            {
+                return false;
+            }
+        }
+
+        if force || due(state.last_emit) {
+            state.last_emit = Some(Instant::now());
+            let (dltotal, dlnow) = state
+                .files
+                .iter()
+                .fold((0, 0), |(t, n), f| (t + f.total, n + f.now()));
+            let rate = state.meter.rate();
+            let ctx = PackageDownloadOverallContext {
+                dltotal,
+                dlnow,
+                rate,
+                eta: eta(dltotal, dlnow, rate),
+                files_done: state.files_done,
+                files_total: state.files.len(),
+            };
+            if tx.send(Event::PackageDownloadOverallProgress(ctx)).is_err() {
+                return false;
+            }
+        }
+
+        true
+    }
 }
 
 // #[derive(Debug)]
@@ -794,3 +2034,615 @@ fn progress(
 
 //     Ok(())
 // }
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::{http::test::Server, package::manifest::Manifest, session::test};
+
+    /// Create a session of a temporary Scoop root downloading from `server`.
+    fn temp_session(name: &str, config: serde_json::Value, server: &Server) -> Session {
+        let session = test::temp_session(&format!("download-{}", name), config);
+        session.set_http_client(server.clone()).unwrap();
+        session
+    }
+
+    /// Create a package downloading `data` from `url`.
+    fn temp_package(session: &Session, url: &str, data: &[u8]) -> Package {
+        let mut checksum = ChecksumBuilder::new().sha256().build();
+        checksum.consume(data);
+        let json = serde_json::json!({
+            "version": "1.0",
+            "homepage": "https://example.com",
+            "license": "MIT",
+            "url": url,
+            "hash": checksum.finalize(),
+        });
+        let path = test::add_manifest(session, "main", "foo", &json);
+        Package::from("foo", "main", Manifest::parse(path).unwrap())
+    }
+
+    /// Download `package`, returning the downloaded data.
+    fn download(session: &Session, package: &Package) -> Fallible<Vec<u8>> {
+        let packages = [package];
+        let mut set = PackageSet::new(session, &packages, false, true)?;
+        set.calculate_download_size()?;
+        set.download()?;
+
+        let cache_root = session.config().cache_path().to_owned();
+        let filename = &package.download_filenames()[0];
+        assert!(set.is_verified(filename));
+        Ok(std::fs::read(cache_root.join(filename))?)
+    }
+
+    /// Create a pending file downloaded to `tmp`, without hash check.
+    fn pending_file(url: &str, tmp: PathBuf) -> PendingFile<'static> {
+        PendingFile {
+            ident: "main/foo".to_owned(),
+            name: "foo".to_owned(),
+            filename: "foo.zip".to_owned(),
+            url: url.to_owned(),
+            mirror: None,
+            parts: vec![],
+            path: tmp.with_extension(""),
+            tmp,
+            hash: None,
+            checksum: None,
+            whole: Mutex::new(None),
+        }
+    }
+
+    /// Get urls and ranges of the `GET` requests received by `server`.
+    fn gets(server: &Server) -> Vec<(String, Option<(u64, u64)>)> {
+        server
+            .requests()
+            .into_iter()
+            // `GET` requests probing the size ask for the first byte only
+            .filter(|r| r.range != Some((0, 0)))
+            .map(|r| (r.url, r.range))
+            .collect()
+    }
+
+    #[test]
+    fn test_local_path() {
+        let path = |s: &str| Some(PathBuf::from(s));
+
+        assert_eq!(local_path("file:///tmp/a%20b.zip"), path("/tmp/a b.zip"));
+        assert_eq!(local_path("file:///C:/dl/a.zip"), path("C:/dl/a.zip"));
+        assert_eq!(
+            local_path("file://server/share/a.zip"),
+            path("//server/share/a.zip")
+        );
+        assert_eq!(
+            local_path(r"\\server\share\a.zip"),
+            path(r"\\server\share\a.zip")
+        );
+        assert_eq!(local_path(r"C:\dl\a.zip"), path(r"C:\dl\a.zip"));
+        assert_eq!(local_path("https://example.com/a.zip"), None);
+    }
+
+    #[test]
+    fn test_parse_content_range() {
+        assert_eq!(parse_content_range("bytes 0-0/1234"), Some(1234));
+        assert_eq!(parse_content_range("bytes */1234"), Some(1234));
+        assert_eq!(parse_content_range("bytes 0-0/*"), None);
+        assert_eq!(parse_content_range("items 0-0/1234"), None);
+    }
+
+    #[test]
+    fn test_split_ranges() {
+        // not worth splitting
+        assert!(split_ranges(0, 5, 1024).is_empty());
+        assert!(split_ranges(1500, 5, 1024).is_empty());
+        assert!(split_ranges(10240, 1, 1024).is_empty());
+
+        assert_eq!(split_ranges(2048, 5, 1024), vec![(0, 1023), (1024, 2047)]);
+        assert_eq!(split_ranges(10, 3, 1), vec![(0, 3), (4, 7), (8, 9)],);
+        // the last range is shortened to the file size
+        assert_eq!(split_ranges(3000, 2, 1024), vec![(0, 1499), (1500, 2999)]);
+        assert_eq!(split_ranges(2049, 2, 1024), vec![(0, 1024), (1025, 2048)]);
+
+        // ranges cover the whole file without gaps
+        for (size, split, min) in [(10_000_001, 16, 1_048_576), (7, 7, 1), (100, 3, 0)] {
+            let ranges = split_ranges(size, split, min);
+            assert!(ranges.len() <= split as usize);
+            assert_eq!(ranges.first().unwrap().0, 0);
+            assert_eq!(ranges.last().unwrap().1, size - 1);
+            for pair in ranges.windows(2) {
+                assert_eq!(pair[0].1 + 1, pair[1].0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_jobs() {
+        let abort = AtomicBool::new(false);
+        let running = Mutex::new(HashMap::<&str, usize>::new());
+        let peak = AtomicUsize::new(0);
+        let jobs = ["a", "a", "a", "b", "b", "c"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, host)| (host.to_owned(), (host, i)))
+            .collect::<Vec<_>>();
+
+        let mut done = run_jobs(jobs, 4, 2, &abort, |(host, i)| {
+            let cnt = {
+                let mut running = running.lock().unwrap();
+                let cnt = running.entry(host).or_default();
+                *cnt += 1;
+                *cnt
+            };
+            peak.fetch_max(cnt, Ordering::Relaxed);
+            std::thread::sleep(Duration::from_millis(20));
+            *running.lock().unwrap().get_mut(host).unwrap() -= 1;
+            Ok(i)
+        })
+        .unwrap();
+        done.sort();
+        assert_eq!(done, vec![0, 1, 2, 3, 4, 5]);
+        // no more than 2 jobs of the same host at the same time
+        assert_eq!(peak.load(Ordering::Relaxed), 2);
+
+        // the failure is reported over the transfers aborted due to it
+        let abort = AtomicBool::new(false);
+        let jobs = (0..8).map(|i| ("a".to_owned(), i)).collect::<Vec<_>>();
+        let ret = run_jobs(jobs, 2, 2, &abort, |i| match i {
+            0 => {
+                std::thread::sleep(Duration::from_millis(20));
+                Err(Error::Http(HttpError::Aborted))
+            }
+            1 => Err(Error::Custom("failed".to_owned())),
+            _ => Ok(i),
+        });
+        assert!(matches!(ret, Err(Error::Custom(msg)) if msg == "failed"));
+        assert!(abort.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_download_segmented() {
+        let url = "https://example.com/foo.zip";
+        let data = (0..100u8).collect::<Vec<_>>();
+        let server = Server::default().serve(url, &data);
+        let config = serde_json::json!({
+            "aria2-enabled": true,
+            "aria2-split": 4,
+            "aria2-min-split-size": "16",
+        });
+        let session = temp_session("segmented", config, &server);
+        let package = temp_package(&session, url, &data);
+
+        assert_eq!(download(&session, &package).unwrap(), data);
+        let ranges = gets(&server)
+            .into_iter()
+            .map(|(_, range)| range.unwrap())
+            .collect::<HashSet<_>>();
+        let expected = [(0, 24), (25, 49), (50, 74), (75, 99)];
+        assert_eq!(ranges, HashSet::from(expected));
+
+        // segments are removed once stitched
+        let cache_root = session.config().cache_path().to_owned();
+        assert_eq!(std::fs::read_dir(cache_root).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_download_ranges_ignored() {
+        let url = "https://example.com/foo.zip";
+        let data = (0..100u8).collect::<Vec<_>>();
+        let server = Server::default().serve(url, &data).ignore_ranges();
+        let config = serde_json::json!({
+            "aria2-enabled": true,
+            "aria2-split": 4,
+            "aria2-min-split-size": "16",
+        });
+        let session = temp_session("ranges-ignored", config, &server);
+        let package = temp_package(&session, url, &data);
+
+        // the whole file received by a segment is taken as is
+        assert_eq!(download(&session, &package).unwrap(), data);
+        assert!(gets(&server).iter().all(|(_, range)| range.is_some()));
+
+        let cache_root = session.config().cache_path().to_owned();
+        assert_eq!(std::fs::read_dir(cache_root).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_download_mirror_fallback() {
+        let url = "https://example.com/foo.zip";
+        let mirror = "https://mirror.example.org/foo.zip";
+        let data = b"foo".repeat(40);
+        let config = serde_json::json!({
+            "mirrors": { "https://example.com/": "https://mirror.example.org/" },
+        });
+
+        // served by the mirror
+        let server = Server::default().serve(mirror, &data);
+        let session = temp_session("mirror", config.clone(), &server);
+        let package = temp_package(&session, url, &data);
+        assert_eq!(download(&session, &package).unwrap(), data);
+        assert_eq!(gets(&server), vec![(mirror.to_owned(), None)]);
+
+        // the mirror fails, falling back to the original url
+        let server = Server::default().serve(url, &data);
+        let session = temp_session("mirror-fallback", config, &server);
+        let package = temp_package(&session, url, &data);
+        assert_eq!(download(&session, &package).unwrap(), data);
+        let expected = vec![(mirror.to_owned(), None), (url.to_owned(), None)];
+        assert_eq!(gets(&server), expected);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let secs = Duration::from_secs;
+        assert_eq!(backoff(secs(1), 1), secs(1));
+        assert_eq!(backoff(secs(1), 3), secs(4));
+        assert_eq!(backoff(secs(1), 7), MAX_BACKOFF);
+        assert_eq!(backoff(secs(90), 1), MAX_BACKOFF);
+        // no overflow of large waits or retries
+        assert_eq!(backoff(secs(u64::MAX), 2), MAX_BACKOFF);
+        assert_eq!(backoff(secs(1), u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_transfer_retry() {
+        let url = "https://example.com/foo.zip";
+        let dir = std::env::temp_dir().join("libscoop-download-retry");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = pending_file(url, dir.join("foo.zip.download"));
+
+        let run = |server: &Server, retries: u32| {
+            let (tx, rx) = flume::unbounded();
+            let tracker = ProgressTracker::new(Some(tx));
+            let fidx = tracker.add(&file.ident, url, &file.filename, 0, 1);
+            let abort = AtomicBool::new(false);
+            let ctx = TransferContext {
+                client: server,
+                tracker: &tracker,
+                limiter: None,
+                retries,
+                retry_wait: Duration::from_millis(1),
+                abort: &abort,
+            };
+            let transfer = Transfer {
+                fidx,
+                sidx: 0,
+                request: Request::new(url),
+                fallback: None,
+                local: None,
+                target: file.tmp.clone(),
+            };
+            let ret = transfer.run(&file, &ctx);
+            drop(tracker);
+
+            let waits = rx
+                .iter()
+                .filter_map(|event| match event {
+                    Event::PackageDownloadProgress(ctx) => match ctx.state {
+                        DownloadState::Retrying { attempt, wait, .. } => Some((attempt, wait)),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            (ret, waits, server.requests().len())
+        };
+        let ms = Duration::from_millis;
+
+        // transient errors are retried with exponential backoff
+        let server = Server::default()
+            .serve(url, b"foo")
+            .fail(url, HttpError::Timeout("timed out".to_owned()))
+            .fail(url, HttpError::Status(503))
+            .fail(url, HttpError::Interrupted("reset".to_owned()));
+        let (ret, waits, requests) = run(&server, 3);
+        assert!(!ret.unwrap());
+        assert_eq!(waits, vec![(1, ms(1)), (2, ms(2)), (3, ms(4))]);
+        assert_eq!(requests, 4);
+        // data of failed attempts is discarded
+        assert_eq!(std::fs::read(&file.tmp).unwrap(), b"foo");
+
+        // giving up once retries are exhausted
+        let server = Server::default()
+            .serve(url, b"foo")
+            .fail(url, HttpError::Status(502))
+            .fail(url, HttpError::Status(502));
+        let (ret, waits, requests) = run(&server, 1);
+        assert!(matches!(ret, Err(Error::Http(HttpError::Status(502)))));
+        assert_eq!(waits.len(), 1);
+        assert_eq!(requests, 2);
+
+        // other errors are not retried
+        let server = Server::default()
+            .serve(url, b"foo")
+            .fail(url, HttpError::Status(403));
+        let (ret, waits, requests) = run(&server, 3);
+        assert!(matches!(ret, Err(Error::Http(HttpError::Status(403)))));
+        assert!(waits.is_empty());
+        assert_eq!(requests, 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_request_headers() {
+        let url = "https://private.example.com/foo.zip";
+        let server = Server::default().serve(url, b"foo");
+        let config = serde_json::json!({
+            "gh_token": "ghp_secret",
+            "private_hosts": [
+                { "match": "private\\.example\\.com", "headers": "Authorization=token abc; X-Foo: bar" },
+                { "match": "other.example.com", "headers": "X-Other: baz" },
+            ],
+        });
+        let session = temp_session("headers", config, &server);
+        let package = temp_package(&session, url, b"foo");
+        download(&session, &package).unwrap();
+
+        // headers of the matching private host are sent along with downloads
+        let requests = server.requests();
+        assert!(!requests.is_empty());
+        for request in requests {
+            assert_eq!(request.headers, ["Authorization: token abc", "X-Foo: bar"]);
+        }
+
+        let config = session.config();
+        let headers = |url: &str| request_headers(&config, url, &[]);
+        let bearer = "Authorization: Bearer ghp_secret";
+        for url in [
+            "https://github.com/foo/bar/releases/download/v1.0/foo.zip",
+            "https://api.github.com/repos/foo/bar/releases/latest",
+            "https://raw.githubusercontent.com/foo/bar/main/foo.json",
+            "https://objects.githubusercontent.com/foo.zip",
+        ] {
+            assert_eq!(headers(url), [bearer], "{}", url);
+        }
+
+        // the token is never sent to other hosts
+        assert!(headers("https://example.com/foo.zip").is_empty());
+        assert!(headers("https://github.com.example.com/foo.zip").is_empty());
+        assert!(headers("https://example.com/?https://github.com/").is_empty());
+        assert_eq!(
+            headers("https://other.example.com/foo.zip"),
+            ["X-Other: baz"]
+        );
+
+        let cookie = [("session", "abc"), ("lang", "en")];
+        assert_eq!(
+            request_headers(&config, "https://example.com/foo.zip", &cookie),
+            ["Cookie: session=abc; lang=en"]
+        );
+    }
+
+    #[test]
+    fn test_download_mirror_segments() {
+        let url = "https://example.com/foo.zip";
+        let mirror = "https://mirror.example.org/foo.zip";
+        let data = (0..100u8).collect::<Vec<_>>();
+        let config = serde_json::json!({
+            "aria2-enabled": true,
+            "aria2-split": 4,
+            "aria2-min-split-size": "16",
+            "mirrors": { "https://example.com/": "https://mirror.example.org/" },
+        });
+
+        let file_done = |server: &Server| {
+            let session = temp_session("mirror-segments", config.clone(), server);
+            let rx = session.event_bus().receiver();
+            let package = temp_package(&session, url, &data);
+            assert_eq!(download(&session, &package).unwrap(), data);
+            rx.try_iter()
+                .find_map(|event| match event {
+                    Event::PackageDownloadFileDone(ctx) => Some(ctx),
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        // all segments are served by the mirror
+        let server = Server::default().serve(url, &data).serve(mirror, &data);
+        let ctx = file_done(&server);
+        assert_eq!(ctx.source, mirror);
+        assert!(ctx.mirrored && !ctx.mixed);
+
+        // a segment falls back to the original url
+        let server = Server::default()
+            .serve(url, &data)
+            .serve(mirror, &data)
+            .fail(mirror, HttpError::Status(503));
+        let ctx = file_done(&server);
+        assert_eq!(ctx.source, mirror);
+        assert!(ctx.mirrored && ctx.mixed);
+        let urls = gets(&server)
+            .into_iter()
+            .map(|(url, _)| url)
+            .collect::<Vec<_>>();
+        assert_eq!(urls.iter().filter(|&u| u == url).count(), 1);
+        assert_eq!(urls.iter().filter(|&u| u == mirror).count(), 4);
+
+        // the mirror can't serve the file at all
+        let server = Server::default().serve(url, &data);
+        let ctx = file_done(&server);
+        assert_eq!(ctx.source, url);
+        assert!(!ctx.mirrored && !ctx.mixed);
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let rate = 100_000;
+        let ms = Duration::from_millis;
+        let assert_wait = |wait: Duration, expected: Duration| {
+            let diff = wait.as_secs_f64() - expected.as_secs_f64();
+            assert!(diff.abs() < 1e-6, "{:?} != {:?}", wait, expected);
+        };
+
+        // the overall rate holds across connections, each consumption goes
+        // into debt further
+        let limiter = RateLimiter::new(rate);
+        let start = limiter.state.lock().unwrap().0;
+        for i in 1..=40 {
+            assert_wait(limiter.take(2500, start), ms(25 * i));
+        }
+        // the debt is paid after a second
+        assert_wait(limiter.take(0, start + ms(1000)), ms(0));
+
+        // idle time allows a burst of one second at most
+        let start = start + ms(2500);
+        assert_wait(limiter.take(rate as usize, start), ms(0));
+        assert_wait(limiter.take(rate as usize / 2, start), ms(500));
+
+        // consuming sleeps for the debt
+        let limiter = RateLimiter::new(rate);
+        let start = Instant::now();
+        limiter.consume(rate as usize / 20);
+        assert!(start.elapsed() >= ms(50));
+    }
+
+    #[test]
+    fn test_rate_meter() {
+        let secs = Duration::from_secs;
+        let start = Instant::now() - secs(10);
+
+        let mut meter = RateMeter::new();
+        assert_eq!(meter.rate(), 0);
+        meter.samples.push_back((start, 0));
+        meter.samples.push_back((start + secs(2), 2000));
+        assert_eq!(meter.rate(), 1000);
+
+        // samples out of the window are dropped, except for the latest one
+        meter.samples.push_back((start + secs(8), 5000));
+        meter.record(9000);
+        assert_eq!(meter.samples.len(), 3);
+        assert_eq!(meter.samples[0].1, 2000);
+
+        // too few time elapsed to tell
+        let mut meter = RateMeter::new();
+        meter.record(0);
+        meter.record(100);
+        assert_eq!(meter.rate(), 0);
+
+        assert_eq!(eta(1000, 300, 100), Some(secs(7)));
+        assert_eq!(eta(1000, 950, 100), Some(secs(1)));
+        assert_eq!(eta(1000, 1000, 100), Some(secs(0)));
+        assert_eq!(eta(0, 300, 100), None);
+        assert_eq!(eta(1000, 300, 0), None);
+    }
+
+    #[test]
+    fn test_progress_tracker() {
+        let (tx, rx) = flume::unbounded();
+        let tracker = ProgressTracker::new(Some(tx));
+        let fidx = tracker.add(
+            "main/foo",
+            "https://example.com/foo.zip",
+            "foo.zip",
+            1000,
+            2,
+        );
+        let bar = tracker.add("main/bar", "https://example.com/bar.zip", "bar.zip", 0, 1);
+
+        let files = || {
+            rx.try_iter()
+                .filter_map(|event| match event {
+                    Event::PackageDownloadProgress(ctx) => Some(ctx),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let overall = |events: Vec<Event>| {
+            events
+                .into_iter()
+                .filter_map(|event| match event {
+                    Event::PackageDownloadOverallProgress(ctx) => Some(ctx),
+                    _ => None,
+                })
+                .last()
+                .unwrap()
+        };
+
+        assert!(tracker.update(fidx, 0, 500, 200));
+        assert!(tracker.update(fidx, 1, 500, 100));
+        // progress is throttled
+        let events = rx.try_iter().collect::<Vec<_>>();
+        assert_eq!(events.len(), 2);
+        match &events[0] {
+            Event::PackageDownloadProgress(ctx) => {
+                assert_eq!((ctx.dltotal, ctx.dlnow), (1000, 200));
+                assert_eq!(ctx.state, DownloadState::Downloading);
+            }
+            _ => panic!("unexpected event"),
+        }
+        let ctx = overall(events);
+        assert_eq!((ctx.dltotal, ctx.dlnow), (1000, 200));
+        assert_eq!((ctx.files_done, ctx.files_total), (0, 2));
+
+        // the size of an unsegmented file is taken from the transfer
+        assert!(tracker.update(bar, 0, 100, 50));
+        let events = files();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].dltotal, events[0].dlnow), (100, 50));
+
+        // a failed segment is reset and reported immediately
+        let wait = Duration::from_secs(1);
+        tracker.retrying(fidx, 1, 1, 3, wait);
+        let events = files();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].dlnow, 200);
+        let retrying = DownloadState::Retrying {
+            attempt: 1,
+            retries: 3,
+            wait,
+        };
+        assert_eq!(events[0].state, retrying);
+
+        // the transfer resumes on new data
+        assert!(tracker.update(fidx, 1, 500, 50));
+        let events = files();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].dlnow, 250);
+        assert_eq!(events[0].state, DownloadState::Resumed);
+
+        // the rate and ETA are calculated once enough time elapsed
+        std::thread::sleep(PROGRESS_INTERVAL * 2);
+        assert!(tracker.update(fidx, 0, 500, 500));
+        let events = rx.try_iter().collect::<Vec<_>>();
+        let ctx = events
+            .iter()
+            .find_map(|event| match event {
+                Event::PackageDownloadProgress(ctx) => Some(ctx.clone()),
+                _ => None,
+            })
+            .unwrap();
+        // bytes discarded by the retry are counted as well
+        assert!(ctx.rate > 0);
+        assert_eq!(ctx.eta, eta(1000, 550, ctx.rate));
+        let ctx = overall(events);
+        assert!(ctx.rate > 0);
+        assert_eq!(ctx.eta, eta(1100, 600, ctx.rate));
+
+        // a file is done once all its segments are finished
+        tracker.finish(fidx);
+        assert!(files().is_empty());
+        tracker.update(fidx, 1, 500, 500);
+        tracker.finish(fidx);
+        let events = rx.try_iter().collect::<Vec<_>>();
+        let ctx = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::PackageDownloadProgress(ctx) => Some(ctx.clone()),
+                _ => None,
+            })
+            .last()
+            .unwrap();
+        assert_eq!(ctx.state, DownloadState::Done);
+        assert_eq!((ctx.dltotal, ctx.dlnow), (1000, 1000));
+        let ctx = overall(events);
+        assert_eq!((ctx.dltotal, ctx.dlnow), (1100, 1050));
+        assert_eq!((ctx.files_done, ctx.files_total), (1, 2));
+
+        // events can no longer be delivered
+        drop(rx);
+        assert!(!tracker.update(bar, 0, 100, 100));
+    }
+}
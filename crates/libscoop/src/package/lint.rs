@@ -0,0 +1,802 @@
+//! Lint manifests for problems that parsing does not catch.
+//!
+//! Manifests are linted as JSON documents rather than parsed [`Manifest`]s,
+//! so that manifests failing to parse are diagnosed as well. Values of known
+//! fields having the wrong type are found by deserializing the document into
+//! a [`ManifestSpec`]. Each diagnostic locates the offending value with a JSON
+//! pointer (RFC 6901), and comes with a suggested fix if there is an obvious
+//! one.
+//!
+//! [`Manifest`]: super::Manifest
+use serde_json::{Map, Value};
+use serde_path_to_error::Segment;
+
+use super::manifest::ManifestSpec;
+use crate::constant::{REGEX_HASH, SPDX_LIST};
+
+/// Maximum number of type errors reported of a manifest.
+const MAX_TYPE_ERRORS: usize = 20;
+
+/// Known fields of a manifest.
+const MANIFEST_KEYS: &[&str] = &[
+    "##",
+    "$schema",
+    "architecture",
+    "autoupdate",
+    "bin",
+    "checkver",
+    "cookie",
+    "depends",
+    "description",
+    "env_add_path",
+    "env_set",
+    "extract_dir",
+    "extract_to",
+    "hash",
+    "homepage",
+    "innosetup",
+    "installer",
+    "license",
+    "notes",
+    "persist",
+    "post_install",
+    "post_uninstall",
+    "pre_install",
+    "pre_uninstall",
+    "psmodule",
+    "shortcuts",
+    "suggest",
+    "uninstaller",
+    "url",
+    "version",
+];
+
+/// Known fields of an architecture specification.
+const ARCH_KEYS: &[&str] = &[
+    "##",
+    "bin",
+    "checkver",
+    "env_add_path",
+    "env_set",
+    "extract_dir",
+    "extract_to",
+    "hash",
+    "installer",
+    "post_install",
+    "post_uninstall",
+    "pre_install",
+    "pre_uninstall",
+    "shortcuts",
+    "uninstaller",
+    "url",
+];
+
+/// Known architectures.
+const ARCHS: &[&str] = &["32bit", "64bit", "arm64"];
+
+/// Known fields of a checkver specification.
+const CHECKVER_KEYS: &[&str] = &[
+    "##",
+    "github",
+    "jsonpath",
+    "regex",
+    "replace",
+    "reverse",
+    "script",
+    "sourceforge",
+    "url",
+    "useragent",
+    "xpath",
+];
+
+/// Known fields of an autoupdate specification.
+const AUTOUPDATE_KEYS: &[&str] = &[
+    "##",
+    "architecture",
+    "bin",
+    "env_add_path",
+    "env_set",
+    "extract_dir",
+    "extract_to",
+    "hash",
+    "installer",
+    "license",
+    "notes",
+    "persist",
+    "post_install",
+    "psmodule",
+    "shortcuts",
+    "url",
+];
+
+/// Known fields of an autoupdate architecture specification.
+const AUTOUPDATE_ARCH_KEYS: &[&str] = &[
+    "##",
+    "bin",
+    "env_add_path",
+    "env_set",
+    "extract_dir",
+    "extract_to",
+    "hash",
+    "installer",
+    "post_install",
+    "shortcuts",
+    "url",
+];
+
+/// Known fields of a hash extraction.
+const HASH_EXTRACTION_KEYS: &[&str] = &["find", "jsonpath", "mode", "regex", "url", "xpath"];
+
+/// License identifiers accepted by Scoop other than SPDX identifiers.
+const NON_SPDX_LICENSES: &[&str] = &["Freeware", "Proprietary", "Public Domain", "Shareware"];
+
+/// Severity of a [`Diagnostic`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The manifest is broken or will not work as intended.
+    Error,
+
+    /// The manifest works but should be fixed.
+    Warning,
+}
+
+/// A problem found in a manifest.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// JSON pointer to the offending value.
+    pointer: String,
+
+    severity: Severity,
+
+    message: String,
+
+    /// Suggested fix of the problem, if any.
+    fix: Option<String>,
+}
+
+impl Diagnostic {
+    /// Return the JSON pointer to the offending value.
+    ///
+    /// The pointer of a missing field points to where the field should be.
+    #[inline]
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    /// Return the severity of the diagnostic.
+    #[inline]
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Return the message of the diagnostic.
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Return the suggested fix of the diagnostic, if any.
+    #[inline]
+    pub fn fix(&self) -> Option<&str> {
+        self.fix.as_deref()
+    }
+}
+
+/// Append the reference `token` to the JSON `pointer`.
+fn child<T: ToString>(pointer: &str, token: T) -> String {
+    let token = token.to_string().replace('~', "~0").replace('/', "~1");
+    format!("{}/{}", pointer, token)
+}
+
+/// Get the strings of a single string or an array of strings, along with
+/// their pointers.
+fn strings<'a>(value: Option<&'a Value>, pointer: &str) -> Vec<(&'a str, String)> {
+    match value {
+        Some(Value::String(s)) => vec![(s.as_str(), pointer.to_owned())],
+        Some(Value::Array(a)) => a
+            .iter()
+            .enumerate()
+            .filter_map(|(i, v)| v.as_str().map(|s| (s, child(pointer, i))))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Get the Levenshtein distance between `a` and `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = (prev + (ca != *cb) as usize).min(row[j] + 1).min(cur + 1);
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
+/// Normalize the path separators of `path` and strip its leading `./`.
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.trim_start_matches("./").to_ascii_lowercase()
+}
+
+#[derive(Default)]
+struct Linter {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter {
+    fn report(&mut self, pointer: &str, severity: Severity, message: String, fix: Option<String>) {
+        let diagnostic = Diagnostic {
+            pointer: pointer.to_owned(),
+            severity,
+            message,
+            fix,
+        };
+        // fields inherited by architectures may be reported more than once
+        if !self.diagnostics.contains(&diagnostic) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Check the keys of `object` against the `known` keys, with `deprecated`
+    /// keys paired with their replacements.
+    fn keys(
+        &mut self,
+        object: &Map<String, Value>,
+        pointer: &str,
+        known: &[&str],
+        deprecated: &[(&str, &str)],
+    ) {
+        for key in object.keys() {
+            if let Some((_, fix)) = deprecated.iter().find(|(k, _)| k == key) {
+                let msg = format!("field '{}' is deprecated", key);
+                self.report(
+                    &child(pointer, key),
+                    Severity::Warning,
+                    msg,
+                    Some(fix.to_string()),
+                );
+            } else if !known.contains(&key.as_str()) {
+                let similar = known
+                    .iter()
+                    .map(|k| (distance(k, key), k))
+                    .filter(|(d, _)| *d <= 2)
+                    .min();
+                let fix = match similar {
+                    Some((_, k)) => format!("rename it to '{}'", k),
+                    None => "remove the field".to_owned(),
+                };
+                let msg = format!("unknown field '{}'", key);
+                self.report(&child(pointer, key), Severity::Warning, msg, Some(fix));
+            }
+        }
+    }
+
+    /// Check the required fields of the manifest.
+    fn required(&mut self, manifest: &Map<String, Value>) {
+        for key in ["version", "homepage", "license"] {
+            match manifest.get(key) {
+                None => {
+                    let msg = format!("missing required field '{}'", key);
+                    let fix = format!("add the '{}' field", key);
+                    self.report(&child("", key), Severity::Error, msg, Some(fix));
+                }
+                Some(Value::String(s)) if s.trim().is_empty() => {
+                    let msg = format!("field '{}' is empty", key);
+                    self.report(&child("", key), Severity::Error, msg, None);
+                }
+                _ => {}
+            }
+        }
+
+        let has_url = |spec: &Value| spec.get("url").is_some();
+        let arch_has_url = manifest
+            .get("architecture")
+            .and_then(|a| a.as_object())
+            .map(|a| a.values().any(has_url))
+            .unwrap_or(false);
+        if !manifest.contains_key("url") && !arch_has_url {
+            let msg = "missing download url".to_owned();
+            let fix = "add the 'url' field, or add it to the architectures".to_owned();
+            self.report("/url", Severity::Error, msg, Some(fix));
+        }
+    }
+
+    /// Check the license identifiers against the SPDX license list.
+    fn license(&mut self, license: Option<&Value>) {
+        let (identifier, url, pointer) = match license {
+            Some(Value::String(s)) => (s.as_str(), None, "/license".to_owned()),
+            Some(Value::Object(o)) => match o.get("identifier").and_then(|v| v.as_str()) {
+                Some(id) => (id, o.get("url"), "/license/identifier".to_owned()),
+                None => return,
+            },
+            _ => return,
+        };
+
+        for id in identifier.split(['|', ',']).map(str::trim) {
+            if SPDX_LIST.contains(id) || NON_SPDX_LICENSES.contains(&id) || url.is_some() {
+                continue;
+            }
+
+            let similar = SPDX_LIST
+                .iter()
+                .chain(NON_SPDX_LICENSES)
+                .find(|k| k.eq_ignore_ascii_case(id));
+            let fix = match similar {
+                Some(k) => format!("use '{}'", k),
+                None => "use a SPDX license identifier, or add the license 'url'".to_owned(),
+            };
+            let msg = format!("license '{}' is not a SPDX license identifier", id);
+            self.report(&pointer, Severity::Warning, msg, Some(fix));
+        }
+    }
+
+    /// Check the `url`, `hash`, `bin` and `shortcuts` of `spec`, which is the
+    /// manifest or an architecture of it inheriting fields from `base`.
+    fn spec(
+        &mut self,
+        spec: &Map<String, Value>,
+        base: Option<&Map<String, Value>>,
+        pointer: &str,
+        nightly: bool,
+    ) {
+        // an inherited field and its pointer
+        let field = |key: &str| match spec.get(key) {
+            Some(value) => Some((value, child(pointer, key))),
+            None => base.and_then(|b| b.get(key)).map(|v| (v, child("", key))),
+        };
+
+        let hash_pointer = child(pointer, "hash");
+        for (hash, pointer) in strings(spec.get("hash"), &hash_pointer) {
+            if REGEX_HASH.is_match(hash) {
+                continue;
+            }
+            let fixed = hash.trim().to_lowercase();
+            let fix = match REGEX_HASH.is_match(&fixed) {
+                true => format!("use '{}'", fixed),
+                false => "use a hex digest, prefixed with its algorithm unless it is SHA-256, \
+                    e.g. 'sha512:<hex>'"
+                    .to_owned(),
+            };
+            let msg = format!("invalid hash '{}'", hash);
+            self.report(&pointer, Severity::Error, msg, Some(fix));
+        }
+
+        if spec.contains_key("url") || spec.contains_key("hash") {
+            let urls = field("url").map(|(v, p)| strings(Some(v), &p).len());
+            let hashes = field("hash");
+            let fix = Some("provide one hash for each url".to_owned());
+            match (urls, hashes) {
+                (Some(_), None) if !nightly => {
+                    let msg = "missing hash of the download url".to_owned();
+                    self.report(&hash_pointer, Severity::Error, msg, fix);
+                }
+                (Some(urls), Some((hashes, pointer))) => {
+                    let hashes = strings(Some(hashes), &pointer).len();
+                    if urls != hashes {
+                        let msg = format!("{} hash(es) for {} url(s)", hashes, urls);
+                        self.report(&pointer, Severity::Error, msg, fix);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let extract_dirs = field("extract_dir")
+            .map(|(v, p)| strings(Some(v), &p))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(dir, _)| normalize(dir).trim_end_matches('/').to_owned())
+            .filter(|dir| !dir.is_empty())
+            .collect::<Vec<_>>();
+        if extract_dirs.is_empty() {
+            return;
+        }
+
+        let mut paths = vec![];
+        if let Some((bin, pointer)) = field("bin") {
+            match bin {
+                Value::String(s) => paths.push((s.as_str(), pointer)),
+                Value::Array(a) => {
+                    for (i, v) in a.iter().enumerate() {
+                        let pointer = child(&pointer, i);
+                        match v {
+                            Value::String(s) => paths.push((s.as_str(), pointer)),
+                            Value::Array(a) => {
+                                if let Some(s) = a.first().and_then(|v| v.as_str()) {
+                                    paths.push((s, child(&pointer, 0)));
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some((Value::Array(shortcuts), pointer)) = field("shortcuts") {
+            for (i, v) in shortcuts.iter().enumerate() {
+                if let Some(s) = v.get(0).and_then(|v| v.as_str()) {
+                    paths.push((s, child(&child(&pointer, i), 0)));
+                }
+            }
+        }
+
+        for (path, pointer) in paths {
+            let normalized = normalize(path);
+            let dir = extract_dirs
+                .iter()
+                .find(|dir| normalized.starts_with(&format!("{}/", dir)));
+            if let Some(dir) = dir {
+                // normalizing keeps the length of the path except the `./`
+                let stripped = path.replace('\\', "/");
+                let stripped = stripped.trim_start_matches("./");
+                let fixed = &path[path.len() - stripped.len() + dir.len() + 1..];
+                let msg = format!(
+                    "path '{}' points into extract_dir '{}', whose content is extracted to \
+                    the app directory",
+                    path, dir
+                );
+                self.report(
+                    &pointer,
+                    Severity::Error,
+                    msg,
+                    Some(format!("use '{}'", fixed)),
+                );
+            }
+        }
+    }
+
+    /// Check the types of the fields by deserializing the `manifest`.
+    ///
+    /// Deserialization stops at the first error, so the offending value is
+    /// removed and the deserialization is retried to find further errors.
+    /// Missing required fields and values already reported as errors, e.g. a
+    /// malformed hash, are not reported again.
+    fn types(&mut self, manifest: &Map<String, Value>) {
+        let mut json = Value::Object(manifest.clone());
+
+        for _ in 0..MAX_TYPE_ERRORS {
+            // stand in for the required fields missing or removed below
+            if let Value::Object(manifest) = &mut json {
+                for key in ["version", "homepage", "license"] {
+                    manifest
+                        .entry(key)
+                        .or_insert_with(|| Value::String(String::new()));
+                }
+            }
+
+            let err = match serde_path_to_error::deserialize::<_, ManifestSpec>(&json) {
+                Ok(_) => return,
+                Err(err) => err,
+            };
+
+            let mut tokens = vec![];
+            for segment in err.path().iter() {
+                tokens.push(match segment {
+                    Segment::Seq { index } => index.to_string(),
+                    Segment::Map { key } => key.to_owned(),
+                    Segment::Enum { variant } => variant.to_owned(),
+                    Segment::Unknown => break,
+                });
+            }
+            let pointer_of = |tokens: &[String]| {
+                tokens
+                    .iter()
+                    .fold(String::new(), |pointer, token| child(&pointer, token))
+            };
+            let pointer = pointer_of(&tokens);
+
+            let reported = self
+                .diagnostics
+                .iter()
+                .any(|d| d.severity == Severity::Error && d.pointer == pointer);
+            if !reported {
+                let msg = err.inner().to_string();
+                self.report(&pointer, Severity::Error, msg, None);
+            }
+
+            // Remove the offending value, or its closest ancestor keyed in an
+            // object when it is an array element, as removing an element
+            // would shift the pointers of its siblings.
+            let mut removed = false;
+            for idx in (0..tokens.len()).rev() {
+                if let Some(Value::Object(o)) = json.pointer_mut(&pointer_of(&tokens[..idx])) {
+                    removed = o.remove(&tokens[idx]).is_some();
+                    break;
+                }
+            }
+            if !removed {
+                return;
+            }
+        }
+    }
+
+    /// Check the hash extractions of an autoupdate spec.
+    fn hash_extractions(&mut self, hash: Option<&Value>, pointer: &str) {
+        let pointer = child(pointer, "hash");
+        let extractions = match hash {
+            Some(Value::Object(o)) => vec![(o, pointer)],
+            Some(Value::Array(a)) => a
+                .iter()
+                .enumerate()
+                .filter_map(|(i, v)| v.as_object().map(|o| (o, child(&pointer, i))))
+                .collect(),
+            _ => vec![],
+        };
+        for (extraction, pointer) in extractions {
+            let deprecated = [("jp", "rename it to 'jsonpath'")];
+            self.keys(extraction, &pointer, HASH_EXTRACTION_KEYS, &deprecated);
+        }
+    }
+}
+
+/// Lint the manifest `text`.
+///
+/// # Returns
+///
+/// Diagnostics of the problems found, in the order of discovery.
+pub(crate) fn lint(text: &str) -> Vec<Diagnostic> {
+    let mut linter = Linter::default();
+    let json = match serde_json::from_str::<Value>(text) {
+        Ok(json) => json,
+        Err(e) => {
+            let msg = format!("invalid JSON: {}", e);
+            linter.report("", Severity::Error, msg, None);
+            return linter.diagnostics;
+        }
+    };
+    let manifest = match json.as_object() {
+        Some(manifest) => manifest,
+        None => {
+            let msg = "manifest is not a JSON object".to_owned();
+            linter.report("", Severity::Error, msg, None);
+            return linter.diagnostics;
+        }
+    };
+
+    let msi = (
+        "msi",
+        "remove it, msi files are extracted from 'url' as archives",
+    );
+    let nightly = manifest.get("version").and_then(|v| v.as_str()) == Some("nightly");
+
+    linter.required(manifest);
+    linter.keys(manifest, "", MANIFEST_KEYS, &[msi]);
+    linter.license(manifest.get("license"));
+    linter.spec(manifest, None, "", nightly);
+
+    if let Some(archs) = manifest.get("architecture").and_then(|v| v.as_object()) {
+        for (arch, spec) in archs {
+            let pointer = child("/architecture", arch);
+            if !ARCHS.contains(&arch.as_str()) {
+                let msg = format!("unknown architecture '{}'", arch);
+                let fix = format!("use one of {}", ARCHS.join(", "));
+                linter.report(&pointer, Severity::Error, msg, Some(fix));
+                continue;
+            }
+            if let Some(spec) = spec.as_object() {
+                linter.keys(spec, &pointer, ARCH_KEYS, &[msi]);
+                linter.spec(spec, Some(manifest), &pointer, nightly);
+            }
+        }
+    }
+
+    if let Some(checkver) = manifest.get("checkver").and_then(|v| v.as_object()) {
+        let deprecated = [
+            ("re", "rename it to 'regex'"),
+            ("jp", "rename it to 'jsonpath'"),
+        ];
+        linter.keys(checkver, "/checkver", CHECKVER_KEYS, &deprecated);
+    }
+
+    if let Some(autoupdate) = manifest.get("autoupdate").and_then(|v| v.as_object()) {
+        linter.keys(autoupdate, "/autoupdate", AUTOUPDATE_KEYS, &[]);
+        linter.hash_extractions(autoupdate.get("hash"), "/autoupdate");
+
+        let archs = autoupdate.get("architecture").and_then(|v| v.as_object());
+        for (arch, spec) in archs.into_iter().flatten() {
+            let pointer = child("/autoupdate/architecture", arch);
+            if !ARCHS.contains(&arch.as_str()) {
+                let msg = format!("unknown architecture '{}'", arch);
+                let fix = format!("use one of {}", ARCHS.join(", "));
+                linter.report(&pointer, Severity::Error, msg, Some(fix));
+                continue;
+            }
+            if let Some(spec) = spec.as_object() {
+                linter.keys(spec, &pointer, AUTOUPDATE_ARCH_KEYS, &[]);
+                linter.hash_extractions(spec.get("hash"), &pointer);
+            }
+        }
+    }
+
+    linter.types(manifest);
+    linter.diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lint_json(json: &str) -> Vec<(String, Severity, Option<String>)> {
+        lint(json)
+            .into_iter()
+            .map(|d| (d.pointer, d.severity, d.fix))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_types() {
+        let ret = lint_json(
+            r#"{
+                "version": "1.0",
+                "homepage": "https://example.com",
+                "license": "MIT",
+                "url": "https://example.com/foo.zip",
+                "hash": "d2e53aff4d5685340fe37d6637751e025018c38771d868e9bd0cba2d6b0ea25f",
+                "depends": 5,
+                "persist": [1],
+                "architecture": {"64bit": {"bin": {"foo": 1}}}
+            }"#,
+        );
+        let mut pointers = ret
+            .iter()
+            .map(|(pointer, severity, _)| {
+                assert_eq!(*severity, Severity::Error);
+                pointer.as_str()
+            })
+            .collect::<Vec<_>>();
+        pointers.sort_unstable();
+        assert_eq!(
+            pointers,
+            ["/architecture/64bit/bin", "/depends", "/persist/0"]
+        );
+
+        // errors after one in nested arrays are reported as well
+        let ret = lint_json(
+            r#"{
+                "version": "1.0",
+                "homepage": "https://example.com",
+                "license": "MIT",
+                "url": "https://example.com/foo.zip",
+                "hash": "d2e53aff4d5685340fe37d6637751e025018c38771d868e9bd0cba2d6b0ea25f",
+                "bin": [["foo.exe", 1]],
+                "depends": 5
+            }"#,
+        );
+        let pointers = ret.iter().map(|(p, _, _)| p.as_str()).collect::<Vec<_>>();
+        assert_eq!(pointers, ["/bin/0/1", "/depends"], "{:#?}", ret);
+
+        let ret = lint(
+            r#"{
+                "version": "1.0",
+                "homepage": 1,
+                "license": "MIT",
+                "url": "https://example.com/foo.zip",
+                "hash": "d2e53aff4d5685340fe37d6637751e025018c38771d868e9bd0cba2d6b0ea25f"
+            }"#,
+        );
+        assert_eq!(ret.len(), 1, "{:#?}", ret);
+        assert_eq!(ret[0].pointer(), "/homepage");
+        assert!(
+            ret[0].message().contains("invalid type"),
+            "{}",
+            ret[0].message()
+        );
+    }
+
+    #[test]
+    fn test_lint() {
+        let ret = lint_json(
+            r#"{
+                "version": "1.0",
+                "homepage": "https://example.com",
+                "license": "MIT",
+                "url": "https://example.com/foo.zip",
+                "hash": "d2e53aff4d5685340fe37d6637751e025018c38771d868e9bd0cba2d6b0ea25f",
+                "extract_dir": "foo-1.0",
+                "bin": ["foo.exe", ["bar.exe", "bar"]]
+            }"#,
+        );
+        assert!(ret.is_empty());
+
+        let ret = lint_json(
+            r#"{
+                "version": "1.0",
+                "license": "mit",
+                "url": ["https://example.com/a.zip", "https://example.com/b.zip"],
+                "hash": "SHA1:2fd4e1c67a2d28fced849ee1bb76e7391b93eb12",
+                "extract_dir": "foo-1.0",
+                "bin": [".\\foo-1.0\\foo.exe", ["FOO-1.0/bar.exe", "bar"]],
+                "shortcuts": [["foo-1.0\\foo.exe", "Foo"]],
+                "descripton": "typo",
+                "msi": "foo.msi",
+                "checkver": {"re": "v([\\d.]+)"},
+                "autoupdate": {"hash": {"jp": "$.sha256"}, "foo": 1}
+            }"#,
+        );
+        let expected = [
+            (
+                "/homepage",
+                Severity::Error,
+                Some("add the 'homepage' field"),
+            ),
+            (
+                "/descripton",
+                Severity::Warning,
+                Some("rename it to 'description'"),
+            ),
+            ("/msi", Severity::Warning, None),
+            ("/license", Severity::Warning, Some("use 'MIT'")),
+            (
+                "/hash",
+                Severity::Error,
+                Some("use 'sha1:2fd4e1c67a2d28fced849ee1bb76e7391b93eb12'"),
+            ),
+            (
+                "/hash",
+                Severity::Error,
+                Some("provide one hash for each url"),
+            ),
+            ("/bin/0", Severity::Error, Some("use 'foo.exe'")),
+            ("/bin/1/0", Severity::Error, Some("use 'bar.exe'")),
+            ("/shortcuts/0/0", Severity::Error, Some("use 'foo.exe'")),
+            (
+                "/checkver/re",
+                Severity::Warning,
+                Some("rename it to 'regex'"),
+            ),
+            (
+                "/autoupdate/foo",
+                Severity::Warning,
+                Some("remove the field"),
+            ),
+            (
+                "/autoupdate/hash/jp",
+                Severity::Warning,
+                Some("rename it to 'jsonpath'"),
+            ),
+        ];
+        assert_eq!(ret.len(), expected.len(), "{:#?}", ret);
+        for ((pointer, severity, fix), expected) in ret.iter().zip(expected) {
+            assert_eq!(pointer, expected.0);
+            assert_eq!(*severity, expected.1);
+            if let Some(expected_fix) = expected.2 {
+                assert_eq!(fix.as_deref(), Some(expected_fix));
+            }
+        }
+
+        let ret = lint_json(
+            r#"{
+                "version": "1.0",
+                "homepage": "https://example.com",
+                "license": {"identifier": "Custom", "url": "https://example.com/license"},
+                "architecture": {
+                    "64bit": {"url": "https://example.com/x64.zip", "extract_dir": "x64"},
+                    "32bit": {"url": "https://example.com/x86.zip", "hash": "md5:xyz"},
+                    "amd64": {}
+                },
+                "bin": "x64/foo.exe"
+            }"#,
+        );
+        let pointers = ret.iter().map(|r| r.0.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            pointers,
+            [
+                "/architecture/64bit/hash",
+                "/bin",
+                "/architecture/32bit/hash",
+                "/architecture/amd64",
+            ]
+        );
+
+        assert_eq!(lint_json("{").len(), 1);
+        assert_eq!(lint_json("[]")[0].0, "");
+    }
+}
@@ -1,3 +1,4 @@
+use scoop_hash::Algorithm;
 use serde::de::{self, MapAccess, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::{HashMap, HashSet};
@@ -8,7 +9,7 @@ use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 use tracing::{debug, warn};
 
-use crate::constant::{REGEX_HASH, SPDX_LIST};
+use crate::constant::{CHECKVER_GITHUB_REGEX, REGEX_HASH, SPDX_LIST};
 use crate::error::Fallible;
 use crate::internal;
 
@@ -336,10 +337,13 @@ pub enum HashString {
     Md5(String),
     Sha1(String),
     Sha256(String),
+    Sha384(String),
     Sha512(String),
+    Sha3_256(String),
+    Blake3(String),
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct HashExtraction {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub find: Option<String>,
@@ -621,7 +625,7 @@ impl<'de> Deserialize<'de> for Checkver {
                 E: de::Error,
             {
                 let regex = match s {
-                    "github" => Some("/releases/tag/(?:v|V)?([\\d.]+)".to_owned()),
+                    "github" => Some(CHECKVER_GITHUB_REGEX.to_owned()),
                     _ => Some(s.to_owned()),
                 };
 
@@ -656,8 +660,9 @@ impl<'de> Deserialize<'de> for Checkver {
                     match key.as_str() {
                         "github" => {
                             let prefix = map.next_value::<String>()?;
+                            let prefix = prefix.trim_end_matches('/');
                             url = Some(format!("{}/releases/latest", prefix));
-                            regex = Some("/releases/tag/(?:v|V)?([\\d.]+)".to_owned());
+                            regex = Some(CHECKVER_GITHUB_REGEX.to_owned());
                         }
                         "re" | "regex" => regex = Some(map.next_value()?),
                         "url" => url = Some(map.next_value()?),
@@ -1094,32 +1099,28 @@ impl HashString {
 
         let (algo, hash) = raw.split_once(':').unwrap_or(("sha256", raw));
         let hash = hash.to_lowercase();
-        match algo {
-            "md5" => Ok(HashString::Md5(hash)),
-            "sha1" => Ok(HashString::Sha1(hash)),
-            "sha256" => Ok(HashString::Sha256(hash)),
-            "sha512" => Ok(HashString::Sha512(hash)),
-            _ => Err(crate::Error::Custom(format!(
-                "unsupported hash algorithm: {}",
-                algo
-            ))),
+        match algo.parse::<Algorithm>()? {
+            Algorithm::Md5 => Ok(HashString::Md5(hash)),
+            Algorithm::Sha1 => Ok(HashString::Sha1(hash)),
+            Algorithm::Sha256 => Ok(HashString::Sha256(hash)),
+            Algorithm::Sha384 => Ok(HashString::Sha384(hash)),
+            Algorithm::Sha512 => Ok(HashString::Sha512(hash)),
+            Algorithm::Sha3_256 => Ok(HashString::Sha3_256(hash)),
+            Algorithm::Blake3 => Ok(HashString::Blake3(hash)),
+            algo => Err(scoop_hash::Error::UnsupportedAlgorithm(algo.to_string()).into()),
         }
     }
 
     /// Return the hash algorithm.
-    ///
-    /// # Returns
-    ///
-    /// - `md5`
-    /// - `sha1`
-    /// - `sha256`
-    /// - `sha512`
-    pub fn algorithm(&self) -> &str {
+    pub fn algorithm(&self) -> Algorithm {
         match self {
-            HashString::Md5(_) => "md5",
-            HashString::Sha1(_) => "sha1",
-            HashString::Sha256(_) => "sha256",
-            HashString::Sha512(_) => "sha512",
+            HashString::Md5(_) => Algorithm::Md5,
+            HashString::Sha1(_) => Algorithm::Sha1,
+            HashString::Sha256(_) => Algorithm::Sha256,
+            HashString::Sha384(_) => Algorithm::Sha384,
+            HashString::Sha512(_) => Algorithm::Sha512,
+            HashString::Sha3_256(_) => Algorithm::Sha3_256,
+            HashString::Blake3(_) => Algorithm::Blake3,
         }
     }
 
@@ -1129,7 +1130,10 @@ impl HashString {
             HashString::Md5(s) => s,
             HashString::Sha1(s) => s,
             HashString::Sha256(s) => s,
+            HashString::Sha384(s) => s,
             HashString::Sha512(s) => s,
+            HashString::Sha3_256(s) => s,
+            HashString::Blake3(s) => s,
         }
     }
 }
@@ -1140,7 +1144,10 @@ impl fmt::Display for HashString {
             HashString::Md5(s) => format!("md5:{}", s),
             HashString::Sha1(s) => format!("sha1:{}", s),
             HashString::Sha256(s) => format!("sha256:{}", s),
+            HashString::Sha384(s) => format!("sha384:{}", s),
             HashString::Sha512(s) => format!("sha512:{}", s),
+            HashString::Sha3_256(s) => format!("sha3-256:{}", s),
+            HashString::Blake3(s) => format!("blake3:{}", s),
         };
 
         write!(f, "{}", s)
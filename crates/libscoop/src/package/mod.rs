@@ -1,17 +1,31 @@
+pub(crate) mod autoupdate;
+pub(crate) mod checkver;
 pub(crate) mod download;
+pub(crate) mod lint;
 pub(crate) mod manifest;
 pub(crate) mod query;
 pub(crate) mod resolve;
 pub(crate) mod sync;
 
 use once_cell::unsync::OnceCell;
-use std::{fmt, path::PathBuf};
-
+use scoop_hash::ChecksumBuilder;
+use std::{
+    fmt,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+pub use checkver::CheckverMatch;
+pub use download::{
+    DownloadState, PackageDownloadFileContext, PackageDownloadOverallContext,
+    PackageDownloadProgressContext,
+};
+pub use lint::{Diagnostic, Severity};
 pub use manifest::{HashString, InstallInfo, License, Manifest};
 pub use query::QueryOption;
 pub use sync::SyncOption;
 
-use crate::{constant::ISOLATED_PACKAGE_BUCKET, internal};
+use crate::{constant::ISOLATED_PACKAGE_BUCKET, error::Fallible, internal};
 
 /// A Scoop package.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -434,6 +448,11 @@ pub(super) fn extract_name<S: AsRef<str>>(input: S) -> String {
         .to_owned()
 }
 
+/// Compute the checksum of the file at `path` using the algorithm of `hash`.
+pub(crate) fn file_checksum(path: &Path, hash: &HashString) -> Fallible<String> {
+    Ok(scoop_hash::hash_file(hash.algorithm(), path)?)
+}
+
 /// Hash mismatch context.
 #[derive(Clone, Debug)]
 pub struct HashMismatchContext {
@@ -1,5 +1,6 @@
 use rayon::prelude::{ParallelBridge, ParallelIterator};
 use regex::{Regex, RegexBuilder};
+use std::path::Path;
 use tracing::{debug, info};
 
 use crate::{
@@ -389,26 +390,7 @@ pub(crate) fn query_synced(
 
                             // The query has finished, the package has been found,
                             // the last step is to check if the package is installed.
-                            let mut path = apps_dir.join(name);
-                            path.push("current");
-                            path.push("install.json");
-
-                            if let Ok(install_info) = InstallInfo::parse(&path) {
-                                path.pop();
-                                path.push("manifest.json");
-                                if let Ok(install_manifest) = Manifest::parse(path) {
-                                    let state = InstallState::Installed(InstallStateInstalled {
-                                        version: install_manifest.version().to_owned(),
-                                        bucket: install_info.bucket().map(|s| s.to_owned()),
-                                        arch: install_info.arch().to_owned(),
-                                        held: install_info.is_held(),
-                                        url: install_info.url().map(|s| s.to_owned()),
-                                    });
-                                    package.fill_install_state(state);
-                                }
-                            } else {
-                                package.fill_install_state(InstallState::NotInstalled);
-                            }
+                            fill_install_state(&apps_dir, &package);
 
                             return Some(package);
                         }
@@ -425,3 +407,28 @@ pub(crate) fn query_synced(
 
     Ok(packages)
 }
+
+/// Fill the install state of a synced `package` by looking up its install
+/// info in `apps_dir`.
+pub(crate) fn fill_install_state(apps_dir: &Path, package: &Package) {
+    let mut path = apps_dir.join(package.name());
+    path.push("current");
+    path.push("install.json");
+
+    if let Ok(install_info) = InstallInfo::parse(&path) {
+        path.pop();
+        path.push("manifest.json");
+        if let Ok(install_manifest) = Manifest::parse(path) {
+            let state = InstallState::Installed(InstallStateInstalled {
+                version: install_manifest.version().to_owned(),
+                bucket: install_info.bucket().map(|s| s.to_owned()),
+                arch: install_info.arch().to_owned(),
+                held: install_info.is_held(),
+                url: install_info.url().map(|s| s.to_owned()),
+            });
+            package.fill_install_state(state);
+        }
+    } else {
+        package.fill_install_state(InstallState::NotInstalled);
+    }
+}
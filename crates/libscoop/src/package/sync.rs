@@ -1,6 +1,5 @@
 use once_cell::unsync::OnceCell;
-use scoop_hash::ChecksumBuilder;
-use std::io::Read;
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use tracing::{debug, info};
 
 use crate::{
@@ -10,7 +9,7 @@ use crate::{
 
 use super::{
     download::{self, DownloadSize},
-    query, resolve, Package,
+    query, resolve, HashMismatchContext, Package,
 };
 
 /// Options that may be used to tweak behavior of package sync operation.
@@ -79,6 +78,14 @@ pub enum SyncOption {
     /// there may be broken packages being committed to the system.
     IgnoreFailure,
 
+    /// Limit the overall download rate to the given bytes per second.
+    ///
+    /// # Note
+    ///
+    /// This option overrides the `download_max_rate` config for the operation.
+    /// The limit is shared by all connections, including segments of a file.
+    MaxDownloadRate(u64),
+
     /// Do not install dependencies.
     ///
     /// # Note
@@ -376,10 +383,21 @@ pub fn install(session: &Session, queries: &[&str], options: &[SyncOption]) -> F
         }
     };
 
+    install_packages(session, packages, options)
+}
+
+/// Sync operation: install and/or upgrade the given packages.
+pub(crate) fn install_packages(
+    session: &Session,
+    mut packages: Vec<Package>,
+    options: &[SyncOption],
+) -> Fallible<()> {
     if packages.is_empty() {
         return Ok(());
     }
 
+    let only_upgrade = options.contains(&SyncOption::OnlyUpgrade);
+    let escape_hold = options.contains(&SyncOption::EscapeHold);
     let transaction = Transaction::default();
 
     let no_dependencies = options.contains(&SyncOption::NoDependencies);
@@ -429,7 +447,15 @@ pub fn install(session: &Session, queries: &[&str], options: &[SyncOption]) -> F
         return Ok(());
     }
 
-    let mut set = download::PackageSet::new(session, &packages, reuse_cache)?;
+    let no_hash_check = options.contains(&SyncOption::NoHashCheck);
+    let mut set = download::PackageSet::new(session, &packages, reuse_cache, !no_hash_check)?;
+    let max_rate = options.iter().find_map(|opt| match opt {
+        SyncOption::MaxDownloadRate(rate) => Some(*rate),
+        _ => None,
+    });
+    if let Some(rate) = max_rate {
+        set.set_max_rate(rate);
+    }
 
     let assume_yes = options.contains(&SyncOption::AssumeYes);
     let offline = options.contains(&SyncOption::Offline);
@@ -480,7 +506,6 @@ pub fn install(session: &Session, queries: &[&str], options: &[SyncOption]) -> F
         }
     }
 
-    let no_hash_check = options.contains(&SyncOption::NoHashCheck);
     if !no_hash_check {
         if let Some(tx) = session.emitter() {
             let _ = tx.send(Event::PackageIntegrityCheckStart);
@@ -489,47 +514,82 @@ pub fn install(session: &Session, queries: &[&str], options: &[SyncOption]) -> F
         let config = session.config();
         let cache_root = config.cache_path();
 
-        let mut buf = [0; 1024 * 64];
-
-        for &pkg in packages.iter() {
-            if pkg.version() == "nightly" {
-                info!("skip hash check for nightly package '{}'", pkg.name());
-                continue;
-            }
-
-            let files = pkg.download_filenames();
-            let hashes = pkg.download_hashes();
-            let files_cnt = files.len();
+        // Collect files to check beforehand, packages are then checked in
+        // parallel while files of each package are checked in order.
+        let checks = packages
+            .iter()
+            .filter_map(|&pkg| {
+                if pkg.version() == "nightly" {
+                    info!("skip hash check for nightly package '{}'", pkg.name());
+                    return None;
+                }
 
-            for (idx, (filename, hash)) in files.into_iter().zip(hashes.into_iter()).enumerate() {
-                let path = cache_root.join(filename);
+                let files = pkg
+                    .download_filenames()
+                    .into_iter()
+                    .zip(pkg.download_urls())
+                    .zip(pkg.download_hashes())
+                    .map(|((filename, url), hash)| {
+                        // fresh downloads are verified while downloading
+                        let verified = set.is_verified(&filename);
+                        let path = cache_root.join(filename);
+                        (path, url.to_owned(), hash.clone(), verified)
+                    })
+                    .collect::<Vec<_>>();
+
+                Some((pkg.name().to_owned(), files))
+            })
+            .collect::<Vec<_>>();
 
-                let mut hasher = ChecksumBuilder::new().algo(hash.algorithm())?.build();
+        let tx = session.emitter();
+        let results = checks
+            .into_par_iter()
+            .map(|(name, files)| {
+                let mut mismatches = vec![];
+                let mut errors = vec![];
+                let files_cnt = files.len();
+
+                for (idx, (path, url, hash, verified)) in files.into_iter().enumerate() {
+                    if let Some(tx) = tx.as_ref() {
+                        let progress = format!("{} ({}/{})", name, idx + 1, files_cnt);
+                        let _ = tx.send(Event::PackageIntegrityCheckProgress(progress));
+                    }
 
-                if let Some(tx) = session.emitter() {
-                    let progress = format!("{} ({}/{})", pkg.name(), idx + 1, files_cnt);
-                    let _ = tx.send(Event::PackageIntegrityCheckProgress(progress));
-                }
+                    if verified {
+                        continue;
+                    }
 
-                let mut file = std::fs::File::open(path)?;
-                loop {
-                    let len = file.read(&mut buf)?;
-                    if len == 0 {
-                        break;
+                    // failing to read a file doesn't stop checking the others
+                    let actual = match super::file_checksum(&path, &hash) {
+                        Ok(actual) => actual,
+                        Err(err) => {
+                            errors.push((path, err));
+                            continue;
+                        }
+                    };
+                    let expected = hash.value();
+                    if actual != expected {
+                        let name = name.to_owned();
+                        let ctx = HashMismatchContext::new(name, url, expected.to_owned(), actual);
+                        mismatches.push(ctx);
                     }
-                    hasher.consume(&buf[..len]);
                 }
 
-                let actual = hasher.finalize();
-                let expected = hash.value();
-                if actual != expected {
-                    let name = pkg.name().to_owned();
-                    let url = pkg.download_urls()[idx].to_owned();
-                    let ctx =
-                        super::HashMismatchContext::new(name, url, expected.to_owned(), actual);
-                    return Err(Error::HashMismatch(ctx));
-                }
-            }
+                (mismatches, errors)
+            })
+            .collect::<Vec<_>>();
+
+        let (mut mismatches, mut errors) = (vec![], vec![]);
+        for (m, e) in results {
+            mismatches.extend(m);
+            errors.extend(e);
+        }
+
+        match (mismatches.len(), errors.len()) {
+            (0, 0) => {}
+            (1, 0) => return Err(Error::HashMismatch(mismatches.pop().unwrap())),
+            (0, 1) => return Err(errors.pop().unwrap().1),
+            _ => return Err(Error::HashMismatches { mismatches, errors }),
         }
 
         if let Some(tx) = session.emitter() {
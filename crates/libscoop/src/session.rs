@@ -1,13 +1,17 @@
 use flume::{Receiver, Sender};
 use once_cell::unsync::OnceCell;
 use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
 use tracing::{debug, info, trace};
 
 use crate::{
     config::{possible_config_paths, Config, ConfigBuilder},
     error::{Error, Fallible},
     event::{Event, EventBus},
+    http::{CurlClient, HttpClient},
+    package::download::RemoteSize,
 };
 
 /// A handle representing a Scoop session.
@@ -23,6 +27,12 @@ pub struct Session {
 
     /// User agent for the session
     pub(crate) user_agent: OnceCell<String>,
+
+    /// HTTP client for the session
+    http_client: OnceCell<Arc<dyn HttpClient>>,
+
+    /// Remote sizes of download urls probed during the session
+    pub(crate) remote_sizes: RefCell<HashMap<String, RemoteSize>>,
 }
 
 impl Default for Session {
@@ -57,6 +67,8 @@ impl Session {
             config,
             event_bus: OnceCell::new(),
             user_agent: OnceCell::new(),
+            http_client: OnceCell::new(),
+            remote_sizes: RefCell::new(HashMap::new()),
         }
     }
 
@@ -80,6 +92,8 @@ impl Session {
             config,
             event_bus: OnceCell::new(),
             user_agent: OnceCell::new(),
+            http_client: OnceCell::new(),
+            remote_sizes: RefCell::new(HashMap::new()),
         })
     }
 
@@ -92,7 +106,7 @@ impl Session {
     ///
     /// [1]: crate::operation::config_list
     /// [2]: crate::operation
-    pub fn config(&self) -> Ref<Config> {
+    pub fn config(&self) -> Ref<'_, Config> {
         self.config.borrow()
     }
 
@@ -101,7 +115,7 @@ impl Session {
     /// This method is only directly accessible from within the crate itself.
     /// It maybe indirectly used by other public available APIs to (indirectly)
     /// mutate the config. See [`Session::config`] for more details.
-    pub(crate) fn config_mut(&self) -> Fallible<RefMut<Config>> {
+    pub(crate) fn config_mut(&self) -> Fallible<RefMut<'_, Config>> {
         self.config.try_borrow_mut().map_err(|_| Error::ConfigInUse)
     }
 
@@ -144,4 +158,101 @@ impl Session {
             .set(user_agent.to_owned())
             .map_err(|_| Error::UserAgentAlreadySet)
     }
+
+    /// Set the HTTP client for the session.
+    ///
+    /// All HTTP requests of the session are performed by the HTTP client. The
+    /// HTTP client for a session can only be set once. If not set, the default
+    /// [`CurlClient`][1] will be used.
+    ///
+    /// # Errors
+    ///
+    /// This method will return an error if the HTTP client has already been
+    /// set.
+    ///
+    /// [1]: crate::http::CurlClient
+    pub fn set_http_client<C>(&self, client: C) -> Fallible<()>
+    where
+        C: HttpClient + 'static,
+    {
+        self.http_client
+            .set(Arc::new(client))
+            .map_err(|_| Error::HttpClientAlreadySet)
+    }
+
+    /// Get the HTTP client of the session.
+    pub(crate) fn http_client(&self) -> Arc<dyn HttpClient> {
+        let client = self.http_client.get_or_init(|| Arc::new(CurlClient));
+        Arc::clone(client)
+    }
+
+    /// Create a session sharing the config, the user agent and the HTTP client
+    /// of this session, to be sent to another thread.
+    ///
+    /// The event bus is not shared, events of the forked session are not
+    /// delivered.
+    pub(crate) fn fork(&self) -> Session {
+        let user_agent = OnceCell::new();
+        if let Some(ua) = self.user_agent.get() {
+            let _ = user_agent.set(ua.to_owned());
+        }
+        let http_client = OnceCell::new();
+        let _ = http_client.set(self.http_client());
+
+        Session {
+            config: RefCell::new(self.config().clone()),
+            event_bus: OnceCell::new(),
+            user_agent,
+            http_client,
+            remote_sizes: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Create a session of an empty Scoop root in the temp directory, with
+    /// `config` merged into its config.
+    pub fn temp_session(name: &str, config: serde_json::Value) -> Session {
+        let root = std::env::temp_dir().join(format!("libscoop-{}", name));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+
+        let mut json = serde_json::json!({
+            "root_path": root,
+            "cache_path": root.join("cache"),
+            "download_retry_wait": 0,
+        });
+        if let serde_json::Value::Object(config) = config {
+            json.as_object_mut().unwrap().extend(config);
+        }
+        let path = root.join("config.json");
+        std::fs::write(&path, json.to_string()).unwrap();
+
+        Session::new_with(path).unwrap()
+    }
+
+    /// Add the manifest of package `name` to `bucket` of the session.
+    ///
+    /// # Returns
+    ///
+    /// Path of the manifest.
+    pub fn add_manifest(
+        session: &Session,
+        bucket: &str,
+        name: &str,
+        manifest: &serde_json::Value,
+    ) -> PathBuf {
+        let dir = session.config().root_path().join("buckets");
+        let dir = dir.join(bucket).join("bucket");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join(format!("{}.json", name));
+        std::fs::write(&path, manifest.to_string()).unwrap();
+        path
+    }
 }
@@ -0,0 +1,78 @@
+use libscoop::{operation, Session, SyncOption};
+use std::path::{Path, PathBuf};
+
+/// Create a Scoop root in the temp directory, with a `main` bucket holding
+/// package `foo` whose file is served from a local `file://` source.
+///
+/// # Returns
+///
+/// Path of the root directory.
+fn setup(name: &str, content: &[u8]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("libscoop-it-{}", name));
+    let _ = std::fs::remove_dir_all(&root);
+
+    let source = root.join("source");
+    std::fs::create_dir_all(&source).unwrap();
+    std::fs::write(source.join("foo 1.0.zip"), content).unwrap();
+
+    let mut checksum = scoop_hash::ChecksumBuilder::new().sha256().build();
+    checksum.consume(content);
+    let source = source.to_string_lossy().replace('\\', "/");
+    let url = format!("file:///{}/foo%201.0.zip", source.trim_start_matches('/'));
+    let manifest = serde_json::json!({
+        "version": "1.0",
+        "homepage": "https://example.com",
+        "license": "MIT",
+        "url": url,
+        "hash": checksum.finalize(),
+    });
+    let bucket = root.join("buckets").join("main").join("bucket");
+    std::fs::create_dir_all(&bucket).unwrap();
+    std::fs::write(bucket.join("foo.json"), manifest.to_string()).unwrap();
+
+    let config = serde_json::json!({
+        "root_path": root,
+        "cache_path": root.join("cache"),
+    });
+    std::fs::write(root.join("config.json"), config.to_string()).unwrap();
+
+    root
+}
+
+/// Get names of the files in the cache directory of `root`.
+fn cached(root: &Path) -> Vec<String> {
+    std::fs::read_dir(root.join("cache"))
+        .map(|entries| {
+            entries
+                .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_sync_from_local_source() {
+    let root = setup("sync-local", b"foo");
+    let session = Session::new_with(root.join("config.json")).unwrap();
+    let options = vec![SyncOption::AssumeYes, SyncOption::DownloadOnly];
+    operation::package_sync(&session, vec!["foo"], options).unwrap();
+
+    let files = cached(&root);
+    assert_eq!(files.len(), 1);
+    assert!(files[0].starts_with("foo#1.0#"));
+    let data = std::fs::read(root.join("cache").join(&files[0])).unwrap();
+    assert_eq!(data, b"foo");
+}
+
+#[test]
+fn test_sync_from_corrupt_local_source() {
+    let root = setup("sync-local-corrupt", b"foo");
+    std::fs::write(root.join("source").join("foo 1.0.zip"), b"bar").unwrap();
+
+    let session = Session::new_with(root.join("config.json")).unwrap();
+    let options = vec![SyncOption::AssumeYes, SyncOption::DownloadOnly];
+    let ret = operation::package_sync(&session, vec!["foo"], options);
+    assert!(matches!(ret, Err(libscoop::Error::HashMismatch(_))));
+    // the corrupt file is not cached
+    assert!(cached(&root).is_empty());
+}
@@ -0,0 +1,73 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use scoop_hash::{Algorithm, ChecksumBuilder, MultiChecksum};
+
+const ALGOS: [Algorithm; 4] = [
+    Algorithm::Md5,
+    Algorithm::Sha1,
+    Algorithm::Sha256,
+    Algorithm::Sha512,
+];
+
+fn separate(size: usize) {
+    let data = &vec![0xffu8; size][..];
+    for algo in ALGOS {
+        let mut hasher = ChecksumBuilder::new().algo(algo).build();
+        hasher.consume(data);
+        hasher.finalize();
+    }
+}
+
+fn multi(size: usize) {
+    let data = &vec![0xffu8; size][..];
+    let mut hasher = MultiChecksum::new(&ALGOS);
+    hasher.consume(data);
+    hasher.finalize();
+}
+
+fn multi_threaded(size: usize) {
+    let data = &vec![0xffu8; size][..];
+    let mut hasher = MultiChecksum::threaded(&ALGOS);
+    for chunk in data.chunks(64 * 1024) {
+        hasher.consume(chunk);
+    }
+    hasher.finalize();
+}
+
+fn benchmark_multi_10000(c: &mut Criterion) {
+    let mut group = c.benchmark_group("multi_10000");
+    group.bench_function("separate", |b| b.iter(|| separate(black_box(10000))));
+    group.bench_function("multi", |b| b.iter(|| multi(black_box(10000))));
+    group.bench_function("multi_threaded", |b| {
+        b.iter(|| multi_threaded(black_box(10000)))
+    });
+    group.finish();
+}
+
+fn benchmark_multi_1000000(c: &mut Criterion) {
+    let mut group = c.benchmark_group("multi_1000000");
+    group.bench_function("separate", |b| b.iter(|| separate(black_box(1000000))));
+    group.bench_function("multi", |b| b.iter(|| multi(black_box(1000000))));
+    group.bench_function("multi_threaded", |b| {
+        b.iter(|| multi_threaded(black_box(1000000)))
+    });
+    group.finish();
+}
+
+fn benchmark_multi_10000000(c: &mut Criterion) {
+    let mut group = c.benchmark_group("multi_10000000");
+    group.sample_size(10);
+    group.bench_function("separate", |b| b.iter(|| separate(black_box(10000000))));
+    group.bench_function("multi", |b| b.iter(|| multi(black_box(10000000))));
+    group.bench_function("multi_threaded", |b| {
+        b.iter(|| multi_threaded(black_box(10000000)))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    benchmark_multi_10000,
+    benchmark_multi_1000000,
+    benchmark_multi_10000000
+);
+criterion_main!(benches);
@@ -1,19 +1,33 @@
 use std::error::Error as StdError;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+#[cfg(feature = "mmap")]
+mod mmap;
+mod multi;
+
+pub use multi::{hash_file_multi, MultiChecksum};
 
 #[cfg(feature = "rustcrypto")]
 mod rustcrypto;
 #[cfg(feature = "rustcrypto")]
-use rustcrypto::{Digest, Md5, Sha1, Sha256, Sha512};
+use rustcrypto::Digest;
 
-#[cfg(not(feature = "rustcrypto"))]
 mod selfcontained;
-#[cfg(not(feature = "rustcrypto"))]
-use selfcontained::{Md5, Sha1, Sha256, Sha512};
+mod state;
+
+pub use state::HashState;
 
-trait Hasher {
+trait Hasher: Send {
     fn hash_type(&self) -> String;
     fn update(&mut self, data: &[u8]);
     fn sum(self: Box<Self>) -> String;
+
+    /// Export the state of the hash computation, `None` if the implementation
+    /// doesn't support it.
+    fn export_state(&self) -> Option<HashState>;
 }
 
 impl core::fmt::Debug for dyn Hasher {
@@ -23,52 +37,224 @@ impl core::fmt::Debug for dyn Hasher {
 }
 
 macro_rules! impl_hasher_for {
-    ($hasher:ty) => {
+    ($hasher:ty, $algo:expr) => {
         impl Hasher for $hasher {
             fn hash_type(&self) -> String {
                 stringify!($hasher).to_string()
             }
 
             fn update(&mut self, data: &[u8]) {
-                #[cfg(not(feature = "rustcrypto"))]
                 self.consume(data);
+            }
+
+            fn sum(self: Box<Self>) -> String {
+                self.result_string()
+            }
 
-                #[cfg(feature = "rustcrypto")]
+            fn export_state(&self) -> Option<HashState> {
+                let (chain, length, buffer) = self.state();
+                Some(HashState {
+                    algo: $algo,
+                    chain,
+                    length,
+                    buffer: buffer.to_vec(),
+                })
+            }
+        }
+    };
+}
+
+impl_hasher_for!(selfcontained::Md5, Algorithm::Md5);
+impl_hasher_for!(selfcontained::Sha1, Algorithm::Sha1);
+impl_hasher_for!(selfcontained::Sha256, Algorithm::Sha256);
+impl_hasher_for!(selfcontained::Sha384, Algorithm::Sha384);
+impl_hasher_for!(selfcontained::Sha512, Algorithm::Sha512);
+impl_hasher_for!(selfcontained::Sha3_256, Algorithm::Sha3_256);
+impl_hasher_for!(selfcontained::Blake3, Algorithm::Blake3);
+
+#[cfg(feature = "rustcrypto")]
+macro_rules! impl_rustcrypto_hasher_for {
+    ($hasher:ty) => {
+        impl Hasher for $hasher {
+            fn hash_type(&self) -> String {
+                stringify!($hasher).to_string()
+            }
+
+            fn update(&mut self, data: &[u8]) {
                 self.inner.update(data);
             }
 
             fn sum(self: Box<Self>) -> String {
-                #[cfg(not(feature = "rustcrypto"))]
-                let ret = self.result_string();
-
-                #[cfg(feature = "rustcrypto")]
-                let ret = format!("{:x}", self.inner.finalize());
+                format!("{:x}", self.inner.finalize())
+            }
 
-                ret
+            fn export_state(&self) -> Option<HashState> {
+                // RustCrypto's hashers don't expose their internal state
+                None
             }
         }
     };
 }
 
-impl_hasher_for!(Md5);
-impl_hasher_for!(Sha1);
-impl_hasher_for!(Sha256);
-impl_hasher_for!(Sha512);
+#[cfg(feature = "rustcrypto")]
+impl_rustcrypto_hasher_for!(rustcrypto::Md5);
+#[cfg(feature = "rustcrypto")]
+impl_rustcrypto_hasher_for!(rustcrypto::Sha1);
+#[cfg(feature = "rustcrypto")]
+impl_rustcrypto_hasher_for!(rustcrypto::Sha256);
+#[cfg(feature = "rustcrypto")]
+impl_rustcrypto_hasher_for!(rustcrypto::Sha384);
+#[cfg(feature = "rustcrypto")]
+impl_rustcrypto_hasher_for!(rustcrypto::Sha512);
+#[cfg(feature = "rustcrypto")]
+impl_rustcrypto_hasher_for!(rustcrypto::Sha3_256);
 
-#[derive(Debug)]
-pub struct Error;
+#[cfg(feature = "rustcrypto")]
+impl Hasher for rustcrypto::Blake3 {
+    fn hash_type(&self) -> String {
+        "rustcrypto::Blake3".to_string()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    fn sum(self: Box<Self>) -> String {
+        self.inner.finalize().to_hex().to_string()
+    }
+
+    fn export_state(&self) -> Option<HashState> {
+        // The blake3 crate doesn't expose its internal state
+        None
+    }
+}
+
+/// Files at least this large are memory-mapped for hashing when the `mmap`
+/// feature is enabled.
+#[cfg(feature = "mmap")]
+const MMAP_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// Size of the buffer used to read data for hashing.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Errors of this crate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The requested hash algorithm is not supported.
+    UnsupportedAlgorithm(String),
+
+    /// The serialized hash state is malformed.
+    InvalidState,
+}
 
 impl StdError for Error {}
 
 impl core::fmt::Display for Error {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "unsupported hash algorithm")
+        match self {
+            Error::UnsupportedAlgorithm(algo) => write!(f, "unsupported hash algorithm '{}'", algo),
+            Error::InvalidState => write!(f, "invalid hash state"),
+        }
+    }
+}
+
+/// Supported hash algorithms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+    Sha3_256,
+    Blake3,
+}
+
+impl Algorithm {
+    /// Get the name of the algorithm, as used in the `algo:hash` notation.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "md5",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha384 => "sha384",
+            Algorithm::Sha512 => "sha512",
+            Algorithm::Sha3_256 => "sha3-256",
+            Algorithm::Blake3 => "blake3",
+        }
+    }
+
+    fn hasher(&self) -> Box<dyn Hasher> {
+        #[cfg(feature = "rustcrypto")]
+        use rustcrypto::{Blake3, Md5, Sha1, Sha256, Sha384, Sha3_256, Sha512};
+        #[cfg(not(feature = "rustcrypto"))]
+        use selfcontained::{Blake3, Md5, Sha1, Sha256, Sha384, Sha3_256, Sha512};
+
+        match self {
+            Algorithm::Md5 => Box::new(Md5::new()),
+            Algorithm::Sha1 => Box::new(Sha1::new()),
+            Algorithm::Sha256 => Box::new(Sha256::new()),
+            Algorithm::Sha384 => Box::new(Sha384::new()),
+            Algorithm::Sha512 => Box::new(Sha512::new()),
+            Algorithm::Sha3_256 => Box::new(Sha3_256::new()),
+            Algorithm::Blake3 => Box::new(Blake3::new()),
+        }
+    }
+
+    /// Create a hasher whose state can be exported.
+    fn resumable_hasher(&self) -> Box<dyn Hasher> {
+        use selfcontained::{Blake3, Md5, Sha1, Sha256, Sha384, Sha3_256, Sha512};
+
+        match self {
+            Algorithm::Md5 => Box::new(Md5::new()),
+            Algorithm::Sha1 => Box::new(Sha1::new()),
+            Algorithm::Sha256 => Box::new(Sha256::new()),
+            Algorithm::Sha384 => Box::new(Sha384::new()),
+            Algorithm::Sha512 => Box::new(Sha512::new()),
+            Algorithm::Sha3_256 => Box::new(Sha3_256::new()),
+            Algorithm::Blake3 => Box::new(Blake3::new()),
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = Error;
+
+    /// Parse an algorithm from its name, case-insensitively.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use scoop_hash::Algorithm;
+    /// assert_eq!("SHA256".parse(), Ok(Algorithm::Sha256));
+    /// assert!("crc32".parse::<Algorithm>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Algorithm, Error> {
+        match s.to_ascii_lowercase().as_str() {
+            "md5" => Ok(Algorithm::Md5),
+            "sha1" => Ok(Algorithm::Sha1),
+            "sha256" => Ok(Algorithm::Sha256),
+            "sha384" => Ok(Algorithm::Sha384),
+            "sha512" => Ok(Algorithm::Sha512),
+            "sha3-256" => Ok(Algorithm::Sha3_256),
+            "blake3" => Ok(Algorithm::Blake3),
+            _ => Err(Error::UnsupportedAlgorithm(s.to_owned())),
+        }
+    }
+}
+
+impl core::fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.name())
     }
 }
 
 /// ChecksumBuilder is used to create a Checksum instance.
 pub struct ChecksumBuilder {
-    hasher: Box<dyn Hasher>,
+    algo: Algorithm,
+    resumable: bool,
 }
 
 impl Default for ChecksumBuilder {
@@ -91,51 +277,69 @@ impl ChecksumBuilder {
     pub fn new() -> ChecksumBuilder {
         // Default to sha256
         ChecksumBuilder {
-            hasher: Box::new(Sha256::new()),
+            algo: Algorithm::Sha256,
+            resumable: false,
         }
     }
 
     /// Use the specified hash algorithm.
     ///
-    /// # Errors
+    /// # Examples
     ///
-    /// Returns an error if the specified algorithm is not supported.
-    pub fn algo(self, algo: &str) -> Result<ChecksumBuilder, Error> {
-        match algo {
-            "md5" => Ok(self.md5()),
-            "sha1" => Ok(self.sha1()),
-            "sha256" => Ok(self.sha256()),
-            "sha512" => Ok(self.sha512()),
-            _ => Err(Error),
-        }
+    /// ```rust
+    /// use scoop_hash::{Algorithm, ChecksumBuilder};
+    /// let mut sha1 = ChecksumBuilder::new().algo(Algorithm::Sha1).build();
+    /// sha1.consume(b"hello world");
+    /// assert!(sha1.check("2aae6c35c94fcfb415dbe95f408b9ce91ee846ed"));
+    /// ```
+    pub fn algo(mut self, algo: Algorithm) -> ChecksumBuilder {
+        self.algo = algo;
+        self
     }
 
     /// Use the md5 hash algorithm.
     pub fn md5(self) -> ChecksumBuilder {
-        let algo: Box<dyn Hasher> = Box::new(Md5::new());
-        self.set_algo(algo)
+        self.algo(Algorithm::Md5)
     }
 
     /// Use the sha1 hash algorithm.
     pub fn sha1(self) -> ChecksumBuilder {
-        let algo: Box<dyn Hasher> = Box::new(Sha1::new());
-        self.set_algo(algo)
+        self.algo(Algorithm::Sha1)
     }
 
     /// Use the sha256 hash algorithm.
     pub fn sha256(self) -> ChecksumBuilder {
-        let algo: Box<dyn Hasher> = Box::new(Sha256::new());
-        self.set_algo(algo)
+        self.algo(Algorithm::Sha256)
+    }
+
+    /// Use the sha384 hash algorithm.
+    pub fn sha384(self) -> ChecksumBuilder {
+        self.algo(Algorithm::Sha384)
     }
 
     /// Use the sha512 hash algorithm.
     pub fn sha512(self) -> ChecksumBuilder {
-        let algo: Box<dyn Hasher> = Box::new(Sha512::new());
-        self.set_algo(algo)
+        self.algo(Algorithm::Sha512)
+    }
+
+    /// Use the sha3-256 hash algorithm.
+    pub fn sha3_256(self) -> ChecksumBuilder {
+        self.algo(Algorithm::Sha3_256)
+    }
+
+    /// Use the blake3 hash algorithm.
+    pub fn blake3(self) -> ChecksumBuilder {
+        self.algo(Algorithm::Blake3)
     }
 
-    fn set_algo(mut self, algo: Box<dyn Hasher>) -> ChecksumBuilder {
-        self.hasher = algo;
+    /// Make the state of the hash computation exportable, see
+    /// [`Checksum::state`].
+    ///
+    /// Without the `rustcrypto` feature, all Checksum instances are resumable.
+    /// With the feature enabled, resumable ones use the self-contained
+    /// implementations, as RustCrypto's hashers don't expose their states.
+    pub fn resumable(mut self) -> ChecksumBuilder {
+        self.resumable = true;
         self
     }
 
@@ -143,9 +347,13 @@ impl ChecksumBuilder {
     ///
     /// If no hash algorithm is specified, sha256 will be used.
     pub fn build(self) -> Checksum {
-        Checksum {
-            hasher: self.hasher,
-        }
+        let hasher = if self.resumable {
+            self.algo.resumable_hasher()
+        } else {
+            self.algo.hasher()
+        };
+
+        Checksum { hasher }
     }
 }
 
@@ -189,4 +397,181 @@ impl Checksum {
     pub fn check(self, input: &str) -> bool {
         input == self.finalize()
     }
+
+    /// Exports the state of the hash computation, which can be used to resume
+    /// the computation later with [`Checksum::resume`].
+    ///
+    /// Returns `None` if the Checksum instance is not resumable, see
+    /// [`ChecksumBuilder::resumable`].
+    pub fn state(&self) -> Option<HashState> {
+        self.hasher.export_state()
+    }
+
+    /// Resumes a hash computation from the given state.
+    ///
+    /// The resumed Checksum instance is resumable as well.
+    pub fn resume(state: &HashState) -> Checksum {
+        let hasher = state
+            .hasher()
+            .expect("hash state should have been validated");
+        Checksum { hasher }
+    }
+}
+
+impl Write for Checksum {
+    /// Consumes the provided data, which never fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use scoop_hash::ChecksumBuilder;
+    /// let mut sha256 = ChecksumBuilder::new().build();
+    /// std::io::copy(&mut &b"hello world"[..], &mut sha256).unwrap();
+    /// assert!(sha256.check("b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"));
+    /// ```
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.consume(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hash all data read from `reader` with the given algorithm.
+///
+/// # Returns
+///
+/// The hash as a hex string.
+///
+/// # Errors
+///
+/// Returns the error of reading from `reader`.
+///
+/// # Examples
+///
+/// ```rust
+/// use scoop_hash::{hash_reader, Algorithm};
+/// let hash = hash_reader(Algorithm::Md5, &b"hello world"[..]).unwrap();
+/// assert_eq!(hash, "5eb63bbbe01eeed093cb22bb8f5acdc3");
+/// ```
+pub fn hash_reader<R: Read>(algo: Algorithm, mut reader: R) -> io::Result<String> {
+    let mut checksum = ChecksumBuilder::new().algo(algo).build();
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    loop {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        checksum.consume(&buf[..len]);
+    }
+
+    Ok(checksum.finalize())
+}
+
+/// Hash the file at `path` with the given algorithm.
+///
+/// With the `mmap` feature enabled, large files are memory-mapped instead of
+/// being read into a buffer.
+///
+/// # Returns
+///
+/// The hash as a hex string.
+///
+/// # Errors
+///
+/// Returns the error of opening or reading the file.
+pub fn hash_file<P: AsRef<Path>>(algo: Algorithm, path: P) -> io::Result<String> {
+    let file = File::open(path.as_ref())?;
+
+    #[cfg(feature = "mmap")]
+    if file.metadata()?.len() >= MMAP_THRESHOLD {
+        // fall back to reading if the file can't be mapped
+        if let Ok(map) = mmap::Mmap::map(&file) {
+            let mut checksum = ChecksumBuilder::new().algo(algo).build();
+            checksum.consume(&map);
+            return Ok(checksum.finalize());
+        }
+    }
+
+    hash_reader(algo, file)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Write `data` to a temporary file of the given name.
+    fn temp_file(name: &str, data: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("scoop_hash-{}", name));
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_algorithm() {
+        for algo in [
+            Algorithm::Md5,
+            Algorithm::Sha1,
+            Algorithm::Sha256,
+            Algorithm::Sha384,
+            Algorithm::Sha512,
+            Algorithm::Sha3_256,
+            Algorithm::Blake3,
+        ] {
+            assert_eq!(algo.name().parse(), Ok(algo));
+            assert_eq!(algo.to_string().to_uppercase().parse(), Ok(algo));
+        }
+
+        for name in ["", "crc32", "sha3_256", "sha-256", " sha256"] {
+            let err = name.parse::<Algorithm>().unwrap_err();
+            assert_eq!(err, Error::UnsupportedAlgorithm(name.to_owned()));
+        }
+        assert_eq!(
+            Error::UnsupportedAlgorithm("crc32".to_owned()).to_string(),
+            "unsupported hash algorithm 'crc32'"
+        );
+    }
+
+    #[test]
+    fn test_hash_file() {
+        let expected = |algo, data: &[u8]| hash_reader(algo, data).unwrap();
+
+        // read into a buffer
+        let data = b"hello world";
+        let path = temp_file("small", data);
+        for algo in [Algorithm::Md5, Algorithm::Sha256, Algorithm::Blake3] {
+            assert_eq!(hash_file(algo, &path).unwrap(), expected(algo, data));
+        }
+        assert_eq!(
+            hash_file(Algorithm::Md5, &path).unwrap(),
+            "5eb63bbbe01eeed093cb22bb8f5acdc3"
+        );
+
+        // empty files can't be mapped
+        let path = temp_file("empty", b"");
+        assert_eq!(
+            hash_file(Algorithm::Sha1, &path).unwrap(),
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709"
+        );
+
+        // memory-mapped with the `mmap` feature
+        let data = (0..16 * 1024 * 1024 + 7)
+            .map(|i: u32| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let path = temp_file("large", &data);
+        for algo in [Algorithm::Sha1, Algorithm::Sha512] {
+            assert_eq!(hash_file(algo, &path).unwrap(), expected(algo, &data));
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        let err = hash_file(Algorithm::Md5, &path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
 }
@@ -0,0 +1,184 @@
+//! Minimal read-only memory mapping of files.
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+
+/// A read-only memory map of a whole file.
+///
+/// The file must not be modified while it is mapped, or the mapped data may
+/// change underneath.
+pub struct Mmap {
+    ptr: *const u8,
+    len: usize,
+    #[cfg(windows)]
+    mapping: windows_sys::Win32::Foundation::HANDLE,
+}
+
+// SAFETY: the mapping is read-only and owned by the struct.
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    /// Map the given file into memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file is empty or fails to be mapped.
+    #[cfg(unix)]
+    pub fn map(file: &File) -> io::Result<Mmap> {
+        use std::os::unix::io::AsRawFd;
+
+        let len = mapped_len(file)?;
+
+        // SAFETY: the file descriptor is valid during the call, and the
+        // mapping stays valid after the descriptor is closed.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Mmap {
+            ptr: ptr as *const u8,
+            len,
+        })
+    }
+
+    /// Map the given file into memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file is empty or fails to be mapped.
+    #[cfg(windows)]
+    pub fn map(file: &File) -> io::Result<Mmap> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::Memory::{
+            CreateFileMappingW, MapViewOfFile, FILE_MAP_READ, PAGE_READONLY,
+        };
+
+        let len = mapped_len(file)?;
+
+        // SAFETY: the file handle is valid during the calls, the mapping
+        // handle is closed on failure or when the map is dropped.
+        unsafe {
+            let mapping = CreateFileMappingW(
+                file.as_raw_handle() as _,
+                std::ptr::null(),
+                PAGE_READONLY,
+                0,
+                0,
+                std::ptr::null(),
+            );
+            if mapping.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+
+            let view = MapViewOfFile(mapping, FILE_MAP_READ, 0, 0, len);
+            if view.Value.is_null() {
+                let err = io::Error::last_os_error();
+                CloseHandle(mapping);
+                return Err(err);
+            }
+
+            Ok(Mmap {
+                ptr: view.Value as *const u8,
+                len,
+                mapping,
+            })
+        }
+    }
+
+    /// Memory mapping is not supported on this platform.
+    #[cfg(not(any(unix, windows)))]
+    pub fn map(_: &File) -> io::Result<Mmap> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "memory mapping is not supported",
+        ))
+    }
+}
+
+/// Get the length of `file` to be mapped.
+#[cfg(any(unix, windows))]
+fn mapped_len(file: &File) -> io::Result<usize> {
+    let len = file.metadata()?.len();
+    if len == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "empty file can't be mapped",
+        ));
+    }
+
+    usize::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file too large"))
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: the mapping of `len` bytes stays valid until dropped.
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    #[cfg(unix)]
+    fn drop(&mut self) {
+        // SAFETY: the pointer and the length are of a successful mapping.
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.len);
+        }
+    }
+
+    #[cfg(windows)]
+    fn drop(&mut self) {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::Memory::{UnmapViewOfFile, MEMORY_MAPPED_VIEW_ADDRESS};
+
+        // SAFETY: the view and the handle are of a successful mapping.
+        unsafe {
+            UnmapViewOfFile(MEMORY_MAPPED_VIEW_ADDRESS {
+                Value: self.ptr as *mut _,
+            });
+            CloseHandle(self.mapping);
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn drop(&mut self) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_map() {
+        let dir = std::env::temp_dir();
+        let data = (0..10000u32).map(|i| i as u8).collect::<Vec<_>>();
+
+        let path = dir.join("scoop_hash-mmap");
+        std::fs::write(&path, &data).unwrap();
+        let map = Mmap::map(&File::open(&path).unwrap()).unwrap();
+        assert_eq!(&map[..], &data[..]);
+        // the mapping outlives the file handle
+        let map = std::thread::spawn(move || map.to_vec()).join().unwrap();
+        assert_eq!(map, data);
+
+        // hashing falls back to reading when the file fails to be mapped
+        let path = dir.join("scoop_hash-mmap-empty");
+        std::fs::write(&path, b"").unwrap();
+        let err = Mmap::map(&File::open(&path).unwrap()).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::{Algorithm, Hasher, BUFFER_SIZE};
+
+/// Files at least this large are hashed with a thread per hasher.
+const THREAD_THRESHOLD: u64 = 4 * 1024 * 1024;
+
+/// Number of chunks that may be queued for a hasher thread.
+const QUEUE_SIZE: usize = 8;
+
+/// A hasher thread, with the sender feeding it data.
+type HasherThread = (Algorithm, SyncSender<Arc<[u8]>>, JoinHandle<String>);
+
+/// Hashers of a [`MultiChecksum`].
+enum Hashers {
+    /// Hashers fed on the calling thread one after another.
+    Sequential(Vec<(Algorithm, Box<dyn Hasher>)>),
+
+    /// Hashers running on their own threads, fed through channels.
+    Threaded(Vec<HasherThread>),
+}
+
+/// MultiChecksum computes hashes of several algorithms in a single pass.
+///
+/// # Examples
+///
+/// ```rust
+/// use scoop_hash::{Algorithm, MultiChecksum};
+/// let mut checksum = MultiChecksum::new(&[Algorithm::Md5, Algorithm::Sha1]);
+/// checksum.consume(b"hello world");
+/// let result = checksum.finalize();
+/// assert_eq!(result[&Algorithm::Md5], "5eb63bbbe01eeed093cb22bb8f5acdc3");
+/// assert_eq!(result[&Algorithm::Sha1], "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed");
+/// ```
+pub struct MultiChecksum {
+    hashers: Hashers,
+}
+
+impl MultiChecksum {
+    /// Creates a new MultiChecksum of the given algorithms, duplicates are
+    /// ignored.
+    pub fn new(algos: &[Algorithm]) -> MultiChecksum {
+        let hashers = dedup(algos)
+            .into_iter()
+            .map(|algo| (algo, algo.hasher()))
+            .collect();
+
+        MultiChecksum {
+            hashers: Hashers::Sequential(hashers),
+        }
+    }
+
+    /// Creates a new MultiChecksum of the given algorithms, running each
+    /// hasher on its own thread.
+    ///
+    /// This pays off for large inputs, where the data is hashed by all
+    /// algorithms in parallel. Consumed data is copied once to be shared by
+    /// the threads.
+    pub fn threaded(algos: &[Algorithm]) -> MultiChecksum {
+        let hashers = dedup(algos)
+            .into_iter()
+            .map(|algo| {
+                let (tx, rx) = sync_channel::<Arc<[u8]>>(QUEUE_SIZE);
+                let mut hasher = algo.hasher();
+                let handle = std::thread::spawn(move || {
+                    while let Ok(data) = rx.recv() {
+                        hasher.update(&data);
+                    }
+                    hasher.sum()
+                });
+                (algo, tx, handle)
+            })
+            .collect();
+
+        MultiChecksum {
+            hashers: Hashers::Threaded(hashers),
+        }
+    }
+
+    /// Consumes the provided data.
+    pub fn consume(&mut self, data: &[u8]) {
+        match &mut self.hashers {
+            Hashers::Sequential(hashers) => {
+                for (_, hasher) in hashers.iter_mut() {
+                    hasher.update(data);
+                }
+            }
+            Hashers::Threaded(hashers) => {
+                if data.is_empty() {
+                    return;
+                }
+
+                let data: Arc<[u8]> = Arc::from(data);
+                for (_, tx, _) in hashers.iter() {
+                    // a hasher thread never exits before the sender is dropped
+                    let _ = tx.send(Arc::clone(&data));
+                }
+            }
+        }
+    }
+
+    /// Gets the results of the hash computations as hex strings.
+    ///
+    /// Note that the MultiChecksum instance is consumed after getting the
+    /// results.
+    pub fn finalize(self) -> HashMap<Algorithm, String> {
+        match self.hashers {
+            Hashers::Sequential(hashers) => hashers
+                .into_iter()
+                .map(|(algo, hasher)| (algo, hasher.sum()))
+                .collect(),
+            Hashers::Threaded(hashers) => hashers
+                .into_iter()
+                .map(|(algo, tx, handle)| {
+                    drop(tx);
+                    let sum = handle.join().expect("hasher thread panicked");
+                    (algo, sum)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl core::fmt::Debug for MultiChecksum {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let algos = match &self.hashers {
+            Hashers::Sequential(hashers) => hashers.iter().map(|h| h.0).collect::<Vec<_>>(),
+            Hashers::Threaded(hashers) => hashers.iter().map(|h| h.0).collect::<Vec<_>>(),
+        };
+        f.debug_struct("MultiChecksum")
+            .field("algos", &algos)
+            .finish()
+    }
+}
+
+impl Write for MultiChecksum {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.consume(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hash the file at `path` with all the given algorithms in a single pass.
+///
+/// Large files are hashed with a thread per algorithm. With the `mmap`
+/// feature enabled, they are memory-mapped and shared by the threads without
+/// copying.
+///
+/// # Returns
+///
+/// A map of algorithm to the hash as a hex string.
+///
+/// # Errors
+///
+/// Returns the error of opening or reading the file.
+pub fn hash_file_multi<P: AsRef<Path>>(
+    algos: &[Algorithm],
+    path: P,
+) -> io::Result<HashMap<Algorithm, String>> {
+    let mut file = File::open(path.as_ref())?;
+    let len = file.metadata()?.len();
+
+    if len < THREAD_THRESHOLD || algos.len() < 2 {
+        let mut checksum = MultiChecksum::new(algos);
+        io::copy(&mut file, &mut checksum)?;
+        return Ok(checksum.finalize());
+    }
+
+    #[cfg(feature = "mmap")]
+    if let Ok(map) = crate::mmap::Mmap::map(&file) {
+        let map = Arc::new(map);
+        let handles = dedup(algos)
+            .into_iter()
+            .map(|algo| {
+                let map = Arc::clone(&map);
+                let handle = std::thread::spawn(move || {
+                    let mut hasher = algo.hasher();
+                    hasher.update(&map);
+                    hasher.sum()
+                });
+                (algo, handle)
+            })
+            .collect::<Vec<_>>();
+
+        return Ok(handles
+            .into_iter()
+            .map(|(algo, h)| (algo, h.join().expect("hasher thread panicked")))
+            .collect());
+    }
+
+    let mut checksum = MultiChecksum::threaded(algos);
+    let mut buf = vec![0; BUFFER_SIZE];
+    loop {
+        let len = match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        checksum.consume(&buf[..len]);
+    }
+
+    Ok(checksum.finalize())
+}
+
+/// Remove duplicates of `algos`, keeping the order.
+fn dedup(algos: &[Algorithm]) -> Vec<Algorithm> {
+    let mut ret = Vec::with_capacity(algos.len());
+    for &algo in algos {
+        if !ret.contains(&algo) {
+            ret.push(algo);
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_multi_checksum() {
+        let algos = [
+            Algorithm::Md5,
+            Algorithm::Sha1,
+            Algorithm::Sha256,
+            Algorithm::Sha512,
+            Algorithm::Md5,
+        ];
+        let data = vec![0xffu8; 1000000];
+
+        let mut sequential = MultiChecksum::new(&algos);
+        let mut threaded = MultiChecksum::threaded(&algos);
+        for chunk in data.chunks(4096) {
+            sequential.consume(chunk);
+            threaded.consume(chunk);
+        }
+
+        let sequential = sequential.finalize();
+        assert_eq!(sequential.len(), 4);
+        assert_eq!(sequential, threaded.finalize());
+
+        for algo in &algos[..4] {
+            let mut checksum = crate::ChecksumBuilder::new().algo(*algo).build();
+            checksum.consume(&data);
+            assert_eq!(sequential[algo], checksum.finalize());
+        }
+    }
+}
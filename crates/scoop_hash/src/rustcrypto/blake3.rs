@@ -0,0 +1,11 @@
+pub struct WrappedBlake3 {
+    pub inner: blake3::Hasher,
+}
+
+impl WrappedBlake3 {
+    pub fn new() -> Self {
+        Self {
+            inner: blake3::Hasher::new(),
+        }
+    }
+}
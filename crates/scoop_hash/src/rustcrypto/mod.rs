@@ -1,10 +1,16 @@
+mod blake3;
 mod md5;
 mod sha1;
 mod sha256;
+mod sha3;
+mod sha384;
 mod sha512;
 
+pub use self::blake3::WrappedBlake3 as Blake3;
 pub use self::md5::WrappedMd5 as Md5;
 pub use self::sha1::WrappedSha1 as Sha1;
 pub use self::sha256::WrappedSha256 as Sha256;
+pub use self::sha3::WrappedSha3_256 as Sha3_256;
+pub use self::sha384::WrappedSha384 as Sha384;
 pub use self::sha512::WrappedSha512 as Sha512;
 pub use ::md5::Digest;
@@ -0,0 +1,14 @@
+use sha3::Digest;
+use sha3::Sha3_256;
+
+pub struct WrappedSha3_256 {
+    pub inner: Sha3_256,
+}
+
+impl WrappedSha3_256 {
+    pub fn new() -> Self {
+        Self {
+            inner: Sha3_256::new(),
+        }
+    }
+}
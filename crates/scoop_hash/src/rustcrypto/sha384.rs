@@ -0,0 +1,14 @@
+use sha2::Digest;
+use sha2::Sha384;
+
+pub struct WrappedSha384 {
+    pub inner: Sha384,
+}
+
+impl WrappedSha384 {
+    pub fn new() -> Self {
+        Self {
+            inner: Sha384::new(),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! aarch64 accelerated compression functions of SHA-1 and SHA-256, using the
+//! SHA1 and SHA2 cryptographic extensions.
+//!
+//! references:
+//!   [Arm A64 Instruction Set](https://developer.arm.com/documentation/ddi0602/latest/SIMD-FP-Instructions)
+
+use core::arch::aarch64::*;
+
+use super::{sha1, sha256};
+
+/// Check if the SHA1 and SHA2 cryptographic extensions are supported.
+#[inline]
+pub fn has_sha2() -> bool {
+    std::arch::is_aarch64_feature_detected!("sha2")
+}
+
+/// Load the `i`-th 16 bytes of `block` as four big-endian 32-bit words.
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn load_be(block: &[u8], i: usize) -> uint32x4_t {
+    vreinterpretq_u32_u8(vrev32q_u8(vld1q_u8(block[i * 16..].as_ptr())))
+}
+
+/// Compress SHA-1 blocks into the state.
+///
+/// # Safety
+///
+/// The caller must ensure [`has_sha2`] is true.
+#[target_feature(enable = "sha2")]
+pub unsafe fn sha1_compress(state: &mut [u32; 5], blocks: &[u8]) {
+    let mut abcd = vld1q_u32(state.as_ptr());
+    let mut e = state[4];
+
+    for block in blocks.chunks_exact(64) {
+        let abcd_saved = abcd;
+        let e_saved = e;
+
+        let mut w = [
+            load_be(block, 0),
+            load_be(block, 1),
+            load_be(block, 2),
+            load_be(block, 3),
+        ];
+
+        // Four rounds at a time
+        for i in 0..20 {
+            let words = if i < 4 {
+                w[i]
+            } else {
+                let next = vsha1su1q_u32(vsha1su0q_u32(w[0], w[1], w[2]), w[3]);
+                w = [w[1], w[2], w[3], next];
+                next
+            };
+
+            let wk = vaddq_u32(words, vdupq_n_u32(sha1::K[i / 5]));
+            let e_next = vsha1h_u32(vgetq_lane_u32(abcd, 0));
+            abcd = match i / 5 {
+                0 => vsha1cq_u32(abcd, e, wk),
+                2 => vsha1mq_u32(abcd, e, wk),
+                _ => vsha1pq_u32(abcd, e, wk),
+            };
+            e = e_next;
+        }
+
+        abcd = vaddq_u32(abcd, abcd_saved);
+        e = e.wrapping_add(e_saved);
+    }
+
+    vst1q_u32(state.as_mut_ptr(), abcd);
+    state[4] = e;
+}
+
+/// Compress SHA-256 blocks into the state.
+///
+/// # Safety
+///
+/// The caller must ensure [`has_sha2`] is true.
+#[target_feature(enable = "sha2")]
+pub unsafe fn sha256_compress(state: &mut [u32; 8], blocks: &[u8]) {
+    let mut abcd = vld1q_u32(state[0..].as_ptr());
+    let mut efgh = vld1q_u32(state[4..].as_ptr());
+
+    for block in blocks.chunks_exact(64) {
+        let abcd_saved = abcd;
+        let efgh_saved = efgh;
+
+        let mut w = [
+            load_be(block, 0),
+            load_be(block, 1),
+            load_be(block, 2),
+            load_be(block, 3),
+        ];
+
+        // Four rounds at a time
+        for i in 0..16 {
+            let words = if i < 4 {
+                w[i]
+            } else {
+                let next = vsha256su1q_u32(vsha256su0q_u32(w[0], w[1]), w[2], w[3]);
+                w = [w[1], w[2], w[3], next];
+                next
+            };
+
+            let wk = vaddq_u32(words, vld1q_u32(sha256::K[i * 4..].as_ptr()));
+            let abcd_prev = abcd;
+            abcd = vsha256hq_u32(abcd_prev, efgh, wk);
+            efgh = vsha256h2q_u32(efgh, abcd_prev, wk);
+        }
+
+        abcd = vaddq_u32(abcd, abcd_saved);
+        efgh = vaddq_u32(efgh, efgh_saved);
+    }
+
+    vst1q_u32(state[0..].as_mut_ptr(), abcd);
+    vst1q_u32(state[4..].as_mut_ptr(), efgh);
+}
@@ -0,0 +1,421 @@
+// references:
+//   [BLAKE3](https://github.com/BLAKE3-team/BLAKE3-specs/blob/master/blake3.pdf)
+//   [BLAKE3 reference implementation](https://github.com/BLAKE3-team/BLAKE3/blob/master/reference_impl/reference_impl.rs)
+
+use core::{cmp::min, convert::TryInto};
+
+const BLOCK_LEN: usize = 64;
+const CHUNK_LEN: usize = 1024;
+
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const PARENT: u32 = 1 << 2;
+const ROOT: u32 = 1 << 3;
+
+static IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+static MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+/// The mixing function, mixing a column or a diagonal of the state with two
+/// message words.
+#[inline(always)]
+fn g(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, mx: u32, my: u32) {
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(mx);
+    state[d] = (state[d] ^ state[a]).rotate_right(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(12);
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(my);
+    state[d] = (state[d] ^ state[a]).rotate_right(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(7);
+}
+
+#[inline(always)]
+fn round(state: &mut [u32; 16], m: &[u32; 16]) {
+    // Mix the columns
+    g(state, 0, 4, 8, 12, m[0], m[1]);
+    g(state, 1, 5, 9, 13, m[2], m[3]);
+    g(state, 2, 6, 10, 14, m[4], m[5]);
+    g(state, 3, 7, 11, 15, m[6], m[7]);
+    // Mix the diagonals
+    g(state, 0, 5, 10, 15, m[8], m[9]);
+    g(state, 1, 6, 11, 12, m[10], m[11]);
+    g(state, 2, 7, 8, 13, m[12], m[13]);
+    g(state, 3, 4, 9, 14, m[14], m[15]);
+}
+
+/// The compression function, compressing a block into the chaining value.
+fn compress(
+    cv: &[u32; 8],
+    block: &[u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+) -> [u32; 16] {
+    #[rustfmt::skip]
+    let mut state = [
+        cv[0], cv[1], cv[2], cv[3],
+        cv[4], cv[5], cv[6], cv[7],
+        IV[0], IV[1], IV[2], IV[3],
+        counter as u32, (counter >> 32) as u32, block_len, flags,
+    ];
+    let mut block = *block;
+
+    for i in 0..7 {
+        round(&mut state, &block);
+
+        if i < 6 {
+            let mut permuted = [0; 16];
+            for (p, &idx) in permuted.iter_mut().zip(MSG_PERMUTATION.iter()) {
+                *p = block[idx];
+            }
+            block = permuted;
+        }
+    }
+
+    for i in 0..8 {
+        state[i] ^= state[i + 8];
+        state[i + 8] ^= cv[i];
+    }
+    state
+}
+
+/// Read little-endian 32-bit words from bytes.
+fn words<const N: usize>(bytes: &[u8]) -> [u32; N] {
+    let mut words = [0; N];
+    for (w, s) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+        *w = u32::from_le_bytes(s.try_into().unwrap());
+    }
+    words
+}
+
+/// The input of a compression that is not done yet, as it's unknown whether
+/// it's the root node until the end of input.
+struct Output {
+    input_cv: [u32; 8],
+    block: [u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+impl Output {
+    fn chaining_value(&self) -> [u32; 8] {
+        let out = compress(
+            &self.input_cv,
+            &self.block,
+            self.counter,
+            self.block_len,
+            self.flags,
+        );
+        out[..8].try_into().unwrap()
+    }
+
+    fn root_hash(&self) -> [u8; 32] {
+        let out = compress(
+            &self.input_cv,
+            &self.block,
+            0,
+            self.block_len,
+            self.flags | ROOT,
+        );
+        let mut ret = [0; 32];
+        for (o, w) in ret.chunks_exact_mut(4).zip(out.iter()) {
+            o.copy_from_slice(&w.to_le_bytes());
+        }
+        ret
+    }
+}
+
+/// The output of a parent node, of which the block is the concatenation of
+/// the chaining values of its children.
+fn parent_output(left: &[u32; 8], right: &[u32; 8]) -> Output {
+    let mut block = [0; 16];
+    block[..8].copy_from_slice(left);
+    block[8..].copy_from_slice(right);
+    Output {
+        input_cv: IV,
+        block,
+        counter: 0,
+        block_len: BLOCK_LEN as u32,
+        flags: PARENT,
+    }
+}
+
+#[derive(Debug)]
+pub struct Blake3 {
+    // Chaining value of the current chunk
+    chunk_cv: [u32; 8],
+    // Chaining values of the completed subtrees, the `cv_stack_len` can only
+    // be from 0 to 54
+    cv_stack: [[u32; 8]; 54],
+    cv_stack_len: usize,
+    // Hold total length of input data
+    total_length: u64,
+    // Store the last block of the current chunk, which is not compressed until
+    // more data comes, since the last block of a chunk is flagged differently
+    buffer: [u8; BLOCK_LEN],
+    // Hold the length of the last block, the `buflen` can only be from 0 to 64
+    buflen: usize,
+}
+
+impl Default for Blake3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Blake3 {
+    /// Create a new [`Blake3`] instance to consume data and get digest.
+    #[inline]
+    pub fn new() -> Self {
+        Blake3 {
+            chunk_cv: IV,
+            cv_stack: [[0; 8]; 54],
+            cv_stack_len: 0,
+            total_length: 0,
+            buffer: [0; BLOCK_LEN],
+            buflen: 0,
+        }
+    }
+
+    /// Reset this [`Blake3`] instance's status.
+    #[inline]
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Export the internal state of this [`Blake3`] instance: the chaining
+    /// values of the current chunk and of the completed subtrees in
+    /// little-endian, the total length of consumed data, and the consumed data
+    /// that isn't compressed yet.
+    pub fn state(&self) -> (Vec<u8>, u64, &[u8]) {
+        let chain = core::iter::once(&self.chunk_cv)
+            .chain(self.cv_stack[..self.cv_stack_len].iter())
+            .flatten()
+            .flat_map(|i| i.to_le_bytes())
+            .collect();
+        (chain, self.total_length, &self.buffer[..self.buflen])
+    }
+
+    /// Create a new [`Blake3`] instance from the state exported by
+    /// [`Blake3::state`], returning `None` if the state is malformed.
+    pub fn from_state(chain: &[u8], total_length: u64, buffer: &[u8]) -> Option<Self> {
+        let mut hasher = Self::new();
+        hasher.total_length = total_length;
+
+        // A completed subtree is merged into its sibling as soon as the
+        // sibling completes, so each bit set of the number of completed chunks
+        // stands for a subtree on the stack.
+        let cv_stack_len = hasher.chunk_counter().count_ones() as usize;
+        if chain.len() != 32 * (1 + cv_stack_len) || buffer.len() != hasher.buffered_len() {
+            return None;
+        }
+
+        let mut cvs = chain.chunks_exact(32).map(words::<8>);
+        hasher.chunk_cv = cvs.next().unwrap();
+        for (cv, s) in hasher.cv_stack.iter_mut().zip(cvs) {
+            *cv = s;
+        }
+        hasher.cv_stack_len = cv_stack_len;
+        hasher.buffer[..buffer.len()].copy_from_slice(buffer);
+        hasher.buflen = buffer.len();
+        Some(hasher)
+    }
+
+    /// Consume the last buffer data, finalize the calculation and return
+    /// the digest as a `[u8; 32]` array format.
+    #[inline]
+    pub fn result(self) -> [u8; 32] {
+        let mut output = self.chunk_output();
+        for cv in self.cv_stack[..self.cv_stack_len].iter().rev() {
+            output = parent_output(cv, &output.chaining_value());
+        }
+        output.root_hash()
+    }
+
+    /// Consume the last buffer data, finalize the calculation and return
+    /// the digest as a [`String`] format.
+    #[inline]
+    pub fn result_string(self) -> String {
+        self.result()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    /// Consume the input data, but not finalize the calculation. This
+    /// method returns `&self` to make itself chainable, so that callers
+    /// can continuously consume data by chaining function calls.
+    pub fn consume<D: AsRef<[u8]>>(&mut self, data: D) -> &mut Self {
+        let mut data = data.as_ref();
+
+        while !data.is_empty() {
+            // The buffered block is only compressed when more data comes
+            if self.buflen == BLOCK_LEN {
+                if self.chunk_len() == CHUNK_LEN {
+                    // Finish the current chunk and start a new one
+                    let chunk_cv = self.chunk_output().chaining_value();
+                    self.push_chunk(chunk_cv);
+                    self.chunk_cv = IV;
+                } else {
+                    let out = compress(
+                        &self.chunk_cv,
+                        &words(&self.buffer),
+                        self.chunk_counter(),
+                        BLOCK_LEN as u32,
+                        self.start_flag(),
+                    );
+                    self.chunk_cv = out[..8].try_into().unwrap();
+                }
+                self.buflen = 0;
+            }
+
+            let copied_idx = min(BLOCK_LEN - self.buflen, data.len());
+            self.buffer[self.buflen..self.buflen + copied_idx].copy_from_slice(&data[..copied_idx]);
+            self.buflen += copied_idx;
+            self.total_length += copied_idx as u64;
+            data = &data[copied_idx..];
+        }
+
+        self
+    }
+
+    /// Get the index of the current chunk.
+    fn chunk_counter(&self) -> u64 {
+        self.total_length.saturating_sub(1) / CHUNK_LEN as u64
+    }
+
+    /// Get the length of data consumed into the current chunk.
+    fn chunk_len(&self) -> usize {
+        (self.total_length - self.chunk_counter() * CHUNK_LEN as u64) as usize
+    }
+
+    /// Get the length of the buffered block of the current chunk.
+    fn buffered_len(&self) -> usize {
+        match self.chunk_len() {
+            0 => 0,
+            len => len - (len - 1) / BLOCK_LEN * BLOCK_LEN,
+        }
+    }
+
+    /// Get the flag of the next block to be compressed in the current chunk.
+    fn start_flag(&self) -> u32 {
+        if self.chunk_len() <= BLOCK_LEN {
+            CHUNK_START
+        } else {
+            0
+        }
+    }
+
+    /// Get the output of the current chunk, with the buffered block as its
+    /// last block.
+    fn chunk_output(&self) -> Output {
+        let mut block = [0; BLOCK_LEN];
+        block[..self.buflen].copy_from_slice(&self.buffer[..self.buflen]);
+        Output {
+            input_cv: self.chunk_cv,
+            block: words(&block),
+            counter: self.chunk_counter(),
+            block_len: self.buflen as u32,
+            flags: self.start_flag() | CHUNK_END,
+        }
+    }
+
+    /// Push the chaining value of a completed chunk, merging the completed
+    /// subtrees along the way.
+    fn push_chunk(&mut self, mut cv: [u32; 8]) {
+        let mut total_chunks = self.chunk_counter() + 1;
+        while total_chunks & 1 == 0 {
+            self.cv_stack_len -= 1;
+            cv = parent_output(&self.cv_stack[self.cv_stack_len], &cv).chaining_value();
+            total_chunks >>= 1;
+        }
+        self.cv_stack[self.cv_stack_len] = cv;
+        self.cv_stack_len += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Blake3;
+
+    #[test]
+    fn official_test_vectors() {
+        // Input of the official test vectors is a repeating sequence of bytes
+        // from 0 to 250.
+        let input = (0..102400).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let cases = [
+            (
+                0,
+                "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262",
+            ),
+            (
+                1,
+                "2d3adedff11b61f14c886e35afa036736dcd87a74d27b5c1510225d0f592e213",
+            ),
+            (
+                1023,
+                "10108970eeda3eb932baac1428c7a2163b0e924c9a9e25b35bba72b28f70bd11",
+            ),
+            (
+                1024,
+                "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7",
+            ),
+            (
+                1025,
+                "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444",
+            ),
+            (
+                2048,
+                "e776b6028c7cd22a4d0ba182a8bf62205d2ef576467e838ed6f2529b85fba24a",
+            ),
+            (
+                3072,
+                "b98cb0ff3623be03326b373de6b9095218513e64f1ee2edd2525c7ad1e5cffd2",
+            ),
+            (
+                31744,
+                "62b6960e1a44bcc1eb1a611a8d6235b6b4b78f32e7abc4fb4c6cdcce94895c47",
+            ),
+            (
+                102400,
+                "bc3e3d41a1146b069abffad3c0d44860cf664390afce4d9661f7902e7943e085",
+            ),
+        ];
+
+        for (len, output) in cases {
+            let mut hasher = Blake3::new();
+            hasher.consume(&input[..len]);
+            assert_eq!(hasher.result_string(), output, "input length {}", len);
+        }
+    }
+
+    #[test]
+    fn chaining_consume() {
+        let data1 = "hello".as_bytes();
+        let data2 = "world".as_bytes();
+        let mut hasher = Blake3::new();
+        hasher.consume(data1).consume(data2);
+        let hex_str = hasher.result_string();
+        let mut hasher = Blake3::new();
+        hasher.consume("helloworld".as_bytes());
+        assert_eq!(hex_str, hasher.result_string());
+    }
+
+    #[test]
+    fn reset() {
+        let mut hasher = Blake3::new();
+        hasher.consume("".as_bytes());
+        hasher.reset();
+        hasher.consume("abc".as_bytes());
+        let hex_str = hasher.result_string();
+        // equal to `abc`
+        assert_eq!(
+            hex_str,
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+}
@@ -64,6 +64,31 @@ impl Md5 {
         self.finished = false;
     }
 
+    /// Export the internal state of this [`Md5`] instance: the chaining
+    /// values in the byte order of the digest, the total length of consumed
+    /// data, and the consumed data that isn't compressed yet.
+    pub fn state(&self) -> (Vec<u8>, u64, &[u8]) {
+        let chain = self.state.iter().flat_map(|i| i.to_le_bytes()).collect();
+        (chain, self.total_length, &self.buffer[..self.buflen])
+    }
+
+    /// Create a new [`Md5`] instance from the state exported by
+    /// [`Md5::state`], returning `None` if the state is malformed.
+    pub fn from_state(chain: &[u8], total_length: u64, buffer: &[u8]) -> Option<Self> {
+        if chain.len() != 16 || buffer.len() as u64 != total_length % 64 {
+            return None;
+        }
+
+        let mut hasher = Self::new();
+        for (i, s) in hasher.state.iter_mut().zip(chain.chunks_exact(4)) {
+            *i = u32::from_le_bytes(s.try_into().unwrap());
+        }
+        hasher.total_length = total_length;
+        hasher.buffer[..buffer.len()].copy_from_slice(buffer);
+        hasher.buflen = buffer.len();
+        Some(hasher)
+    }
+
     /// Consume the last buffer data, finalize the calculation and return
     /// the digest as a `[u8; 16]` array format.
     #[inline]
@@ -1,10 +1,20 @@
 #![allow(dead_code)]
+#[cfg(target_arch = "aarch64")]
+mod aarch64;
+mod blake3;
 mod md5;
 mod sha1;
 mod sha256;
+mod sha3;
+mod sha384;
 mod sha512;
+#[cfg(target_arch = "x86_64")]
+mod x86;
 
+pub use self::blake3::Blake3;
 pub use md5::Md5;
 pub use sha1::Sha1;
 pub use sha256::Sha256;
+pub use sha3::Sha3_256;
+pub use sha384::Sha384;
 pub use sha512::Sha512;
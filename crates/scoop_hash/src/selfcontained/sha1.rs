@@ -4,8 +4,13 @@
 
 use core::{cmp::min, convert::TryInto};
 
+#[cfg(target_arch = "aarch64")]
+use super::aarch64;
+#[cfg(target_arch = "x86_64")]
+use super::x86;
+
 static INIT_STATE: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
-static K: [u32; 4] = [0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xca62c1d6];
+pub(super) static K: [u32; 4] = [0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xca62c1d6];
 
 #[derive(Debug)]
 pub struct Sha1 {
@@ -50,6 +55,31 @@ impl Sha1 {
         self.finished = false;
     }
 
+    /// Export the internal state of this [`Sha1`] instance: the chaining
+    /// values in the byte order of the digest, the total length of consumed
+    /// data, and the consumed data that isn't compressed yet.
+    pub fn state(&self) -> (Vec<u8>, u64, &[u8]) {
+        let chain = self.state.iter().flat_map(|i| i.to_be_bytes()).collect();
+        (chain, self.total_length, &self.buffer[..self.buflen])
+    }
+
+    /// Create a new [`Sha1`] instance from the state exported by
+    /// [`Sha1::state`], returning `None` if the state is malformed.
+    pub fn from_state(chain: &[u8], total_length: u64, buffer: &[u8]) -> Option<Self> {
+        if chain.len() != 20 || buffer.len() as u64 != total_length % 64 {
+            return None;
+        }
+
+        let mut hasher = Self::new();
+        for (i, s) in hasher.state.iter_mut().zip(chain.chunks_exact(4)) {
+            *i = u32::from_be_bytes(s.try_into().unwrap());
+        }
+        hasher.total_length = total_length;
+        hasher.buffer[..buffer.len()].copy_from_slice(buffer);
+        hasher.buflen = buffer.len();
+        Some(hasher)
+    }
+
     /// Consume the last buffer data, finalize the calculation and return
     /// the digest as a `[u8; 20]` array format.
     #[inline]
@@ -115,7 +145,7 @@ impl Sha1 {
             self.buflen += copied_idx;
 
             if self.buflen == 64 {
-                self.compress(self.buffer);
+                compress(&mut self.state, &self.buffer);
 
                 // clear buffer
                 self.buflen = 0
@@ -128,9 +158,7 @@ impl Sha1 {
 
         if length >= 64 {
             let split_idx = length & !63;
-            data[..split_idx].chunks_exact(64).for_each(|block| {
-                self.compress(block.try_into().unwrap());
-            });
+            compress(&mut self.state, &data[..split_idx]);
 
             // keep the remaining untransformed data
             data = &data[split_idx..];
@@ -144,134 +172,145 @@ impl Sha1 {
 
         self
     }
+}
 
-    /// The [`compression function`]: transform a 512 bits input block, which is
-    /// split into 16 `words` (32 bits per word). Then each word is compressed
-    /// into the state.
-    ///
-    /// [`compression function`]: https://en.wikipedia.org/wiki/One-way_compression_function
-    #[inline]
-    fn compress(&mut self, block: [u8; 64]) {
-        // Create temp state variables for compression
-        let [mut a, mut b, mut c, mut d, mut e] = self.state;
+/// The [`compression function`]: transform 512 bits input blocks, each of
+/// which is split into 16 `words` (32 bits per word). Then the words of each
+/// block are compressed into the state.
+///
+/// Hardware accelerated implementations are used when they are detected to
+/// be supported by the CPU at runtime, otherwise the portable one is used.
+///
+/// The length of `blocks` must be a multiple of 64.
+///
+/// [`compression function`]: https://en.wikipedia.org/wiki/One-way_compression_function
+#[inline]
+fn compress(state: &mut [u32; 5], blocks: &[u8]) {
+    debug_assert!(blocks.len() % 64 == 0);
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if x86::has_sha() {
+            // SAFETY: the required CPU features are detected.
+            unsafe { x86::sha1_compress_sha(state, blocks) };
+            return;
+        }
+
+        if x86::has_ssse3() {
+            // SAFETY: the required CPU features are detected.
+            unsafe { x86::sha1_compress_ssse3(state, blocks) };
+            return;
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    if aarch64::has_sha2() {
+        // SAFETY: the required CPU features are detected.
+        unsafe { aarch64::sha1_compress(state, blocks) };
+        return;
+    }
+
+    compress_soft(state, blocks);
+}
 
-        // Break block into sixteen 32-bit `big-endian` words
-        let mut words = [0u32; 16];
+/// The portable implementation of the compression function.
+fn compress_soft(state: &mut [u32; 5], blocks: &[u8]) {
+    for block in blocks.chunks_exact(64) {
+        let mut words = [0u32; 80];
+
+        // Break block into the first sixteen 32-bit `big-endian` words
         for (o, s) in words.iter_mut().zip(block.chunks_exact(4)) {
             *o = u32::from_be_bytes(s.try_into().unwrap());
         }
 
-        for i in 0..16 {
-            let f = (b & c) | ((!b) & d);
-            let t = a
-                .rotate_left(5)
-                .wrapping_add(f)
-                .wrapping_add(e)
-                .wrapping_add(words[i & 0xf])
-                .wrapping_add(K[0]);
-            e = d;
-            d = c;
-            c = b.rotate_left(30);
-            b = a;
-            a = t;
+        // Extend the first 16 words into the remaining 64 words words[16..79]
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
         }
 
-        for i in 16..20 {
-            let tmp = words[(i - 3) & 0xf]
-                ^ words[(i - 8) & 0xf]
-                ^ words[(i - 14) & 0xf]
-                ^ words[i & 0xf];
-            words[i & 0xf] = tmp << 1 | tmp >> (32 - 1);
-
-            let f = (b & c) | ((!b) & d);
-            let t = a
-                .rotate_left(5)
-                .wrapping_add(f)
-                .wrapping_add(e)
-                .wrapping_add(words[i & 0xf])
-                .wrapping_add(K[0]);
-            e = d;
-            d = c;
-            c = b.rotate_left(30);
-            b = a;
-            a = t;
+        // Add the round constants to the words
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = word.wrapping_add(K[i / 20]);
         }
 
-        for i in 20..40 {
-            let tmp = words[(i - 3) & 0xf]
-                ^ words[(i - 8) & 0xf]
-                ^ words[(i - 14) & 0xf]
-                ^ words[i & 0xf];
-            words[i & 0xf] = tmp << 1 | tmp >> (32 - 1);
-
-            let f = b ^ c ^ d;
-            let t = a
-                .rotate_left(5)
-                .wrapping_add(f)
-                .wrapping_add(e)
-                .wrapping_add(words[i & 0xf])
-                .wrapping_add(K[1]);
-            e = d;
-            d = c;
-            c = b.rotate_left(30);
-            b = a;
-            a = t;
-        }
+        rounds(state, &words);
+    }
+}
 
-        for i in 40..60 {
-            let tmp = words[(i - 3) & 0xf]
-                ^ words[(i - 8) & 0xf]
-                ^ words[(i - 14) & 0xf]
-                ^ words[i & 0xf];
-            words[i & 0xf] = tmp << 1 | tmp >> (32 - 1);
-
-            let f = ((b | c) & d) | (b & c);
-            let t = a
-                .rotate_left(5)
-                .wrapping_add(f)
-                .wrapping_add(e)
-                .wrapping_add(words[i & 0xf])
-                .wrapping_add(K[2]);
-            e = d;
-            d = c;
-            c = b.rotate_left(30);
-            b = a;
-            a = t;
-        }
+/// Compress the 80 words of a block, with the round constants added, into the
+/// state.
+#[inline]
+pub(super) fn rounds(state: &mut [u32; 5], words: &[u32; 80]) {
+    // Create temp state variables for compression
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+
+    for word in &words[..20] {
+        let f = (b & c) | ((!b) & d);
+        let t = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(*word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = t;
+    }
 
-        for i in 60..80 {
-            let tmp = words[(i - 3) & 0xf]
-                ^ words[(i - 8) & 0xf]
-                ^ words[(i - 14) & 0xf]
-                ^ words[i & 0xf];
-            words[i & 0xf] = tmp << 1 | tmp >> (32 - 1);
-
-            let f = b ^ c ^ d;
-            let t = a
-                .rotate_left(5)
-                .wrapping_add(f)
-                .wrapping_add(e)
-                .wrapping_add(words[i & 0xf])
-                .wrapping_add(K[3]);
-            e = d;
-            d = c;
-            c = b.rotate_left(30);
-            b = a;
-            a = t;
-        }
+    for word in &words[20..40] {
+        let f = b ^ c ^ d;
+        let t = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(*word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = t;
+    }
+
+    for word in &words[40..60] {
+        let f = ((b | c) & d) | (b & c);
+        let t = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(*word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = t;
+    }
 
-        // Update state
-        self.state[0] = self.state[0].wrapping_add(a);
-        self.state[1] = self.state[1].wrapping_add(b);
-        self.state[2] = self.state[2].wrapping_add(c);
-        self.state[3] = self.state[3].wrapping_add(d);
-        self.state[4] = self.state[4].wrapping_add(e);
+    for word in &words[60..] {
+        let f = b ^ c ^ d;
+        let t = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(*word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = t;
     }
+
+    // Update state
+    state[0] = state[0].wrapping_add(a);
+    state[1] = state[1].wrapping_add(b);
+    state[2] = state[2].wrapping_add(c);
+    state[3] = state[3].wrapping_add(d);
+    state[4] = state[4].wrapping_add(e);
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Sha1;
+    use super::{compress_soft, Sha1, INIT_STATE};
 
     #[test]
     fn rfc_test_suite() {
@@ -333,4 +372,39 @@ mod tests {
         // equal to `abc`
         assert_eq!(hex_str, "a9993e364706816aba3e25717850c26c9cd0d89d");
     }
+
+    #[test]
+    fn accelerated() {
+        let data = (0..64 * 16).map(|i| (i * 7 + 3) as u8).collect::<Vec<_>>();
+        let mut expected = INIT_STATE;
+        compress_soft(&mut expected, &data);
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            use super::x86;
+
+            if x86::has_ssse3() {
+                let mut state = INIT_STATE;
+                unsafe { x86::sha1_compress_ssse3(&mut state, &data) };
+                assert_eq!(state, expected);
+            }
+
+            if x86::has_sha() {
+                let mut state = INIT_STATE;
+                unsafe { x86::sha1_compress_sha(&mut state, &data) };
+                assert_eq!(state, expected);
+            }
+        }
+
+        #[cfg(target_arch = "aarch64")]
+        {
+            use super::aarch64;
+
+            if aarch64::has_sha2() {
+                let mut state = INIT_STATE;
+                unsafe { aarch64::sha1_compress(&mut state, &data) };
+                assert_eq!(state, expected);
+            }
+        }
+    }
 }
@@ -4,10 +4,15 @@
 
 use core::{cmp::min, convert::TryInto};
 
+#[cfg(target_arch = "aarch64")]
+use super::aarch64;
+#[cfg(target_arch = "x86_64")]
+use super::x86;
+
 static INIT_STATE: [u32; 8] = [
     0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
 ];
-static K: [u32; 64] = [
+pub(super) static K: [u32; 64] = [
     0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
     0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
     0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
@@ -61,6 +66,31 @@ impl Sha256 {
         self.finished = false;
     }
 
+    /// Export the internal state of this [`Sha256`] instance: the chaining
+    /// values in the byte order of the digest, the total length of consumed
+    /// data, and the consumed data that isn't compressed yet.
+    pub fn state(&self) -> (Vec<u8>, u64, &[u8]) {
+        let chain = self.state.iter().flat_map(|i| i.to_be_bytes()).collect();
+        (chain, self.total_length, &self.buffer[..self.buflen])
+    }
+
+    /// Create a new [`Sha256`] instance from the state exported by
+    /// [`Sha256::state`], returning `None` if the state is malformed.
+    pub fn from_state(chain: &[u8], total_length: u64, buffer: &[u8]) -> Option<Self> {
+        if chain.len() != 32 || buffer.len() as u64 != total_length % 64 {
+            return None;
+        }
+
+        let mut hasher = Self::new();
+        for (i, s) in hasher.state.iter_mut().zip(chain.chunks_exact(4)) {
+            *i = u32::from_be_bytes(s.try_into().unwrap());
+        }
+        hasher.total_length = total_length;
+        hasher.buffer[..buffer.len()].copy_from_slice(buffer);
+        hasher.buflen = buffer.len();
+        Some(hasher)
+    }
+
     /// Consume the last buffer data, finalize the calculation and return
     /// the digest as a `[u8; 32]` array format.
     #[inline]
@@ -126,7 +156,7 @@ impl Sha256 {
             self.buflen += copied_idx;
 
             if self.buflen == 64 {
-                self.compress(self.buffer);
+                compress(&mut self.state, &self.buffer);
 
                 // clear buffer
                 self.buflen = 0
@@ -139,9 +169,7 @@ impl Sha256 {
 
         if length >= 64 {
             let split_idx = length & !63;
-            data[..split_idx].chunks_exact(64).for_each(|block| {
-                self.compress(block.try_into().unwrap());
-            });
+            compress(&mut self.state, &data[..split_idx]);
 
             // keep the remaining untransformed data
             data = &data[split_idx..];
@@ -155,17 +183,50 @@ impl Sha256 {
 
         self
     }
+}
 
-    /// The [`compression function`]: transform a 512 bits input block, which is
-    /// split into 16 `words` (32 bits per word). Then each word is compressed
-    /// into the state.
-    ///
-    /// [`compression function`]: https://en.wikipedia.org/wiki/One-way_compression_function
-    #[inline]
-    fn compress(&mut self, block: [u8; 64]) {
-        // Create temp state variables for compression
-        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+/// The [`compression function`]: transform 512 bits input blocks, each of
+/// which is split into 16 `words` (32 bits per word). Then the words of each
+/// block are compressed into the state.
+///
+/// Hardware accelerated implementations are used when they are detected to
+/// be supported by the CPU at runtime, otherwise the portable one is used.
+///
+/// The length of `blocks` must be a multiple of 64.
+///
+/// [`compression function`]: https://en.wikipedia.org/wiki/One-way_compression_function
+#[inline]
+fn compress(state: &mut [u32; 8], blocks: &[u8]) {
+    debug_assert!(blocks.len() % 64 == 0);
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if x86::has_sha() {
+            // SAFETY: the required CPU features are detected.
+            unsafe { x86::sha256_compress_sha(state, blocks) };
+            return;
+        }
+
+        if x86::has_ssse3() {
+            // SAFETY: the required CPU features are detected.
+            unsafe { x86::sha256_compress_ssse3(state, blocks) };
+            return;
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    if aarch64::has_sha2() {
+        // SAFETY: the required CPU features are detected.
+        unsafe { aarch64::sha256_compress(state, blocks) };
+        return;
+    }
 
+    compress_soft(state, blocks);
+}
+
+/// The portable implementation of the compression function.
+fn compress_soft(state: &mut [u32; 8], blocks: &[u8]) {
+    for block in blocks.chunks_exact(64) {
         let mut words = [0u32; 64];
 
         // Break block into the first sixteen 32-bit `big-endian` words
@@ -188,44 +249,55 @@ impl Sha256 {
                 .wrapping_add(s1);
         }
 
-        // Compression function main loop
-        for i in 0..64 {
-            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
-            let ch = (e & f) ^ (!e & g);
-            let temp1 = h
-                .wrapping_add(s1)
-                .wrapping_add(ch)
-                .wrapping_add(K[i])
-                .wrapping_add(words[i]);
-            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
-            let maj = (a & b) ^ (a & c) ^ (b & c);
-            let temp2 = s0.wrapping_add(maj);
-
-            h = g;
-            g = f;
-            f = e;
-            e = d.wrapping_add(temp1);
-            d = c;
-            c = b;
-            b = a;
-            a = temp1.wrapping_add(temp2);
+        // Add the round constants to the words
+        for (word, k) in words.iter_mut().zip(K.iter()) {
+            *word = word.wrapping_add(*k);
         }
 
-        // Update state
-        self.state[0] = self.state[0].wrapping_add(a);
-        self.state[1] = self.state[1].wrapping_add(b);
-        self.state[2] = self.state[2].wrapping_add(c);
-        self.state[3] = self.state[3].wrapping_add(d);
-        self.state[4] = self.state[4].wrapping_add(e);
-        self.state[5] = self.state[5].wrapping_add(f);
-        self.state[6] = self.state[6].wrapping_add(g);
-        self.state[7] = self.state[7].wrapping_add(h);
+        rounds(state, &words);
+    }
+}
+
+/// Compress the 64 words of a block, with the round constants added, into the
+/// state.
+#[inline]
+pub(super) fn rounds(state: &mut [u32; 8], words: &[u32; 64]) {
+    // Create temp state variables for compression
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+
+    // Compression function main loop
+    for word in words.iter() {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let temp1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(*word);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(temp1);
+        d = c;
+        c = b;
+        b = a;
+        a = temp1.wrapping_add(temp2);
     }
+
+    // Update state
+    state[0] = state[0].wrapping_add(a);
+    state[1] = state[1].wrapping_add(b);
+    state[2] = state[2].wrapping_add(c);
+    state[3] = state[3].wrapping_add(d);
+    state[4] = state[4].wrapping_add(e);
+    state[5] = state[5].wrapping_add(f);
+    state[6] = state[6].wrapping_add(g);
+    state[7] = state[7].wrapping_add(h);
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Sha256;
+    use super::{compress_soft, Sha256, INIT_STATE};
 
     #[test]
     fn rfc_test_suite() {
@@ -295,4 +367,39 @@ mod tests {
             "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
         );
     }
+
+    #[test]
+    fn accelerated() {
+        let data = (0..64 * 16).map(|i| (i * 7 + 3) as u8).collect::<Vec<_>>();
+        let mut expected = INIT_STATE;
+        compress_soft(&mut expected, &data);
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            use super::x86;
+
+            if x86::has_ssse3() {
+                let mut state = INIT_STATE;
+                unsafe { x86::sha256_compress_ssse3(&mut state, &data) };
+                assert_eq!(state, expected);
+            }
+
+            if x86::has_sha() {
+                let mut state = INIT_STATE;
+                unsafe { x86::sha256_compress_sha(&mut state, &data) };
+                assert_eq!(state, expected);
+            }
+        }
+
+        #[cfg(target_arch = "aarch64")]
+        {
+            use super::aarch64;
+
+            if aarch64::has_sha2() {
+                let mut state = INIT_STATE;
+                unsafe { aarch64::sha256_compress(&mut state, &data) };
+                assert_eq!(state, expected);
+            }
+        }
+    }
 }
@@ -0,0 +1,287 @@
+// references:
+//   [SHA-3](https://en.wikipedia.org/wiki/SHA-3)
+//   [FIPS 202](https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.202.pdf)
+//   [golang crypto sha3](https://github.com/golang/crypto/blob/master/sha3/sha3.go)
+
+use core::{cmp::min, convert::TryInto};
+
+/// Bytes absorbed into the state per permutation, which is the state size
+/// minus twice the digest size.
+const RATE: usize = 136;
+
+/// Round constants of the iota step.
+static RC: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// Rotation offsets of the rho step, in the order of the lanes visited by the
+/// pi step.
+static RHO: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+/// Lanes visited by the pi step, starting from lane 1.
+static PI: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+#[derive(Debug)]
+pub struct Sha3_256 {
+    // The 5x5 lanes of the Keccak state, lane (x, y) is at `x + 5 * y`
+    state: [u64; 25],
+    // Hold total length of input data
+    total_length: u64,
+    // Store the last part of input data to be absorbed
+    buffer: [u8; RATE],
+    // Hold the length of the last part of input data that aren't absorbed yet,
+    // the `buflen` can only be from 0 to RATE
+    buflen: usize,
+}
+
+impl Default for Sha3_256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sha3_256 {
+    /// Create a new [`Sha3_256`] instance to consume data and get digest.
+    #[inline]
+    pub fn new() -> Self {
+        Sha3_256 {
+            state: [0; 25],
+            total_length: 0,
+            buffer: [0; RATE],
+            buflen: 0,
+        }
+    }
+
+    /// Reset this [`Sha3_256`] instance's status.
+    #[inline]
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Export the internal state of this [`Sha3_256`] instance: the lanes of
+    /// the Keccak state in little-endian, the total length of consumed data,
+    /// and the consumed data that isn't absorbed yet.
+    pub fn state(&self) -> (Vec<u8>, u64, &[u8]) {
+        let chain = self.state.iter().flat_map(|i| i.to_le_bytes()).collect();
+        (chain, self.total_length, &self.buffer[..self.buflen])
+    }
+
+    /// Create a new [`Sha3_256`] instance from the state exported by
+    /// [`Sha3_256::state`], returning `None` if the state is malformed.
+    pub fn from_state(chain: &[u8], total_length: u64, buffer: &[u8]) -> Option<Self> {
+        if chain.len() != 200 || buffer.len() as u64 != total_length % RATE as u64 {
+            return None;
+        }
+
+        let mut hasher = Self::new();
+        for (i, s) in hasher.state.iter_mut().zip(chain.chunks_exact(8)) {
+            *i = u64::from_le_bytes(s.try_into().unwrap());
+        }
+        hasher.total_length = total_length;
+        hasher.buffer[..buffer.len()].copy_from_slice(buffer);
+        hasher.buflen = buffer.len();
+        Some(hasher)
+    }
+
+    /// Consume the last buffer data, finalize the calculation and return
+    /// the digest as a `[u8; 32]` array format.
+    #[inline]
+    pub fn result(mut self) -> [u8; 32] {
+        // SHA-3 domain separation bits `01` followed by the `pad10*1` padding,
+        // the first and the last padding bits may share the same byte.
+        let mut block = [0u8; RATE];
+        block[..self.buflen].copy_from_slice(&self.buffer[..self.buflen]);
+        block[self.buflen] ^= 0x06;
+        block[RATE - 1] ^= 0x80;
+        absorb(&mut self.state, &block);
+
+        self.state[..4]
+            .iter()
+            .flat_map(|i| i.to_le_bytes())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }
+
+    /// Consume the last buffer data, finalize the calculation and return
+    /// the digest as a [`String`] format.
+    #[inline]
+    pub fn result_string(self) -> String {
+        self.result()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    /// Consume the input data, but not finalize the calculation. This
+    /// method returns `&self` to make itself chainable, so that callers
+    /// can continuously consume data by chaining function calls.
+    pub fn consume<D: AsRef<[u8]>>(&mut self, data: D) -> &mut Self {
+        let mut data = data.as_ref();
+
+        self.total_length += data.len() as u64;
+
+        if self.buflen > 0 {
+            let copied_idx = min(RATE - self.buflen, data.len());
+            self.buffer[self.buflen..self.buflen + copied_idx].copy_from_slice(&data[..copied_idx]);
+            self.buflen += copied_idx;
+
+            if self.buflen == RATE {
+                absorb(&mut self.state, &self.buffer);
+
+                // clear buffer
+                self.buflen = 0;
+            }
+
+            // keep the remaining unabsorbed data
+            data = &data[copied_idx..];
+        }
+
+        let mut blocks = data.chunks_exact(RATE);
+        for block in &mut blocks {
+            absorb(&mut self.state, block.try_into().unwrap());
+        }
+
+        // The remainder here must be shorter than RATE
+        let remainder = blocks.remainder();
+        if !remainder.is_empty() {
+            self.buffer[..remainder.len()].copy_from_slice(remainder);
+            self.buflen = remainder.len();
+        }
+
+        self
+    }
+}
+
+/// XOR a block into the state and apply the permutation.
+#[inline]
+fn absorb(state: &mut [u64; 25], block: &[u8; RATE]) {
+    for (lane, s) in state.iter_mut().zip(block.chunks_exact(8)) {
+        *lane ^= u64::from_le_bytes(s.try_into().unwrap());
+    }
+    keccak_f(state);
+}
+
+/// The Keccak-f\[1600\] permutation.
+fn keccak_f(a: &mut [u64; 25]) {
+    for rc in RC.iter() {
+        // theta
+        let mut c = [0u64; 5];
+        for (x, c) in c.iter_mut().enumerate() {
+            *c = a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                a[x + 5 * y] ^= d;
+            }
+        }
+
+        // rho and pi
+        let mut last = a[1];
+        for (&lane, &rho) in PI.iter().zip(RHO.iter()) {
+            let tmp = a[lane];
+            a[lane] = last.rotate_left(rho);
+            last = tmp;
+        }
+
+        // chi
+        for y in 0..5 {
+            let row: [u64; 5] = a[5 * y..5 * y + 5].try_into().unwrap();
+            for x in 0..5 {
+                a[x + 5 * y] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+
+        // iota
+        a[0] ^= rc;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sha3_256;
+
+    #[test]
+    fn nist_test_suite() {
+        let inputs = [
+            "",
+            "a",
+            "abc",
+            "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "0123456701234567012345670123456701234567012345670123456701234567",
+            "abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+        ];
+        let outputs = [
+            "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a",
+            "80084bf2fba02475726feb2cab2d8215eab14bc6bdd8bfb2c8151257032ecd8b",
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+            "41c0dba2a9d6240849100376a8235e2c82e1b9998a999e21db32dd97496d3376",
+            "766047e8f73efd0b6a5a849dc2323a1082a05ef9b5d4496413cdc95c43b35754",
+            "916f6061fe879741ca6469b43971dfdb28b1a32dc36cb3254e812be27aad1d18",
+        ];
+
+        for (input, &output) in inputs.iter().zip(outputs.iter()) {
+            let mut hasher = Sha3_256::new();
+            hasher.consume(input.as_bytes());
+            let computed = hasher.result_string();
+            assert_eq!(output, computed);
+        }
+    }
+
+    #[test]
+    fn chaining_consume() {
+        let data1 = "hello".as_bytes();
+        let data2 = "world".as_bytes();
+        let mut hasher = Sha3_256::new();
+        hasher.consume(data1).consume(data2);
+        let hex_str = hasher.result_string();
+        // equal to `helloworld`
+        assert_eq!(
+            hex_str,
+            "92dad9443e4dd6d70a7f11872101ebff87e21798e4fbb26fa4bf590eb440e71b"
+        );
+    }
+
+    #[test]
+    fn reset() {
+        let mut hasher = Sha3_256::new();
+        hasher.consume("".as_bytes());
+        hasher.reset();
+        hasher.consume("abc".as_bytes());
+        let hex_str = hasher.result_string();
+        // equal to `abc`
+        assert_eq!(
+            hex_str,
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+// references:
+//   [SHA-2](https://en.wikipedia.org/wiki/SHA-2)
+//   [golang crypto sha512](https://github.com/golang/go/blob/master/src/crypto/sha512/sha512.go)
+//
+// SHA-384 is SHA-512 with different initial hash values and the digest
+// truncated to 384 bits.
+
+use core::convert::TryInto;
+
+use super::Sha512;
+
+static INIT_STATE: [u64; 8] = [
+    0xcbbb9d5dc1059ed8,
+    0x629a292a367cd507,
+    0x9159015a3070dd17,
+    0x152fecd8f70e5939,
+    0x67332667ffc00b31,
+    0x8eb44a8768581511,
+    0xdb0c2e0d64f98fa7,
+    0x47b5481dbefa4fa4,
+];
+
+#[derive(Debug)]
+pub struct Sha384 {
+    inner: Sha512,
+}
+
+impl Default for Sha384 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sha384 {
+    /// Create a new [`Sha384`] instance to consume data and get digest.
+    #[inline]
+    pub fn new() -> Self {
+        Sha384 {
+            inner: Sha512::with_init_state(INIT_STATE),
+        }
+    }
+
+    /// Reset this [`Sha384`] instance's status.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.inner = Sha512::with_init_state(INIT_STATE);
+    }
+
+    /// Export the internal state of this [`Sha384`] instance, see
+    /// [`Sha512::state`].
+    pub fn state(&self) -> (Vec<u8>, u64, &[u8]) {
+        self.inner.state()
+    }
+
+    /// Create a new [`Sha384`] instance from the state exported by
+    /// [`Sha384::state`], returning `None` if the state is malformed.
+    pub fn from_state(chain: &[u8], total_length: u64, buffer: &[u8]) -> Option<Self> {
+        Sha512::from_state(chain, total_length, buffer).map(|inner| Sha384 { inner })
+    }
+
+    /// Consume the last buffer data, finalize the calculation and return
+    /// the digest as a `[u8; 48]` array format.
+    #[inline]
+    pub fn result(self) -> [u8; 48] {
+        self.inner.result()[..48].try_into().unwrap()
+    }
+
+    /// Consume the last buffer data, finalize the calculation and return
+    /// the digest as a [`String`] format.
+    #[inline]
+    pub fn result_string(self) -> String {
+        self.result()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    /// Consume the input data, but not finalize the calculation. This
+    /// method returns `&self` to make itself chainable, so that callers
+    /// can continuously consume data by chaining function calls.
+    pub fn consume<D: AsRef<[u8]>>(&mut self, data: D) -> &mut Self {
+        self.inner.consume(data);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sha384;
+
+    #[test]
+    fn rfc_test_suite() {
+        let inputs = [
+            "",
+            "a",
+            "abc",
+            "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "0123456701234567012345670123456701234567012345670123456701234567",
+            "abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+        ];
+        let outputs = [
+            "38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b",
+            "54a59b9f22b0b80880d8427e548b7c23abd873486e1f035dce9cd697e85175033caa88e6d57bc35efae0b5afd3145f31",
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+            "3391fdddfc8dc7393707a65b1b4709397cf8b1d162af05abfe8f450de5f36bc6b0455a8520bc4e6f5fe95b1fe3c8452b",
+            "72f5893331c249312d3c2b7a9709a7b96908b7769179dd9824ed578669fcc1f1c2de02c03b3d35a467aa0b472c1bb3d1",
+            "09330c33f71147e83d192fc782cd1b4753111b173b3b05d22fa08086e3b0f712fcc7c71a557e2db966c3e9fa91746039",
+        ];
+
+        for (input, &output) in inputs.iter().zip(outputs.iter()) {
+            let mut hasher = Sha384::new();
+            hasher.consume(input.as_bytes());
+            let computed = hasher.result_string();
+            assert_eq!(output, computed);
+        }
+    }
+
+    #[test]
+    fn chaining_consume() {
+        let data1 = "hello".as_bytes();
+        let data2 = "world".as_bytes();
+        let mut hasher = Sha384::new();
+        hasher.consume(data1).consume(data2);
+        let hex_str = hasher.result_string();
+        // equal to `helloworld`
+        assert_eq!(hex_str, "97982a5b1414b9078103a1c008c4e3526c27b41cdbcf80790560a40f2a9bf2ed4427ab1428789915ed4b3dc07c454bd9");
+    }
+
+    #[test]
+    fn reset() {
+        let mut hasher = Sha384::new();
+        hasher.consume("".as_bytes());
+        hasher.reset();
+        hasher.consume("abc".as_bytes());
+        let hex_str = hasher.result_string();
+        // equal to `abc`
+        assert_eq!(hex_str, "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7");
+    }
+}
@@ -130,6 +130,16 @@ impl Sha512 {
         }
     }
 
+    /// Create a new [`Sha512`] instance with the given initial state, which
+    /// is how the truncated variants of SHA-512 are computed.
+    #[inline]
+    pub(super) fn with_init_state(state: [u64; 8]) -> Self {
+        Sha512 {
+            state,
+            ..Self::new()
+        }
+    }
+
     /// Reset this [`Sha512`] instance's status.
     #[inline]
     pub fn reset(&mut self) {
@@ -140,6 +150,31 @@ impl Sha512 {
         self.finished = false;
     }
 
+    /// Export the internal state of this [`Sha512`] instance: the chaining
+    /// values in the byte order of the digest, the total length of consumed
+    /// data, and the consumed data that isn't compressed yet.
+    pub fn state(&self) -> (Vec<u8>, u64, &[u8]) {
+        let chain = self.state.iter().flat_map(|i| i.to_be_bytes()).collect();
+        (chain, self.total_length as u64, &self.buffer[..self.buflen])
+    }
+
+    /// Create a new [`Sha512`] instance from the state exported by
+    /// [`Sha512::state`], returning `None` if the state is malformed.
+    pub fn from_state(chain: &[u8], total_length: u64, buffer: &[u8]) -> Option<Self> {
+        if chain.len() != 64 || buffer.len() as u64 != total_length % 128 {
+            return None;
+        }
+
+        let mut hasher = Self::new();
+        for (i, s) in hasher.state.iter_mut().zip(chain.chunks_exact(8)) {
+            *i = u64::from_be_bytes(s.try_into().unwrap());
+        }
+        hasher.total_length = total_length as u128;
+        hasher.buffer[..buffer.len()].copy_from_slice(buffer);
+        hasher.buflen = buffer.len();
+        Some(hasher)
+    }
+
     /// Consume the last buffer data, finalize the calculation and return
     /// the digest as a `[u8; 64]` array format.
     #[inline]
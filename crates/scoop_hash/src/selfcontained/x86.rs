@@ -0,0 +1,266 @@
+//! x86_64 accelerated compression functions of SHA-1 and SHA-256.
+//!
+//! Two implementations are provided for each algorithm:
+//!
+//! - one using the SHA extensions (SHA-NI), which computes the whole rounds
+//!   with dedicated instructions;
+//! - one using SSSE3, which computes the message schedule four words at a time
+//!   and leaves the rounds to the portable implementation.
+//!
+//! references:
+//!   [Intel SHA Extensions](https://www.intel.com/content/www/us/en/developer/articles/technical/intel-sha-extensions.html)
+//!   [Improving the Performance of the Secure Hash Algorithm (SHA-1)](https://www.intel.com/content/www/us/en/developer/articles/technical/improving-the-performance-of-the-secure-hash-algorithm-1.html)
+
+use core::arch::x86_64::*;
+
+use super::{sha1, sha256};
+
+/// Check if the SHA extensions and the instructions required along with them
+/// are supported.
+#[inline]
+pub fn has_sha() -> bool {
+    is_x86_feature_detected!("sha")
+        && is_x86_feature_detected!("sse4.1")
+        && is_x86_feature_detected!("ssse3")
+}
+
+/// Check if SSSE3 is supported.
+#[inline]
+pub fn has_ssse3() -> bool {
+    is_x86_feature_detected!("ssse3")
+}
+
+/// Rotate right the 32-bit lanes of `x` by `n` bits.
+macro_rules! rotr {
+    ($x:expr, $n:literal) => {
+        _mm_or_si128(_mm_srli_epi32($x, $n), _mm_slli_epi32($x, 32 - $n))
+    };
+}
+
+/// Compute SHA-1 rounds of the given range of four-round groups with the
+/// round function `func`, scheduling the message words along the way.
+macro_rules! sha1_rounds {
+    ($w:ident, $prev:ident, $cur:ident, $range:expr, $func:literal) => {
+        for i in $range {
+            let words = if i < 4 {
+                $w[i]
+            } else {
+                let x = _mm_xor_si128(_mm_sha1msg1_epu32($w[0], $w[1]), $w[2]);
+                let next = _mm_sha1msg2_epu32(x, $w[3]);
+                $w = [$w[1], $w[2], $w[3], next];
+                next
+            };
+
+            let next = _mm_sha1rnds4_epu32($cur, _mm_sha1nexte_epu32($prev, words), $func);
+            $prev = $cur;
+            $cur = next;
+        }
+    };
+}
+
+/// Load the `i`-th 16 bytes of `block` as four big-endian 32-bit words.
+#[inline]
+#[target_feature(enable = "ssse3")]
+unsafe fn load_be(block: &[u8], i: usize) -> __m128i {
+    // Swap bytes of each 32-bit lane
+    let mask = _mm_set_epi64x(0x0c0d_0e0f_0809_0a0b, 0x0405_0607_0001_0203);
+    let data = _mm_loadu_si128(block[i * 16..].as_ptr() as *const __m128i);
+    _mm_shuffle_epi8(data, mask)
+}
+
+/// Compress SHA-1 blocks into the state using the SHA extensions.
+///
+/// # Safety
+///
+/// The caller must ensure [`has_sha`] is true.
+#[target_feature(enable = "sha,sse2,ssse3,sse4.1")]
+pub unsafe fn sha1_compress_sha(state: &mut [u32; 5], blocks: &[u8]) {
+    // Reverse the order of all 16 bytes, the first word goes to the top lane
+    let mask = _mm_set_epi64x(0x0001_0203_0405_0607, 0x0809_0a0b_0c0d_0e0f);
+
+    let mut abcd = _mm_set_epi32(
+        state[0] as i32,
+        state[1] as i32,
+        state[2] as i32,
+        state[3] as i32,
+    );
+    let mut e = _mm_set_epi32(state[4] as i32, 0, 0, 0);
+
+    for block in blocks.chunks_exact(64) {
+        let mut w = [_mm_setzero_si128(); 4];
+        for (i, w) in w.iter_mut().enumerate() {
+            let data = _mm_loadu_si128(block[i * 16..].as_ptr() as *const __m128i);
+            *w = _mm_shuffle_epi8(data, mask);
+        }
+
+        // Rounds 0 to 3, E is added to the words directly
+        let mut prev = abcd;
+        let mut cur = _mm_sha1rnds4_epu32(abcd, _mm_add_epi32(e, w[0]), 0);
+
+        // Rounds 4 to 79, four rounds at a time
+        sha1_rounds!(w, prev, cur, 1..5, 0);
+        sha1_rounds!(w, prev, cur, 5..10, 1);
+        sha1_rounds!(w, prev, cur, 10..15, 2);
+        sha1_rounds!(w, prev, cur, 15..20, 3);
+
+        abcd = _mm_add_epi32(abcd, cur);
+        e = _mm_sha1nexte_epu32(prev, e);
+    }
+
+    state[0] = _mm_extract_epi32(abcd, 3) as u32;
+    state[1] = _mm_extract_epi32(abcd, 2) as u32;
+    state[2] = _mm_extract_epi32(abcd, 1) as u32;
+    state[3] = _mm_extract_epi32(abcd, 0) as u32;
+    state[4] = _mm_extract_epi32(e, 3) as u32;
+}
+
+/// Compress SHA-1 blocks into the state, with the message schedule computed
+/// by SSSE3.
+///
+/// # Safety
+///
+/// The caller must ensure [`has_ssse3`] is true.
+#[target_feature(enable = "sse2,ssse3")]
+pub unsafe fn sha1_compress_ssse3(state: &mut [u32; 5], blocks: &[u8]) {
+    let mut words = [0u32; 80];
+
+    for block in blocks.chunks_exact(64) {
+        let mut w = [_mm_setzero_si128(); 4];
+        for (i, w) in w.iter_mut().enumerate() {
+            *w = load_be(block, i);
+        }
+
+        for i in 0..20 {
+            if i >= 4 {
+                // W[t..t+4] = (W[t-3] ^ W[t-8] ^ W[t-14] ^ W[t-16]) <<< 1,
+                // where W[t] is not known yet when computing W[t+3]. Its part
+                // is computed with zero first, and then fixed up with W[t].
+                let w3 = _mm_srli_si128(w[3], 4);
+                let w14 = _mm_alignr_epi8(w[1], w[0], 8);
+                let x = _mm_xor_si128(_mm_xor_si128(w[0], w14), _mm_xor_si128(w[2], w3));
+                let x = _mm_or_si128(_mm_slli_epi32(x, 1), _mm_srli_epi32(x, 31));
+                let fix = _mm_slli_si128(x, 12);
+                let fix = _mm_or_si128(_mm_slli_epi32(fix, 1), _mm_srli_epi32(fix, 31));
+                w = [w[1], w[2], w[3], _mm_xor_si128(x, fix)];
+            }
+
+            let current = if i < 4 { w[i] } else { w[3] };
+            let k = _mm_set1_epi32(sha1::K[i / 5] as i32);
+            let dst = words[i * 4..].as_mut_ptr() as *mut __m128i;
+            _mm_storeu_si128(dst, _mm_add_epi32(current, k));
+        }
+
+        sha1::rounds(state, &words);
+    }
+}
+
+/// Compress SHA-256 blocks into the state using the SHA extensions.
+///
+/// # Safety
+///
+/// The caller must ensure [`has_sha`] is true.
+#[target_feature(enable = "sha,sse2,ssse3,sse4.1")]
+pub unsafe fn sha256_compress_sha(state: &mut [u32; 8], blocks: &[u8]) {
+    // The instructions take the state as ABEF and CDGH
+    let dcba = _mm_loadu_si128(state[0..].as_ptr() as *const __m128i);
+    let hgfe = _mm_loadu_si128(state[4..].as_ptr() as *const __m128i);
+    let cdab = _mm_shuffle_epi32(dcba, 0xb1);
+    let efgh = _mm_shuffle_epi32(hgfe, 0x1b);
+    let mut abef = _mm_alignr_epi8(cdab, efgh, 8);
+    let mut cdgh = _mm_blend_epi16(efgh, cdab, 0xf0);
+
+    for block in blocks.chunks_exact(64) {
+        let abef_saved = abef;
+        let cdgh_saved = cdgh;
+
+        let mut w = [_mm_setzero_si128(); 4];
+        for (i, w) in w.iter_mut().enumerate() {
+            *w = load_be(block, i);
+        }
+
+        for i in 0..16 {
+            let words = if i < 4 {
+                w[i]
+            } else {
+                let x = _mm_sha256msg1_epu32(w[0], w[1]);
+                let x = _mm_add_epi32(x, _mm_alignr_epi8(w[3], w[2], 4));
+                let next = _mm_sha256msg2_epu32(x, w[3]);
+                w = [w[1], w[2], w[3], next];
+                next
+            };
+
+            let k = _mm_loadu_si128(sha256::K[i * 4..].as_ptr() as *const __m128i);
+            let wk = _mm_add_epi32(words, k);
+            cdgh = _mm_sha256rnds2_epu32(cdgh, abef, wk);
+            abef = _mm_sha256rnds2_epu32(abef, cdgh, _mm_shuffle_epi32(wk, 0x0e));
+        }
+
+        abef = _mm_add_epi32(abef, abef_saved);
+        cdgh = _mm_add_epi32(cdgh, cdgh_saved);
+    }
+
+    let feba = _mm_shuffle_epi32(abef, 0x1b);
+    let dchg = _mm_shuffle_epi32(cdgh, 0xb1);
+    let dcba = _mm_blend_epi16(feba, dchg, 0xf0);
+    let hgfe = _mm_alignr_epi8(dchg, feba, 8);
+    _mm_storeu_si128(state[0..].as_mut_ptr() as *mut __m128i, dcba);
+    _mm_storeu_si128(state[4..].as_mut_ptr() as *mut __m128i, hgfe);
+}
+
+/// Compress SHA-256 blocks into the state, with the message schedule computed
+/// by SSSE3.
+///
+/// # Safety
+///
+/// The caller must ensure [`has_ssse3`] is true.
+#[target_feature(enable = "sse2,ssse3")]
+pub unsafe fn sha256_compress_ssse3(state: &mut [u32; 8], blocks: &[u8]) {
+    let mut words = [0u32; 64];
+
+    for block in blocks.chunks_exact(64) {
+        let mut w = [_mm_setzero_si128(); 4];
+        for (i, w) in w.iter_mut().enumerate() {
+            *w = load_be(block, i);
+        }
+
+        for i in 0..16 {
+            if i >= 4 {
+                // W[t] = s1(W[t-2]) + W[t-7] + s0(W[t-15]) + W[t-16]
+                let w15 = _mm_alignr_epi8(w[1], w[0], 4);
+                let s0 = _mm_xor_si128(
+                    _mm_xor_si128(rotr!(w15, 7), rotr!(w15, 18)),
+                    _mm_srli_epi32(w15, 3),
+                );
+                let w7 = _mm_alignr_epi8(w[3], w[2], 4);
+                let x = _mm_add_epi32(_mm_add_epi32(w[0], s0), w7);
+
+                // W[t-2] of the higher two words are the lower two words
+                // being computed, so s1 is computed for each half in turn.
+                let lo = sigma1(_mm_shuffle_epi32(w[3], 0xfe));
+                let x = _mm_add_epi32(x, _mm_move_epi64(lo));
+                let hi = sigma1(_mm_shuffle_epi32(x, 0x40));
+                let x = _mm_add_epi32(x, _mm_unpackhi_epi64(_mm_setzero_si128(), hi));
+
+                w = [w[1], w[2], w[3], x];
+            }
+
+            let current = if i < 4 { w[i] } else { w[3] };
+            let k = _mm_loadu_si128(sha256::K[i * 4..].as_ptr() as *const __m128i);
+            let dst = words[i * 4..].as_mut_ptr() as *mut __m128i;
+            _mm_storeu_si128(dst, _mm_add_epi32(current, k));
+        }
+
+        sha256::rounds(state, &words);
+    }
+}
+
+/// The SHA-256 `s1` function of the message schedule, on the 32-bit lanes of
+/// `x`.
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn sigma1(x: __m128i) -> __m128i {
+    _mm_xor_si128(
+        _mm_xor_si128(rotr!(x, 17), rotr!(x, 19)),
+        _mm_srli_epi32(x, 10),
+    )
+}
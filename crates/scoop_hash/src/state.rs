@@ -0,0 +1,198 @@
+use crate::{Algorithm, Error, Hasher};
+
+/// Magic bytes of serialized states, including the format version.
+const MAGIC: &[u8; 4] = b"SHS\x02";
+
+/// HashState is the state of an unfinished hash computation.
+///
+/// A state holds the chaining values, the length of consumed data, and the
+/// consumed data that isn't compressed yet. It can be serialized to resume the
+/// computation later, for instance when resuming an interrupted download
+/// without hashing the downloaded part again.
+///
+/// # Examples
+///
+/// ```rust
+/// use scoop_hash::{Checksum, ChecksumBuilder, HashState};
+/// let mut sha256 = ChecksumBuilder::new().resumable().build();
+/// sha256.consume(b"hello ");
+/// let bytes = sha256.state().unwrap().to_bytes();
+///
+/// let state = HashState::from_bytes(&bytes).unwrap();
+/// let mut sha256 = Checksum::resume(&state);
+/// sha256.consume(b"world");
+/// assert!(sha256.check("b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HashState {
+    pub(crate) algo: Algorithm,
+    pub(crate) chain: Vec<u8>,
+    pub(crate) length: u64,
+    pub(crate) buffer: Vec<u8>,
+}
+
+impl HashState {
+    /// Get the hash algorithm of the state.
+    pub fn algo(&self) -> Algorithm {
+        self.algo
+    }
+
+    /// Get the length of data consumed up to the state.
+    pub fn consumed(&self) -> u64 {
+        self.length
+    }
+
+    /// Serialize the state into bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let name = self.algo.name().as_bytes();
+        let mut ret = Vec::with_capacity(
+            MAGIC.len() + 12 + name.len() + self.chain.len() + self.buffer.len(),
+        );
+
+        ret.extend_from_slice(MAGIC);
+        ret.push(name.len() as u8);
+        ret.extend_from_slice(name);
+        ret.extend_from_slice(&self.length.to_le_bytes());
+        ret.extend_from_slice(&(self.chain.len() as u16).to_le_bytes());
+        ret.extend_from_slice(&self.chain);
+        ret.push(self.buffer.len() as u8);
+        ret.extend_from_slice(&self.buffer);
+        ret
+    }
+
+    /// Deserialize a state from bytes serialized by [`HashState::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidState`] if the bytes are not a valid state, or
+    /// [`Error::UnsupportedAlgorithm`] if the algorithm of the state is not
+    /// supported.
+    pub fn from_bytes(bytes: &[u8]) -> Result<HashState, Error> {
+        let mut reader = Reader(bytes);
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(Error::InvalidState);
+        }
+
+        let len = reader.take(1)?[0] as usize;
+        let name = std::str::from_utf8(reader.take(len)?).map_err(|_| Error::InvalidState)?;
+        let algo = name.parse::<Algorithm>()?;
+        let length = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+        let len = u16::from_le_bytes(reader.take(2)?.try_into().unwrap()) as usize;
+        let chain = reader.take(len)?.to_vec();
+        let len = reader.take(1)?[0] as usize;
+        let buffer = reader.take(len)?.to_vec();
+
+        if !reader.0.is_empty() {
+            return Err(Error::InvalidState);
+        }
+
+        let state = HashState {
+            algo,
+            chain,
+            length,
+            buffer,
+        };
+
+        // make sure the state can be resumed
+        match state.hasher() {
+            Some(_) => Ok(state),
+            None => Err(Error::InvalidState),
+        }
+    }
+
+    /// Create a hasher resuming from the state, `None` if the state is
+    /// malformed.
+    pub(crate) fn hasher(&self) -> Option<Box<dyn Hasher>> {
+        use crate::selfcontained::{Blake3, Md5, Sha1, Sha256, Sha384, Sha3_256, Sha512};
+
+        let (chain, length, buffer) = (&self.chain[..], self.length, &self.buffer[..]);
+        let hasher: Box<dyn Hasher> = match self.algo {
+            Algorithm::Md5 => Box::new(Md5::from_state(chain, length, buffer)?),
+            Algorithm::Sha1 => Box::new(Sha1::from_state(chain, length, buffer)?),
+            Algorithm::Sha256 => Box::new(Sha256::from_state(chain, length, buffer)?),
+            Algorithm::Sha384 => Box::new(Sha384::from_state(chain, length, buffer)?),
+            Algorithm::Sha512 => Box::new(Sha512::from_state(chain, length, buffer)?),
+            Algorithm::Sha3_256 => Box::new(Sha3_256::from_state(chain, length, buffer)?),
+            Algorithm::Blake3 => Box::new(Blake3::from_state(chain, length, buffer)?),
+        };
+        Some(hasher)
+    }
+}
+
+/// A cursor over serialized bytes.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(Error::InvalidState);
+        }
+
+        let (ret, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Checksum, ChecksumBuilder};
+
+    #[test]
+    fn test_resume() {
+        let data = (0..5000u32).map(|i| i as u8).collect::<Vec<_>>();
+
+        for algo in [
+            Algorithm::Md5,
+            Algorithm::Sha1,
+            Algorithm::Sha256,
+            Algorithm::Sha384,
+            Algorithm::Sha512,
+            Algorithm::Sha3_256,
+            Algorithm::Blake3,
+        ] {
+            let mut checksum = ChecksumBuilder::new().algo(algo).build();
+            checksum.consume(&data);
+            let expected = checksum.finalize();
+
+            for split in [0, 1, 64, 200, 1024, 3073, 5000] {
+                let mut checksum = ChecksumBuilder::new().algo(algo).resumable().build();
+                checksum.consume(&data[..split]);
+                let state = checksum.state().unwrap();
+                assert_eq!(state.consumed(), split as u64);
+
+                let state = HashState::from_bytes(&state.to_bytes()).unwrap();
+                let mut checksum = Checksum::resume(&state);
+                checksum.consume(&data[split..]);
+                assert_eq!(checksum.finalize(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_state() {
+        let mut checksum = ChecksumBuilder::new().resumable().build();
+        checksum.consume(b"hello");
+        let bytes = checksum.state().unwrap().to_bytes();
+
+        assert_eq!(
+            HashState::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(Error::InvalidState)
+        );
+        assert_eq!(HashState::from_bytes(b""), Err(Error::InvalidState));
+
+        // states of the previous format version
+        let mut old = bytes.clone();
+        old[3] = 1;
+        assert_eq!(HashState::from_bytes(&old), Err(Error::InvalidState));
+
+        // buffered data mismatching the consumed length
+        let mut bytes = bytes;
+        let len = bytes.len();
+        bytes[len - 6] = 4;
+        bytes.pop();
+        assert_eq!(HashState::from_bytes(&bytes), Err(Error::InvalidState));
+    }
+}
@@ -0,0 +1,32 @@
+use clap::{ArgAction, Parser};
+use libscoop::Session;
+
+use crate::Result;
+
+use super::checkver::{self, Action};
+
+/// Update manifests of packages with their autoupdate
+#[derive(Debug, Parser)]
+#[clap(arg_required_else_help = true)]
+pub struct Args {
+    /// The package(s) to update, as 'app', 'bucket/app', 'bucket/*' or '*'
+    #[arg(required = true, action = ArgAction::Append)]
+    query: Vec<String>,
+    /// Update to the given version instead of the upstream version
+    #[arg(long, value_name = "VERSION")]
+    version: Option<String>,
+    /// Output the result in JSON
+    #[arg(short = 'j', long, action = ArgAction::SetTrue)]
+    json: bool,
+    /// Commit updated manifests to their bucket repositories
+    #[arg(short = 'c', long, action = ArgAction::SetTrue)]
+    commit: bool,
+}
+
+pub fn execute(args: Args, session: &Session) -> Result<()> {
+    let action = match args.version {
+        Some(version) => Action::UpdateTo(version),
+        None => Action::Update,
+    };
+    checkver::run(session, &args.query, action, args.commit, args.json)
+}
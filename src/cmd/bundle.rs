@@ -0,0 +1,70 @@
+use clap::{ArgAction, Parser, Subcommand};
+use crossterm::style::Stylize;
+use libscoop::{operation, Session, SyncOption};
+use std::path::PathBuf;
+
+use crate::Result;
+
+use super::install;
+
+/// Offline bundle management
+#[derive(Debug, Parser)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Create an offline bundle of package(s) and their dependencies
+    #[clap(arg_required_else_help = true)]
+    Create {
+        /// The package(s) to bundle
+        #[arg(required = true, action = ArgAction::Append)]
+        package: Vec<String>,
+        /// Path of the bundle file to write
+        #[arg(short, long, default_value = "bundle.tar")]
+        output: PathBuf,
+    },
+    /// Install package(s) from an offline bundle
+    #[clap(arg_required_else_help = true)]
+    Install {
+        /// Path of the bundle file
+        file: PathBuf,
+        /// Assume yes to all prompts and run non-interactively
+        #[arg(short = 'y', long, action = ArgAction::SetTrue)]
+        assume_yes: bool,
+    },
+}
+
+pub fn execute(args: Args, session: &Session) -> Result<()> {
+    match args.command {
+        Command::Create { package, output } => {
+            let queries = package.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            let lock = operation::bundle_create(session, queries, &output)?;
+
+            for pkg in lock.packages() {
+                println!("{}/{} {}", pkg.bucket().green(), pkg.name(), pkg.version());
+            }
+
+            println!(
+                "Bundled {} package(s) into {}",
+                lock.packages().len(),
+                output.display()
+            );
+
+            Ok(())
+        }
+        Command::Install { file, assume_yes } => {
+            let mut options = vec![];
+
+            if assume_yes {
+                options.push(SyncOption::AssumeYes);
+            }
+
+            install::sync_with_ui(session, || {
+                operation::bundle_install(session, &file, options)
+            })
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use clap::{ArgAction, Parser, Subcommand};
-use libscoop::{operation, Session};
+use crossterm::style::Stylize;
+use libscoop::{operation, CacheState, Session};
 
 use crate::{util, Result};
 
@@ -28,6 +29,19 @@ pub enum Command {
         #[arg(short, long, action = ArgAction::SetTrue, conflicts_with = "query")]
         all: bool,
     },
+    /// Prune download caches by the retention policy
+    ///
+    /// The policy is defined by the `cache_max_age`, `cache_keep_versions`
+    /// and `cache_max_size` configs.
+    Prune,
+    /// Verify download caches against their manifests
+    Verify {
+        /// Verify caches matching the query
+        query: Option<String>,
+        /// Remove corrupt, orphaned and outdated caches
+        #[arg(short, long, action = ArgAction::SetTrue)]
+        remove: bool,
+    },
 }
 
 pub fn execute(args: Args, session: &Session) -> Result<()> {
@@ -84,6 +98,50 @@ pub fn execute(args: Args, session: &Session) -> Result<()> {
                 }
             }
 
+            Ok(())
+        }
+        Command::Prune => {
+            let files = operation::cache_prune(session)?;
+            let total_count = files.len();
+
+            for f in files.into_iter() {
+                println!("{:>8} {}", "Removed:", f.file_name());
+            }
+
+            println!("{:>8} {} files removed", "Total:", total_count);
+
+            Ok(())
+        }
+        Command::Verify { query, remove } => {
+            let query = query.unwrap_or("*".to_string());
+            let files = operation::cache_verify(session, query.as_str(), remove)?;
+            let total_count = files.len();
+            let mut bad_count = 0;
+
+            for (f, state) in files.into_iter() {
+                let state = match state {
+                    CacheState::Valid => continue,
+                    CacheState::Corrupt => format!("{:>8}", "corrupt").red(),
+                    CacheState::Orphaned => format!("{:>8}", "orphaned").yellow(),
+                    CacheState::Outdated => format!("{:>8}", "outdated").yellow(),
+                    _ => format!("{:>8}", "unknown").dark_grey(),
+                };
+                bad_count += 1;
+
+                println!("{} {}", state, f.file_name());
+            }
+
+            match remove {
+                true => println!(
+                    "{:>8} {} files, {} removed",
+                    "Total:", total_count, bad_count
+                ),
+                false => println!(
+                    "{:>8} {} files, {} invalid",
+                    "Total:", total_count, bad_count
+                ),
+            }
+
             Ok(())
         }
     }
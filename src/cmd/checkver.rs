@@ -0,0 +1,233 @@
+use clap::{ArgAction, Parser};
+use crossterm::style::Stylize;
+use libscoop::{operation, QueryOption, Session};
+use serde_json::json;
+use std::cmp::Ordering;
+
+use crate::Result;
+
+/// Check for upstream versions of packages
+#[derive(Debug, Parser)]
+pub struct Args {
+    /// The package(s) to check, as 'app', 'bucket/app', 'bucket/*' or '*'
+    #[arg(action = ArgAction::Append)]
+    query: Vec<String>,
+    /// Output the result in JSON
+    #[arg(short = 'j', long, action = ArgAction::SetTrue)]
+    json: bool,
+    /// Update outdated manifests with their autoupdate
+    #[arg(short = 'u', long, action = ArgAction::SetTrue)]
+    update: bool,
+    /// Commit updated manifests to their bucket repositories
+    #[arg(short = 'c', long, action = ArgAction::SetTrue, requires = "update")]
+    commit: bool,
+}
+
+/// Checked state of a package.
+enum Status {
+    UpToDate,
+    Outdated,
+    Updated,
+    Failed(String),
+}
+
+impl Status {
+    fn as_str(&self) -> &str {
+        match self {
+            Status::UpToDate => "ok",
+            Status::Outdated => "outdated",
+            Status::Updated => "updated",
+            Status::Failed(_) => "error",
+        }
+    }
+}
+
+/// Checkver result of a package.
+struct Row<'a> {
+    name: &'a str,
+    bucket: &'a str,
+    current: &'a str,
+    latest: Option<String>,
+    status: Status,
+}
+
+/// What to do with the checked packages.
+pub(super) enum Action {
+    /// Only report upstream versions.
+    Check,
+    /// Update manifests outdated by their upstream versions.
+    Update,
+    /// Update manifests to the given version.
+    UpdateTo(String),
+}
+
+pub fn execute(args: Args, session: &Session) -> Result<()> {
+    let action = match args.update {
+        true => Action::Update,
+        false => Action::Check,
+    };
+    run(session, &args.query, action, args.commit, args.json)
+}
+
+/// Check upstream versions of the packages matching `query`, and update their
+/// manifests according to `action`.
+///
+/// Packages are checked concurrently, while updated manifests are committed
+/// one by one afterwards if `commit` is set.
+pub(super) fn run(
+    session: &Session,
+    query: &[String],
+    action: Action,
+    commit: bool,
+    json: bool,
+) -> Result<()> {
+    // `bucket/*` queries all packages of the bucket
+    let mut queries = vec![];
+    let mut buckets = vec![];
+    for query in query.iter() {
+        match query.strip_suffix("/*") {
+            Some(bucket) => buckets.push(bucket),
+            None => queries.push(query.as_str()),
+        }
+    }
+    if queries.is_empty() || !buckets.is_empty() {
+        queries.push("*");
+    }
+
+    let options = vec![QueryOption::Explicit];
+    let packages = operation::package_query(session, queries, options, false)?
+        .into_iter()
+        .filter(|p| p.version() != "nightly")
+        .filter(|p| match action {
+            Action::UpdateTo(_) => true,
+            _ => p.manifest().checkver().is_some(),
+        })
+        .filter(|p| {
+            let wildcard = query.iter().any(|q| q == "*") || query.is_empty();
+            wildcard
+                || buckets.contains(&p.bucket())
+                || query.iter().any(|q| q == p.name() || *q == p.ident())
+        })
+        .collect::<Vec<_>>();
+
+    if packages.is_empty() {
+        match action {
+            Action::UpdateTo(_) => eprintln!("Could not find any matching package."),
+            _ => eprintln!("Could not find any package with checkver."),
+        }
+        return Ok(());
+    }
+
+    let checked = operation::package_concurrent_map(session, packages, |session, package| {
+        let current = package.version();
+        let (latest, captures) = match &action {
+            Action::UpdateTo(version) => (version.to_owned(), None),
+            _ => match operation::package_checkver(session, package) {
+                Ok(matched) => (matched.version().to_owned(), Some(matched)),
+                Err(e) => return (None, Status::Failed(e.to_string())),
+            },
+        };
+
+        let outdated = match &action {
+            Action::UpdateTo(_) => latest != current,
+            _ => operation::compare_versions(&latest, current) == Ordering::Greater,
+        };
+        let status = match (outdated, &action) {
+            (false, _) => Status::UpToDate,
+            (true, Action::Check) => Status::Outdated,
+            (true, _) => {
+                let captures = captures.as_ref().map(|m| m.captures());
+                match operation::package_autoupdate(session, package, &latest, captures) {
+                    Ok(..) => Status::Updated,
+                    Err(e) => Status::Failed(e.to_string()),
+                }
+            }
+        };
+        (Some(latest), status)
+    });
+
+    let (packages, results): (Vec<_>, Vec<_>) = checked.into_iter().unzip();
+    let mut rows = vec![];
+    for (package, (latest, mut status)) in packages.iter().zip(results) {
+        if commit && matches!(status, Status::Updated) {
+            let version = latest.as_deref().unwrap_or_default();
+            let message = format!("{}: Update to version {}", package.name(), version);
+            if let Err(e) = operation::package_commit(package, &message) {
+                status = Status::Failed(e.to_string());
+            }
+        }
+
+        rows.push(Row {
+            name: package.name(),
+            bucket: package.bucket(),
+            current: package.version(),
+            latest,
+            status,
+        });
+    }
+
+    if json {
+        let output = rows
+            .iter()
+            .map(|row| {
+                let error = match &row.status {
+                    Status::Failed(msg) => Some(msg.as_str()),
+                    _ => None,
+                };
+                json!({
+                    "name": row.name,
+                    "bucket": row.bucket,
+                    "current": row.current,
+                    "latest": row.latest,
+                    "status": row.status.as_str(),
+                    "error": error,
+                })
+            })
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    let headers = ["Name", "Bucket", "Current", "Latest", "Status"];
+    let cells = rows
+        .iter()
+        .map(|row| {
+            [
+                row.name,
+                row.bucket,
+                row.current,
+                row.latest.as_deref().unwrap_or("-"),
+                row.status.as_str(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let mut widths = headers.map(|h| h.len());
+    for cell in cells.iter() {
+        for (width, value) in widths.iter_mut().zip(cell.iter()) {
+            *width = (*width).max(value.len());
+        }
+    }
+
+    let line = |values: [&str; 5]| {
+        values
+            .iter()
+            .zip(widths.iter())
+            .map(|(v, w)| format!("{:<w$}", v, w = w))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_owned()
+    };
+    println!("{}", line(headers).bold());
+    for (row, cell) in rows.iter().zip(cells) {
+        let output = line(cell);
+        match &row.status {
+            Status::UpToDate => println!("{}", output),
+            Status::Outdated => println!("{}", output.yellow()),
+            Status::Updated => println!("{}", output.green()),
+            Status::Failed(msg) => println!("{} ({})", output.red(), msg),
+        }
+    }
+
+    Ok(())
+}
@@ -48,6 +48,9 @@ pub struct Args {
     /// Skip package integrity check
     #[arg(long, action = ArgAction::SetTrue)]
     no_hash_check: bool,
+    /// Limit the download rate per second (e.g. 512K, 2M)
+    #[arg(long, value_name = "RATE", value_parser = util::parse_size)]
+    max_rate: Option<u64>,
 }
 
 pub fn execute(args: Args, session: &Session) -> Result<()> {
@@ -93,6 +96,21 @@ pub fn execute(args: Args, session: &Session) -> Result<()> {
         options.push(SyncOption::NoHashCheck);
     }
 
+    if let Some(rate) = args.max_rate {
+        options.push(SyncOption::MaxDownloadRate(rate));
+    }
+
+    let queries = args.package.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    sync_with_ui(session, || {
+        operation::package_sync(session, queries, options)
+    })
+}
+
+/// Run a package sync operation `f` with the sync progress UI.
+pub(super) fn sync_with_ui<F>(session: &Session, f: F) -> Result<()>
+where
+    F: FnOnce() -> std::result::Result<(), libscoop::Error>,
+{
     let rx = session.event_bus().receiver();
     let tx = session.event_bus().sender();
 
@@ -107,15 +125,8 @@ pub fn execute(args: Args, session: &Session) -> Result<()> {
                 Event::PackageResolveStart => println!("Resolving packages..."),
                 Event::PackageDownloadSizingStart => println!("Calculating download size..."),
                 Event::PackageDownloadStart => println!("Downloading packages..."),
-                Event::PackageDownloadProgress(ctx) => {
-                    let ident = ctx.ident.to_owned();
-                    let url = ctx.url.to_owned();
-                    let filename = ctx.filename.to_owned();
-                    let dltotal = ctx.dltotal;
-                    let dlnow = ctx.dlnow;
-
-                    dlprogress.update(ident, url, filename, dltotal, dlnow);
-                }
+                Event::PackageDownloadProgress(ctx) => dlprogress.update(&ctx),
+                Event::PackageDownloadOverallProgress(ctx) => dlprogress.update_overall(&ctx),
                 Event::PackageDownloadDone => {}
                 Event::PackageIntegrityCheckStart => println!("Checking package integrity..."),
                 Event::PackageIntegrityCheckProgress(ctx) => {
@@ -253,8 +264,7 @@ pub fn execute(args: Args, session: &Session) -> Result<()> {
         }
     });
 
-    let queries = args.package.iter().map(|s| s.as_str()).collect::<Vec<_>>();
-    operation::package_sync(session, queries, options)?;
+    f()?;
 
     handle.join().unwrap();
 
@@ -0,0 +1,96 @@
+use anyhow::anyhow;
+use clap::{ArgAction, Parser};
+use crossterm::style::Stylize;
+use libscoop::{operation, Session, Severity};
+use serde_json::json;
+use std::path::Path;
+
+use crate::Result;
+
+/// Lint manifests of packages
+#[derive(Debug, Parser)]
+#[clap(arg_required_else_help = true)]
+pub struct Args {
+    /// Path(s) to manifest files or bucket directories, or bucket name(s)
+    #[arg(required = true, action = ArgAction::Append)]
+    target: Vec<String>,
+    /// Output the diagnostics in JSON
+    #[arg(short = 'j', long, action = ArgAction::SetTrue)]
+    json: bool,
+}
+
+pub fn execute(args: Args, session: &Session) -> Result<()> {
+    let mut results = vec![];
+    for target in args.target.iter() {
+        let path = Path::new(target);
+        if path.is_file() {
+            let diagnostics = operation::manifest_lint(path)?;
+            results.push((path.to_path_buf(), diagnostics));
+        } else {
+            results.extend(operation::bucket_lint(session, target)?);
+        }
+    }
+
+    let count = |severity: Severity| {
+        results
+            .iter()
+            .flat_map(|(_, d)| d.iter())
+            .filter(|d| d.severity() == severity)
+            .count()
+    };
+    let errors = count(Severity::Error);
+    let warnings = count(Severity::Warning);
+
+    if args.json {
+        let output = results
+            .iter()
+            .filter(|(_, diagnostics)| !diagnostics.is_empty())
+            .map(|(path, diagnostics)| {
+                json!({
+                    "path": path.display().to_string(),
+                    "diagnostics": diagnostics,
+                })
+            })
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        for (path, diagnostics) in results.iter() {
+            if diagnostics.is_empty() {
+                continue;
+            }
+
+            println!("{}", path.display().to_string().bold());
+            for diagnostic in diagnostics {
+                let severity = match diagnostic.severity() {
+                    Severity::Error => "error".red(),
+                    Severity::Warning => "warning".yellow(),
+                };
+                let pointer = match diagnostic.pointer() {
+                    "" => "/",
+                    pointer => pointer,
+                };
+                println!(
+                    "  {}: {} ({})",
+                    severity,
+                    diagnostic.message(),
+                    pointer.cyan()
+                );
+                if let Some(fix) = diagnostic.fix() {
+                    println!("    {} {}", "fix:".green(), fix);
+                }
+            }
+        }
+
+        println!(
+            "Linted {} manifest(s): {} error(s), {} warning(s).",
+            results.len(),
+            errors,
+            warnings
+        );
+    }
+
+    match errors {
+        0 => Ok(()),
+        _ => Err(anyhow!("found {} error(s) in manifests", errors)),
+    }
+}
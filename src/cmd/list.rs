@@ -47,9 +47,10 @@ pub fn execute(args: Args, session: &Session) -> Result<()> {
                     continue;
                 }
 
-                let upgradable = pkg.upgradable_version();
-                if args.upgradable && upgradable.is_some() {
-                    output.push_str(format!(" -> {}", upgradable.unwrap().blue()).as_str());
+                if let Some(upgradable) = pkg.upgradable_version() {
+                    if args.upgradable {
+                        output.push_str(format!(" -> {}", upgradable.blue()).as_str());
+                    }
                 }
 
                 if held {
@@ -5,9 +5,12 @@ use tracing_subscriber::{
     filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter,
 };
 
+mod autoupdate;
 mod bucket;
+mod bundle;
 mod cache;
 mod cat;
+mod checkver;
 mod cleanup;
 mod completions;
 mod config;
@@ -15,6 +18,7 @@ mod hold;
 mod home;
 mod info;
 mod install;
+mod lint;
 mod list;
 mod search;
 mod unhold;
@@ -52,9 +56,12 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 pub enum Command {
+    Autoupdate(autoupdate::Args),
     Bucket(bucket::Args),
+    Bundle(bundle::Args),
     Cache(cache::Args),
     Cat(cat::Args),
+    Checkver(checkver::Args),
     Cleanup(cleanup::Args),
     Completions(completions::Args),
     Config(config::Args),
@@ -63,6 +70,7 @@ pub enum Command {
     Info(info::Args),
     #[clap(alias = "i")]
     Install(install::Args),
+    Lint(lint::Args),
     List(list::Args),
     #[clap(alias = "s")]
     Search(search::Args),
@@ -84,9 +92,12 @@ pub fn start() -> Result<()> {
     let _ = session.set_user_agent(&user_agent);
 
     match args.command {
+        Command::Autoupdate(args) => autoupdate::execute(args, &session),
         Command::Bucket(args) => bucket::execute(args, &session),
+        Command::Bundle(args) => bundle::execute(args, &session),
         Command::Cache(args) => cache::execute(args, &session),
         Command::Cat(args) => cat::execute(args, &session),
+        Command::Checkver(args) => checkver::execute(args, &session),
         Command::Cleanup(args) => cleanup::execute(args, &session),
         Command::Completions(args) => completions::execute(args),
         Command::Config(args) => config::execute(args, &session),
@@ -94,6 +105,7 @@ pub fn start() -> Result<()> {
         Command::Home(args) => home::execute(args, &session),
         Command::Info(args) => info::execute(args, &session),
         Command::Install(args) => install::execute(args, &session),
+        Command::Lint(args) => lint::execute(args, &session),
         Command::List(args) => list::execute(args, &session),
         Command::Search(args) => search::execute(args, &session),
         Command::Unhold(args) => unhold::execute(args, &session),
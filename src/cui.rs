@@ -4,7 +4,8 @@ use crossterm::{
     terminal::{Clear, ClearType},
     ExecutableCommand,
 };
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use indicatif::{HumanBytes, HumanDuration, MultiProgress, ProgressBar, ProgressStyle};
+use libscoop::{DownloadState, PackageDownloadOverallContext, PackageDownloadProgressContext};
 use std::{
     collections::HashMap,
     io::{stdout, Write},
@@ -12,69 +13,96 @@ use std::{
 
 static BAR_FMT: &str = " {wide_msg} {total_bytes:>12} [{bar:>20}] {percent:>3}%";
 
-/// Multiple progress bars with own context.
+/// Multiple progress bars of package downloads.
 pub struct MultiProgressUI {
     mp: MultiProgress,
-    ctx: HashMap<String, HashMap<String, (u64, u64)>>,
-    bars: HashMap<String, ProgressBar>,
+    bars: HashMap<(String, String), ProgressBar>,
+    overall: Option<ProgressBar>,
 }
 
 impl MultiProgressUI {
     pub fn new() -> MultiProgressUI {
         MultiProgressUI {
             mp: MultiProgress::new(),
-            ctx: HashMap::new(),
             bars: HashMap::new(),
+            overall: None,
         }
     }
 
-    /// Update progress bar with the given context.
-    pub fn update(&mut self, ident: String, url: String, _: String, dltotal: u64, dlnow: u64) {
-        if dltotal == 0 {
+    /// Update the progress bar of a file with the given context.
+    pub fn update(&mut self, ctx: &PackageDownloadProgressContext) {
+        if ctx.dltotal == 0 {
             return;
         }
 
-        let mut total = 0;
-        let mut now = 0;
-
-        self.ctx
-            .entry(ident.clone())
-            .and_modify(|inner| {
-                inner.insert(url.clone(), (dltotal, dlnow));
-            })
-            .or_insert({
-                let mut ctx = HashMap::new();
-                ctx.insert(url.clone(), (dltotal, dlnow));
-                ctx
-            })
-            .iter()
-            .for_each(|(_, (t, n))| {
-                total += t;
-                now += n;
-            });
-
-        self.bars
-            .entry(ident.clone())
-            .and_modify(|bar| {
-                bar.set_length(total);
-                bar.set_position(now);
-
-                if total == now {
-                    bar.finish();
-                }
-            })
-            .or_insert_with(|| {
-                let bar = self.mp.add(ProgressBar::new(total));
-                bar.set_message(ident.clone());
-                bar.set_position(0);
-                bar.set_style(
-                    ProgressStyle::default_bar()
-                        .template(BAR_FMT)
-                        .unwrap()
-                        .progress_chars("#> "),
-                );
-                bar
-            });
+        let mut msg = ctx.ident.clone();
+        match ctx.state {
+            DownloadState::Retrying {
+                attempt, retries, ..
+            } => msg.push_str(&format!(
+                " {}",
+                format!("(retry {}/{})", attempt, retries).yellow()
+            )),
+            DownloadState::Resumed => msg.push_str(&format!(" {}", "(resumed)".yellow())),
+            _ => {}
+        }
+        if ctx.rate > 0 && ctx.state != DownloadState::Done {
+            msg.push_str(&format!(" {}/s", HumanBytes(ctx.rate)));
+        }
+
+        let key = (ctx.ident.clone(), ctx.url.clone());
+        let bar = self.bars.entry(key).or_insert_with(|| {
+            let bar = ProgressBar::new(ctx.dltotal);
+            let bar = match self.overall.as_ref() {
+                Some(overall) => self.mp.insert_before(overall, bar),
+                None => self.mp.add(bar),
+            };
+            bar.set_style(
+                ProgressStyle::default_bar()
+                    .template(BAR_FMT)
+                    .unwrap()
+                    .progress_chars("#> "),
+            );
+            bar
+        });
+
+        bar.set_message(msg);
+        bar.set_length(ctx.dltotal);
+        bar.set_position(ctx.dlnow);
+
+        if ctx.state == DownloadState::Done {
+            bar.finish();
+        }
+    }
+
+    /// Update the overall progress bar with the given context.
+    pub fn update_overall(&mut self, ctx: &PackageDownloadOverallContext) {
+        let bar = self.overall.get_or_insert_with(|| {
+            let bar = self.mp.add(ProgressBar::new(ctx.dltotal));
+            bar.set_style(
+                ProgressStyle::default_bar()
+                    .template(BAR_FMT)
+                    .unwrap()
+                    .progress_chars("#> "),
+            );
+            bar
+        });
+
+        let mut msg = format!("Total ({}/{})", ctx.files_done, ctx.files_total);
+        if ctx.rate > 0 {
+            msg.push_str(&format!(" {}/s", HumanBytes(ctx.rate)));
+        }
+        if let Some(eta) = ctx.eta.filter(|_| ctx.files_done < ctx.files_total) {
+            msg.push_str(&format!(" ETA {}", HumanDuration(eta)));
+        }
+
+        bar.set_message(msg);
+        bar.set_length(ctx.dltotal);
+        bar.set_position(ctx.dlnow);
+
+        if ctx.files_done == ctx.files_total {
+            bar.finish();
+        }
     }
 }
 
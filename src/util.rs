@@ -36,3 +36,19 @@ pub fn humansize(length: u64, with_unit: bool) -> String {
         flength.to_string()
     }
 }
+
+/// Parse a size with an optional K/M/G suffix, e.g. `512K`, into bytes.
+pub fn parse_size(s: &str) -> std::result::Result<u64, String> {
+    let s = s.trim();
+    let (num, unit) = match s.char_indices().last() {
+        Some((idx, 'k' | 'K')) => (&s[..idx], 1024u64),
+        Some((idx, 'm' | 'M')) => (&s[..idx], 1024 * 1024),
+        Some((idx, 'g' | 'G')) => (&s[..idx], 1024 * 1024 * 1024),
+        _ => (s, 1),
+    };
+
+    match num.parse::<u64>().ok().and_then(|n| n.checked_mul(unit)) {
+        Some(n) if n > 0 => Ok(n),
+        _ => Err(format!("invalid size '{}'", s)),
+    }
+}